@@ -2,23 +2,32 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use chrono::Local;
+use font_kit::family_name::FamilyName;
+use font_kit::properties::Properties;
 use font_kit::source::SystemSource;
 use hex;
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
-use std::io::{Read, Write};
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use tauri::{
-    http::Response,
-    menu::{Menu, MenuItem},
+    http::{
+        header::{HeaderValue, CONTENT_LENGTH},
+        Method, Request, Response,
+    },
+    menu::{CheckMenuItem, Menu, MenuItem},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
-    AppHandle, Manager, PhysicalPosition, PhysicalSize, State, WebviewWindow, WindowEvent,
+    AppHandle, Emitter, Manager, PhysicalPosition, PhysicalSize, RunEvent, State, WebviewWindow,
+    WindowEvent,
 };
 use tauri_plugin_autostart::MacosLauncher;
 use tauri_plugin_global_shortcut::{Code, GlobalShortcutExt, Modifiers, Shortcut, ShortcutState};
+use tauri_plugin_shell::ShellExt;
 use tauri_plugin_store::StoreExt;
 use uuid::Uuid;
 use walkdir::WalkDir;
@@ -27,19 +36,68 @@ use zip::ZipArchive;
 
 // App state for portable mode paths
 struct AppState {
-    #[allow(dead_code)]
     data_path: PathBuf,
     images_path: PathBuf,
+    // --ephemeral: 不写磁盘，图片只保存在内存中，退出即丢弃
+    ephemeral: bool,
+    memory_images: HashMap<String, Vec<u8>>,
+}
+
+fn is_ephemeral(app: &AppHandle) -> bool {
+    app.state::<Mutex<AppState>>().lock().unwrap().ephemeral
+}
+
+// 保存配置（临时模式下只保留在内存中）
+fn persist_store(
+    app: &AppHandle,
+    store: &tauri_plugin_store::Store<tauri::Wry>,
+) -> Result<(), String> {
+    if is_ephemeral(app) {
+        return Ok(());
+    }
+    store.save().map_err(|e| e.to_string())
 }
 
 static SHORTCUT_HELD: AtomicBool = AtomicBool::new(false);
+// 取消正在进行的备份
+static BACKUP_CANCELLED: AtomicBool = AtomicBool::new(false);
+static BACKUP_RUNNING: AtomicBool = AtomicBool::new(false);
+
+// 临时置顶的计时任务；再次调用时取消旧任务，重新计时
+static TEMPORARY_ON_TOP_TASK: Mutex<Option<tauri::async_runtime::JoinHandle<()>>> =
+    Mutex::new(None);
+
+// 窗口位置/尺寸的延迟保存任务；拖动或缩放停止 500ms 后才写入 config.json
+static WINDOW_BOUNDS_SAVE_TASK: Mutex<Option<tauri::async_runtime::JoinHandle<()>>> =
+    Mutex::new(None);
+
+// 待写入的 data.json（合并频繁的保存请求，按间隔落盘）
+static PENDING_DATA: Mutex<Option<String>> = Mutex::new(None);
+static DATA_SAVE_SCHEDULED: AtomicBool = AtomicBool::new(false);
 
 // Settings structure matching electron-store schema
 #[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct Settings {
     pub auto_launch: bool,
     pub always_on_top: bool,
+    pub save_interval_ms: u64,
+    pub offline_mode: bool,
+    pub shortcut: String,
+    // 点击关闭按钮时隐藏到托盘（false 时直接退出）
+    pub close_to_tray: bool,
+    // 切换置顶的全局快捷键，None 表示不注册
+    pub always_on_top_shortcut: Option<String>,
+    // 窗口不透明度（0.2 - 1.0）
+    pub window_opacity: f64,
+    // 单张图片大小上限（字节），超出时拒绝保存
+    pub max_image_bytes: u64,
+    // 计算图片 hash 的线程数上限，0 表示使用全部核心
+    pub hash_threads: u32,
+    // 监听 images 目录的外部变化（默认关闭）
+    pub watch_images: bool,
+    // 后端写出的 JSON（data.json、恢复快照、备份/导出）格式化并按键名排序
+    pub pretty_json: bool,
 }
 
 impl Default for Settings {
@@ -47,6 +105,16 @@ impl Default for Settings {
         Self {
             auto_launch: false,
             always_on_top: false,
+            save_interval_ms: 500,
+            offline_mode: false,
+            shortcut: DEFAULT_TOGGLE_SHORTCUT.to_string(),
+            close_to_tray: true,
+            always_on_top_shortcut: None,
+            window_opacity: 1.0,
+            max_image_bytes: DEFAULT_MAX_IMAGE_BYTES,
+            hash_threads: 0,
+            watch_images: false,
+            pretty_json: false,
         }
     }
 }
@@ -58,6 +126,11 @@ pub struct WindowBounds {
     pub y: Option<i32>,
     pub width: u32,
     pub height: u32,
+    // 最大化/全屏时 x/y/width/height 仍保存还原后的窗口尺寸
+    #[serde(default)]
+    pub maximized: bool,
+    #[serde(default)]
+    pub fullscreen: bool,
 }
 
 impl Default for WindowBounds {
@@ -67,6 +140,8 @@ impl Default for WindowBounds {
             y: None,
             width: 800,
             height: 600,
+            maximized: false,
+            fullscreen: false,
         }
     }
 }
@@ -74,11 +149,58 @@ impl Default for WindowBounds {
 // Backup settings structure
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
+#[serde(default)]
 pub struct BackupSettings {
     pub backup_directory: Option<String>,
     pub max_backups: u32,
     pub auto_backup_enabled: bool,
     pub auto_backup_interval: u32,
+    // 附属数据是否纳入备份
+    pub include_image_metadata: bool,
+    pub include_labels: bool,
+    pub include_thumbnails: bool,
+    // 自动备份失败时的重试次数
+    pub auto_backup_retries: u32,
+    // 使用口令加密备份文件
+    pub encrypt_backups: bool,
+    // 除数量外的清理条件：最长保留天数、备份总大小上限
+    pub max_age_days: Option<u32>,
+    pub max_total_bytes: Option<u64>,
+    // 文件名中的机器标识（多台电脑备份到同一目录时区分），如 litepad_backup_laptop_20250101_120000.zip
+    pub filename_prefix: Option<String>,
+    pub hostname_in_filename: bool,
+    // 每日定时备份时间（本地时间 HH:MM），设置后取代按间隔备份
+    pub auto_backup_time: Option<String>,
+    // zip 压缩级别：图片大多已压缩，none 速度最快
+    pub compression: BackupCompression,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupCompression {
+    None,
+    Fast,
+    #[default]
+    Balanced,
+    Best,
+}
+
+impl BackupCompression {
+    fn file_options(self) -> SimpleFileOptions {
+        let options = SimpleFileOptions::default();
+        match self {
+            BackupCompression::None => options.compression_method(zip::CompressionMethod::Stored),
+            BackupCompression::Fast => options
+                .compression_method(zip::CompressionMethod::Deflated)
+                .compression_level(Some(1)),
+            BackupCompression::Balanced => {
+                options.compression_method(zip::CompressionMethod::Deflated)
+            }
+            BackupCompression::Best => options
+                .compression_method(zip::CompressionMethod::Deflated)
+                .compression_level(Some(9)),
+        }
+    }
 }
 
 impl Default for BackupSettings {
@@ -88,10 +210,46 @@ impl Default for BackupSettings {
             max_backups: 5,
             auto_backup_enabled: false,
             auto_backup_interval: 30,
+            include_image_metadata: true,
+            include_labels: true,
+            include_thumbnails: false,
+            auto_backup_retries: 3,
+            encrypt_backups: false,
+            max_age_days: None,
+            max_total_bytes: None,
+            filename_prefix: None,
+            hostname_in_filename: false,
+            auto_backup_time: None,
+            compression: BackupCompression::default(),
         }
     }
 }
 
+// 附属数据（相对 data 目录），备份时按设置选择性打包
+const IMAGE_META_DIR: &str = "image_meta";
+const LABELS_FILE: &str = "labels.json";
+const THUMBS_DIR: &str = "thumbs";
+
+fn backup_sidecars(settings: &BackupSettings) -> Vec<&'static str> {
+    let mut sidecars = Vec::new();
+    if settings.include_image_metadata {
+        sidecars.push(IMAGE_META_DIR);
+    }
+    if settings.include_labels {
+        sidecars.push(LABELS_FILE);
+    }
+    if settings.include_thumbnails {
+        sidecars.push(THUMBS_DIR);
+    }
+    sidecars
+}
+
+fn is_sidecar_entry(name: &str) -> bool {
+    name == LABELS_FILE
+        || name.starts_with(&format!("{}/", IMAGE_META_DIR))
+        || name.starts_with(&format!("{}/", THUMBS_DIR))
+}
+
 // Backup info for listing backups
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -99,6 +257,13 @@ pub struct BackupInfo {
     pub filename: String,
     pub created_at: i64,
     pub size: u64,
+    pub encrypted: bool,
+    // 文件名中的机器标识，旧格式备份为 None
+    pub tag: Option<String>,
+    // 用户备注（backups.json）
+    pub label: Option<String>,
+    // 仅在 get_backup_list(validate) 时检查；加密备份无法检查，为 None
+    pub readable: Option<bool>,
 }
 
 // Path validation result
@@ -121,6 +286,31 @@ pub struct UpdateInfo {
     pub release_url: Option<String>,
     pub release_notes: Option<String>,
     pub published_at: Option<String>,
+    // 与当前平台匹配的安装包；为 None 时前端可从 assets 中手动选择
+    pub asset: Option<UpdateAsset>,
+    pub assets: Vec<UpdateAsset>,
+    // 检查时间（毫秒）以及是否来自缓存
+    #[serde(default)]
+    pub checked_at: i64,
+    #[serde(default)]
+    pub from_cache: bool,
+}
+
+// 上一次更新检查结果（store 中的 updateCache）
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateCache {
+    info: UpdateInfo,
+    include_prereleases: bool,
+}
+
+// Release 附件信息
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateAsset {
+    pub name: String,
+    pub download_url: String,
+    pub size: u64,
 }
 
 // GitHub API Release Response (只需要部分字段)
@@ -129,33 +319,142 @@ struct GitHubRelease {
     tag_name: String,
     html_url: String,
     body: Option<String>,
-    published_at: String,
+    #[serde(default)]
+    published_at: Option<String>,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    assets: Vec<GitHubAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubAsset {
+    name: String,
+    browser_download_url: String,
+    size: u64,
+}
+
+impl From<&GitHubAsset> for UpdateAsset {
+    fn from(asset: &GitHubAsset) -> Self {
+        Self {
+            name: asset.name.clone(),
+            download_url: asset.browser_download_url.clone(),
+            size: asset.size,
+        }
+    }
+}
+
+// 根据命名约定为当前平台/架构挑选安装包
+fn select_platform_asset(assets: &[GitHubAsset]) -> Option<&GitHubAsset> {
+    let os_markers: &[&str] = match std::env::consts::OS {
+        "windows" => &[".msi", ".exe", "windows", "win64"],
+        "macos" => &[".dmg", ".app.tar.gz", "macos", "darwin"],
+        "linux" => &[".appimage", ".deb", ".rpm", "linux"],
+        _ => return None,
+    };
+    let (arch_markers, other_arch_markers): (&[&str], &[&str]) = match std::env::consts::ARCH {
+        "x86_64" => (&["x64", "x86_64", "amd64"], &["arm64", "aarch64"]),
+        "aarch64" => (&["arm64", "aarch64"], &["x64", "x86_64", "amd64"]),
+        _ => (&[], &[]),
+    };
+
+    assets
+        .iter()
+        .filter_map(|asset| {
+            let name = asset.name.to_lowercase();
+            if name.ends_with(".sig") || name.ends_with(".sha256") || name.ends_with(".txt") {
+                return None;
+            }
+            if !os_markers.iter().any(|m| name.contains(m)) {
+                return None;
+            }
+            if other_arch_markers.iter().any(|m| name.contains(m)) {
+                return None;
+            }
+            let arch_score = if arch_markers.iter().any(|m| name.contains(m)) {
+                1
+            } else {
+                0
+            };
+            Some((arch_score, asset))
+        })
+        .max_by_key(|(score, _)| *score)
+        .map(|(_, asset)| asset)
 }
 
 // Compare versions (遵循 semver)
-fn compare_versions(current: &str, latest: &str) -> bool {
-    let current_parts: Vec<u32> = current
-        .trim_start_matches('v')
-        .split('.')
-        .filter_map(|s| s.parse().ok())
-        .collect();
+// 语义化版本：major.minor.patch[-pre.release][+build]，build 元数据不参与比较
+#[derive(Debug, PartialEq, Eq)]
+struct Version {
+    major: u64,
+    minor: u64,
+    patch: u64,
+    pre: Vec<String>,
+}
 
-    let latest_parts: Vec<u32> = latest
-        .trim_start_matches('v')
-        .split('.')
-        .filter_map(|s| s.parse().ok())
-        .collect();
+fn parse_version(version: &str) -> Option<Version> {
+    let version = version.trim().trim_start_matches(['v', 'V']);
+    let version = version.split('+').next().unwrap_or_default();
+    let (core, pre) = match version.split_once('-') {
+        Some((core, pre)) => (core, pre.split('.').map(|s| s.to_string()).collect()),
+        None => (version, Vec::new()),
+    };
 
-    for i in 0..3 {
-        let c = current_parts.get(i).unwrap_or(&0);
-        let l = latest_parts.get(i).unwrap_or(&0);
-        if l > c {
-            return true;
-        } else if l < c {
-            return false;
-        }
+    // 缺省的 minor / patch 视为 0
+    let mut numbers = core.split('.').map(|part| part.parse::<u64>());
+    let major = numbers.next()?.ok()?;
+    let minor = numbers.next().unwrap_or(Ok(0)).ok()?;
+    let patch = numbers.next().unwrap_or(Ok(0)).ok()?;
+
+    Some(Version {
+        major,
+        minor,
+        patch,
+        pre,
+    })
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        use std::cmp::Ordering;
+
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                // 预发布版本低于对应的正式版本
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => {
+                    for (a, b) in self.pre.iter().zip(&other.pre) {
+                        let ordering = match (a.parse::<u64>(), b.parse::<u64>()) {
+                            (Ok(a), Ok(b)) => a.cmp(&b),
+                            (Ok(_), Err(_)) => Ordering::Less,
+                            (Err(_), Ok(_)) => Ordering::Greater,
+                            (Err(_), Err(_)) => a.cmp(b),
+                        };
+                        if ordering != Ordering::Equal {
+                            return ordering;
+                        }
+                    }
+                    self.pre.len().cmp(&other.pre.len())
+                }
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// latest 是否比 current 新
+fn compare_versions(current: &str, latest: &str) -> bool {
+    match (parse_version(current), parse_version(latest)) {
+        (Some(current), Some(latest)) => latest > current,
+        _ => false,
     }
-    false
 }
 
 // Get default backup directory (Documents/LitePad/Backups)
@@ -175,7 +474,10 @@ fn resolve_backup_directory(configured: Option<String>) -> Option<String> {
 const MIN_WINDOW_WIDTH: u32 = 400;
 const MIN_WINDOW_HEIGHT: u32 = 300;
 
-fn rect_intersects_monitor(
+// 窗口在显示器上至少要露出 100x100 像素才算可见
+const MIN_VISIBLE_WINDOW_PX: u32 = 100;
+
+fn rect_visible_on_monitor(
     x: i32,
     y: i32,
     width: u32,
@@ -184,14 +486,29 @@ fn rect_intersects_monitor(
 ) -> bool {
     let pos = monitor.position();
     let size = monitor.size();
-    let mx1 = pos.x;
-    let my1 = pos.y;
-    let mx2 = mx1 + size.width as i32;
-    let my2 = my1 + size.height as i32;
-    let x2 = x + width as i32;
-    let y2 = y + height as i32;
+    let visible_width = (x as i64 + width as i64).min(pos.x as i64 + size.width as i64)
+        - (x as i64).max(pos.x as i64);
+    let visible_height = (y as i64 + height as i64).min(pos.y as i64 + size.height as i64)
+        - (y as i64).max(pos.y as i64);
+
+    visible_width >= MIN_VISIBLE_WINDOW_PX.min(width) as i64
+        && visible_height >= MIN_VISIBLE_WINDOW_PX.min(height) as i64
+}
+
+// 窗口与显示器的重叠面积（像素）
+fn monitor_overlap_area(x: i32, y: i32, width: u32, height: u32, monitor: &tauri::Monitor) -> u64 {
+    let pos = monitor.position();
+    let size = monitor.size();
+    let left = x.max(pos.x) as i64;
+    let top = y.max(pos.y) as i64;
+    let right = (x as i64 + width as i64).min(pos.x as i64 + size.width as i64);
+    let bottom = (y as i64 + height as i64).min(pos.y as i64 + size.height as i64);
 
-    x < mx2 && x2 > mx1 && y < my2 && y2 > my1
+    if right > left && bottom > top {
+        ((right - left) * (bottom - top)) as u64
+    } else {
+        0
+    }
 }
 
 fn ensure_window_on_screen(window: &WebviewWindow) -> bool {
@@ -202,16 +519,124 @@ fn ensure_window_on_screen(window: &WebviewWindow) -> bool {
     if let (Some(pos), Some(size)) = (position, size) {
         if monitors
             .iter()
-            .any(|m| rect_intersects_monitor(pos.x, pos.y, size.width, size.height, m))
+            .any(|m| rect_visible_on_monitor(pos.x, pos.y, size.width, size.height, m))
         {
             return false;
         }
     }
 
+    // 窗口所在的显示器可能已被移除，先移到主显示器再居中
+    if let Ok(Some(monitor)) = window.primary_monitor() {
+        let _ = window.set_position(*monitor.position());
+    }
     let _ = window.center();
     true
 }
 
+// 将窗口尺寸和位置限制在显示器范围内，保证窗口完整可见
+fn clamp_rect_to_monitor(
+    pos: Option<(i32, i32)>,
+    width: u32,
+    height: u32,
+    monitor: &tauri::Monitor,
+) -> (Option<(i32, i32)>, u32, u32) {
+    let monitor_pos = monitor.position();
+    let monitor_size = monitor.size();
+    let width = width.min(monitor_size.width);
+    let height = height.min(monitor_size.height);
+    let pos = pos.map(|(x, y)| {
+        let max_x = monitor_pos.x + monitor_size.width as i32 - width as i32;
+        let max_y = monitor_pos.y + monitor_size.height as i32 - height as i32;
+        (x.clamp(monitor_pos.x, max_x), y.clamp(monitor_pos.y, max_y))
+    });
+    (pos, width, height)
+}
+
+// 对象键按字典序重排，保证格式化后的输出稳定、便于 diff
+fn canonical_json(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<_> = map.into_iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            serde_json::Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, canonical_json(value)))
+                    .collect(),
+            )
+        }
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.into_iter().map(canonical_json).collect())
+        }
+        other => other,
+    }
+}
+
+// pretty 时格式化 JSON 快照；默认原样保留（前端传来的是压缩格式），无法解析时也原样写出
+fn format_json_snapshot(raw: &str, pretty: bool) -> String {
+    if !pretty {
+        return raw.to_string();
+    }
+    serde_json::from_str::<serde_json::Value>(raw)
+        .ok()
+        .and_then(|value| serde_json::to_string_pretty(&canonical_json(value)).ok())
+        .unwrap_or_else(|| raw.to_string())
+}
+
+fn pretty_json_enabled(app: &AppHandle) -> bool {
+    app.store("config.json")
+        .ok()
+        .and_then(|store| store.get("settings"))
+        .and_then(|v| serde_json::from_value::<Settings>(v).ok())
+        .is_some_and(|s| s.pretty_json)
+}
+
+// 原子写入：先写临时文件并 fsync，再重命名覆盖（临时文件与目标在同一目录）
+// 临时文件名带随机后缀，同名不同扩展名的目标（如 a.json / a.bak）并发写入时互不覆盖
+fn write_atomic(path: &std::path::Path, content: &[u8]) -> Result<(), String> {
+    write_atomic_from(path, &mut &content[..])
+}
+
+// 同 write_atomic，内容从 reader 流式写入（如 zip 条目），不需要整体读入内存
+fn write_atomic_from(path: &std::path::Path, reader: &mut impl Read) -> Result<(), String> {
+    let file_name = path
+        .file_name()
+        .ok_or("Invalid file path")?
+        .to_string_lossy();
+    let tmp_path = path.with_file_name(format!(".{}.{}.tmp", file_name, Uuid::new_v4()));
+    let written = fs::File::create(&tmp_path)
+        .and_then(|mut file| {
+            std::io::copy(reader, &mut file)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp_path, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    written.map_err(|e| e.to_string())
+}
+
+// 立即写入待保存的数据，返回是否有数据被写入
+fn flush_pending_data(app: &AppHandle) -> Result<bool, String> {
+    let data = match PENDING_DATA.lock().unwrap().take() {
+        Some(data) => data,
+        None => return Ok(false),
+    };
+
+    let (data_path, ephemeral) = {
+        let state = app.state::<Mutex<AppState>>();
+        let state = state.lock().unwrap();
+        (state.data_path.clone(), state.ephemeral)
+    };
+    if !ephemeral {
+        let content = format_json_snapshot(&data, pretty_json_enabled(app));
+        write_atomic(&data_path.join("data.json"), content.as_bytes())?;
+    }
+
+    let _ = app.emit("data-saved", data.len());
+    Ok(true)
+}
+
 // Get portable data path (next to executable)
 fn get_portable_data_path() -> PathBuf {
     let exe_path = std::env::current_exe().expect("Failed to get executable path");
@@ -221,6 +646,106 @@ fn get_portable_data_path() -> PathBuf {
     exe_dir.join("data")
 }
 
+// 非便携模式下的数据目录（程序目录不可写时使用）
+fn get_user_data_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("LitePad"))
+}
+
+// 程序目录下存在 portable 标记文件或设置了 LITEPAD_PORTABLE=1 时强制使用便携模式
+const PORTABLE_MARKER_FILE: &str = "portable";
+
+fn is_portable_forced() -> bool {
+    std::env::var("LITEPAD_PORTABLE").is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        || get_portable_data_path()
+            .parent()
+            .is_some_and(|dir| dir.join(PORTABLE_MARKER_FILE).exists())
+}
+
+// 检查目录是否可写（不存在时尝试创建）
+fn is_directory_writable(dir: &std::path::Path) -> bool {
+    if fs::create_dir_all(dir).is_err() {
+        return false;
+    }
+    let probe = dir.join(format!(".write-test-{}", Uuid::new_v4()));
+    let writable = fs::write(&probe, b"").is_ok();
+    let _ = fs::remove_file(&probe);
+    writable
+}
+
+// 解析数据目录：迁移指针 > 强制便携 > 程序目录可写时便携 > 用户数据目录
+fn resolve_data_path() -> PathBuf {
+    if let Some(path) = read_data_location_pointer() {
+        return path;
+    }
+    let portable = get_portable_data_path();
+    if is_portable_forced() || is_directory_writable(&portable) {
+        return portable;
+    }
+    get_user_data_path().unwrap_or(portable)
+}
+
+// 数据目录迁移后的指针文件，记录实际使用的数据目录；
+// 优先放在程序目录，程序目录不可写时放在用户配置目录
+const DATA_LOCATION_FILE: &str = "data-location.txt";
+
+fn data_location_pointer_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Some(exe_dir) = get_portable_data_path().parent() {
+        paths.push(exe_dir.join(DATA_LOCATION_FILE));
+    }
+    if let Some(config_dir) = dirs::config_dir() {
+        paths.push(config_dir.join("LitePad").join(DATA_LOCATION_FILE));
+    }
+    paths
+}
+
+// 读取指针文件中记录的数据目录；指针不存在或目录已丢失时返回 None
+fn read_data_location_pointer() -> Option<PathBuf> {
+    data_location_pointer_paths()
+        .into_iter()
+        .find_map(|pointer| {
+            let content = fs::read_to_string(pointer).ok()?;
+            let path = PathBuf::from(content.trim());
+            (path.is_absolute() && path.is_dir()).then_some(path)
+        })
+}
+
+fn write_data_location_pointer(data_path: &std::path::Path) -> Result<(), String> {
+    let mut last_error = "Failed to get executable directory".to_string();
+    for pointer in data_location_pointer_paths() {
+        if let Some(parent) = pointer.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        match fs::write(&pointer, data_path.to_string_lossy().as_bytes()) {
+            Ok(()) => return Ok(()),
+            Err(e) => last_error = e.to_string(),
+        }
+    }
+    Err(last_error)
+}
+
+// 当前数据目录信息
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataDirectoryInfo {
+    pub data_path: String,
+    pub images_path: String,
+    pub portable: bool,
+    pub ephemeral: bool,
+}
+
+// Get the active data directory (portable, user data dir or a migrated location)
+#[tauri::command]
+fn get_data_directory(state: State<'_, Mutex<AppState>>) -> DataDirectoryInfo {
+    let state = state.lock().unwrap();
+    DataDirectoryInfo {
+        data_path: state.data_path.to_string_lossy().to_string(),
+        images_path: state.images_path.to_string_lossy().to_string(),
+        portable: state.data_path == get_portable_data_path(),
+        ephemeral: state.ephemeral,
+    }
+}
+
 // Toggle window visibility
 // Strategy: if window is visible (and not minimized), hide it; otherwise show and focus it
 fn toggle_window(app: &AppHandle) {
@@ -232,6 +757,7 @@ fn toggle_window(app: &AppHandle) {
 
         if is_visible && !is_minimized {
             // 窗口可见，隐藏它
+            let _ = flush_pending_data(app);
             let _ = window.hide();
         } else {
             // 窗口不可见/最小化，显示并聚焦
@@ -255,6 +781,40 @@ fn toggle_window(app: &AppHandle) {
 
 // Commands exposed to frontend
 
+// Reset the main window to the default size, centered on the primary monitor
+#[tauri::command]
+fn reset_window_position(app: AppHandle) -> Result<(), String> {
+    let window = app
+        .get_webview_window("main")
+        .ok_or("Main window not found")?;
+    let defaults = WindowBounds::default();
+
+    let _ = window.set_fullscreen(false);
+    let _ = window.unmaximize();
+    let _ = window.unminimize();
+
+    // 先移到主显示器上，center() 会以窗口当前所在显示器为准
+    if let Ok(Some(monitor)) = window.primary_monitor() {
+        let _ = window.set_position(*monitor.position());
+    }
+    window
+        .set_size(PhysicalSize::new(
+            defaults.width.max(MIN_WINDOW_WIDTH),
+            defaults.height.max(MIN_WINDOW_HEIGHT),
+        ))
+        .map_err(|e| e.to_string())?;
+    window.center().map_err(|e| e.to_string())?;
+
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    store.delete("windowBounds");
+    persist_store(&app, &store)?;
+
+    let _ = window.show();
+    let _ = window.set_focus();
+
+    Ok(())
+}
+
 #[tauri::command]
 fn get_version() -> String {
     env!("CARGO_PKG_VERSION").to_string()
@@ -290,13 +850,18 @@ async fn set_auto_launch(app: AppHandle, enabled: bool) -> Result<(), String> {
         .unwrap_or_default();
     settings.auto_launch = enabled;
     store.set("settings", serde_json::to_value(&settings).unwrap());
-    store.save().map_err(|e| e.to_string())?;
+    persist_store(&app, &store)?;
 
     Ok(())
 }
 
 #[tauri::command]
 async fn set_always_on_top(app: AppHandle, enabled: bool) -> Result<(), String> {
+    apply_always_on_top(&app, enabled)
+}
+
+// 设置并保存置顶状态，同步托盘勾选并通知前端
+fn apply_always_on_top(app: &AppHandle, enabled: bool) -> Result<(), String> {
     if let Some(window) = app.get_webview_window("main") {
         window
             .set_always_on_top(enabled)
@@ -311,618 +876,8553 @@ async fn set_always_on_top(app: AppHandle, enabled: bool) -> Result<(), String>
         .unwrap_or_default();
     settings.always_on_top = enabled;
     store.set("settings", serde_json::to_value(&settings).unwrap());
-    store.save().map_err(|e| e.to_string())?;
+    persist_store(app, &store)?;
+
+    if let Some(item) = app.try_state::<CheckMenuItem<tauri::Wry>>() {
+        let _ = item.set_checked(enabled);
+    }
+    let _ = app.emit("always-on-top-changed", enabled);
 
     Ok(())
 }
 
-#[tauri::command]
-fn get_system_fonts() -> Vec<String> {
-    let source = SystemSource::new();
-    let mut fonts: Vec<String> = source
-        .all_families()
-        .unwrap_or_default()
-        .into_iter()
-        .filter(|name| !name.starts_with('.') && !name.starts_with('@'))
-        .collect();
-    fonts.sort();
-    fonts.dedup();
+const MIN_WINDOW_OPACITY: f64 = 0.2;
+const MAX_WINDOW_OPACITY: f64 = 1.0;
 
-    // Fallback if empty
-    if fonts.is_empty() {
-        fonts = vec![
-            "SimSun".to_string(),
-            "Microsoft YaHei".to_string(),
-            "SimHei".to_string(),
-            "KaiTi".to_string(),
-            "FangSong".to_string(),
-            "Consolas".to_string(),
-            "Segoe UI".to_string(),
-        ];
-    }
+// Windows: 通过分层窗口属性设置整窗透明度
+#[cfg(windows)]
+fn apply_window_opacity(window: &WebviewWindow, opacity: f64) -> Result<(), String> {
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        GetWindowLongPtrW, SetLayeredWindowAttributes, SetWindowLongPtrW, GWL_EXSTYLE, LWA_ALPHA,
+        WS_EX_LAYERED,
+    };
 
-    fonts
+    let hwnd = window.hwnd().map_err(|e| e.to_string())?.0 as _;
+    let alpha = (opacity.clamp(MIN_WINDOW_OPACITY, MAX_WINDOW_OPACITY) * 255.0).round() as u8;
+    unsafe {
+        let ex_style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
+        if ex_style & WS_EX_LAYERED as isize == 0 {
+            SetWindowLongPtrW(hwnd, GWL_EXSTYLE, ex_style | WS_EX_LAYERED as isize);
+        }
+        if SetLayeredWindowAttributes(hwnd, 0, alpha, LWA_ALPHA) == 0 {
+            return Err("Failed to set window opacity".to_string());
+        }
+    }
+    Ok(())
 }
 
-// 图片保存结果，包含 hash 和 URL
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct SaveImageResult {
-    pub hash: String,
-    pub url: String,
-    pub size: usize,
-    pub ext: String,
+// macOS / Linux: Tauri 未提供整窗透明度接口
+#[cfg(not(windows))]
+fn apply_window_opacity(_window: &WebviewWindow, _opacity: f64) -> Result<(), String> {
+    Err("UNSUPPORTED".to_string())
 }
 
+// Set the window opacity (clamped to 0.2 - 1.0); returns UNSUPPORTED where unavailable
 #[tauri::command]
-async fn save_image(
-    state: State<'_, Mutex<AppState>>,
-    buffer: Vec<u8>,
-    ext: String,
-) -> Result<SaveImageResult, String> {
-    // 计算 SHA-256 hash
-    let mut hasher = Sha256::new();
-    hasher.update(&buffer);
-    let hash = hex::encode(hasher.finalize());
+async fn set_window_opacity(app: AppHandle, opacity: f64) -> Result<f64, String> {
+    let opacity = if opacity.is_finite() {
+        opacity.clamp(MIN_WINDOW_OPACITY, MAX_WINDOW_OPACITY)
+    } else {
+        MAX_WINDOW_OPACITY
+    };
+    let window = app
+        .get_webview_window("main")
+        .ok_or("Main window not found")?;
+    apply_window_opacity(&window, opacity)?;
 
-    let state = state.lock().unwrap();
-    // 使用 hash 作为文件名（去重）
-    let filename = format!("{}{}", hash, ext);
-    let file_path = state.images_path.join(&filename);
+    // Save to store
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    let mut settings: Settings = store
+        .get("settings")
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    settings.window_opacity = opacity;
+    store.set("settings", serde_json::to_value(&settings).unwrap());
+    persist_store(&app, &store)?;
 
-    // 如果文件已存在（相同 hash），直接返回，不重复写入
-    if !file_path.exists() {
-        fs::write(&file_path, &buffer).map_err(|e| e.to_string())?;
-    }
+    Ok(opacity)
+}
 
-    // 返回 litepad:// 协议 URL
-    Ok(SaveImageResult {
-        hash: hash.clone(),
-        url: format!("litepad://images/{}{}", hash, ext),
-        size: buffer.len(),
-        ext: ext.clone(),
-    })
+// 窗口布局预设（逻辑像素）
+const STICKY_NOTE_SIZE: u32 = 300;
+const STICKY_NOTE_MARGIN: u32 = 16;
+const COMPACT_WINDOW_SIZE: (u32, u32) = (500, 400);
+
+// 切换到便签/紧凑预设前的窗口布局，切回 editor 时恢复
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EditorLayout {
+    bounds: WindowBounds,
+    always_on_top: bool,
 }
 
-// 根据 hash 获取图片路径（用于 litepad:// 协议）
-#[tauri::command]
-fn get_image_path(state: State<'_, Mutex<AppState>>, hash: String, ext: String) -> Result<String, String> {
-    let state = state.lock().unwrap();
-    let filename = format!("{}{}", hash, ext);
-    let file_path = state.images_path.join(&filename);
+fn current_window_preset(store: &tauri_plugin_store::Store<tauri::Wry>) -> String {
+    store
+        .get("windowPreset")
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_else(|| "editor".to_string())
+}
 
-    if file_path.exists() {
-        Ok(file_path.to_string_lossy().to_string())
-    } else {
-        Err(format!("Image not found: {}", filename))
-    }
+// 目标区域所在的显示器（重叠面积最大），都不相交时使用窗口当前显示器
+fn monitor_for_rect(
+    window: &WebviewWindow,
+    pos: Option<(i32, i32)>,
+    width: u32,
+    height: u32,
+) -> Option<tauri::Monitor> {
+    let by_overlap = pos.and_then(|(x, y)| {
+        window
+            .available_monitors()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|m| (monitor_overlap_area(x, y, width, height, &m), m))
+            .filter(|(area, _)| *area > 0)
+            .max_by_key(|(area, _)| *area)
+            .map(|(_, m)| m)
+    });
+    by_overlap
+        .or_else(|| window.current_monitor().ok().flatten())
+        .or_else(|| window.primary_monitor().ok().flatten())
 }
 
-// 检查图片是否存在
+// Get the last applied window layout preset
 #[tauri::command]
-fn has_image(state: State<'_, Mutex<AppState>>, hash: String, ext: String) -> bool {
-    let state = state.lock().unwrap();
-    let filename = format!("{}{}", hash, ext);
-    let file_path = state.images_path.join(&filename);
-    file_path.exists()
+fn get_window_preset(app: AppHandle) -> Result<String, String> {
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    Ok(current_window_preset(&store))
 }
 
-// 保存从服务器下载的图片
+// Apply a window layout preset: sticky (300x300, top-right, pinned), editor (saved bounds) or compact (500x400)
 #[tauri::command]
-async fn save_downloaded_image(
-    state: State<'_, Mutex<AppState>>,
-    hash: String,
-    ext: String,
-    buffer: Vec<u8>,
-) -> Result<String, String> {
-    let state = state.lock().unwrap();
-    let filename = format!("{}{}", hash, ext);
-    let file_path = state.images_path.join(&filename);
-
-    // 验证 hash
-    let mut hasher = Sha256::new();
-    hasher.update(&buffer);
-    let computed_hash = hex::encode(hasher.finalize());
+async fn apply_window_preset(app: AppHandle, name: String) -> Result<(), String> {
+    let window = app
+        .get_webview_window("main")
+        .ok_or("Main window not found")?;
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    let settings: Settings = store
+        .get("settings")
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
 
-    if computed_hash != hash {
-        return Err(format!(
-            "Hash mismatch: expected {}, got {}",
-            hash, computed_hash
-        ));
+    match name.as_str() {
+        "sticky" | "compact" => {
+            // 从 editor 切出时记录当前布局
+            if current_window_preset(&store) == "editor" || store.get("editorLayout").is_none() {
+                let layout = EditorLayout {
+                    bounds: store
+                        .get("windowBounds")
+                        .and_then(|v| serde_json::from_value(v).ok())
+                        .unwrap_or_default(),
+                    always_on_top: settings.always_on_top,
+                };
+                store.set("editorLayout", serde_json::to_value(&layout).unwrap());
+            }
+
+            let monitor = window
+                .current_monitor()
+                .ok()
+                .flatten()
+                .or_else(|| window.primary_monitor().ok().flatten())
+                .ok_or("No monitor available")?;
+            let scale = monitor.scale_factor();
+            let to_physical = |logical: u32| (logical as f64 * scale).round() as u32;
+
+            let _ = window.set_fullscreen(false);
+            let _ = window.unmaximize();
+
+            let (width, height, pos) = if name == "sticky" {
+                window
+                    .set_min_size(Some(tauri::LogicalSize::new(
+                        STICKY_NOTE_SIZE,
+                        STICKY_NOTE_SIZE,
+                    )))
+                    .map_err(|e| e.to_string())?;
+                let size = to_physical(STICKY_NOTE_SIZE);
+                let margin = to_physical(STICKY_NOTE_MARGIN) as i32;
+                let monitor_pos = monitor.position();
+                let x = monitor_pos.x + monitor.size().width as i32 - size as i32 - margin;
+                let y = monitor_pos.y + margin;
+                (size, size, Some((x, y)))
+            } else {
+                window
+                    .set_min_size(Some(tauri::LogicalSize::new(
+                        MIN_WINDOW_WIDTH,
+                        MIN_WINDOW_HEIGHT,
+                    )))
+                    .map_err(|e| e.to_string())?;
+                let pos = window.outer_position().ok().map(|p| (p.x, p.y));
+                (
+                    to_physical(COMPACT_WINDOW_SIZE.0),
+                    to_physical(COMPACT_WINDOW_SIZE.1),
+                    pos,
+                )
+            };
+
+            let (pos, width, height) = clamp_rect_to_monitor(pos, width, height, &monitor);
+            window
+                .set_size(PhysicalSize::new(width, height))
+                .map_err(|e| e.to_string())?;
+            if let Some((x, y)) = pos {
+                let _ = window.set_position(PhysicalPosition::new(x, y));
+            }
+            if name == "sticky" && !settings.always_on_top {
+                apply_always_on_top(&app, true)?;
+            }
+        }
+        "editor" => {
+            let layout = store
+                .get("editorLayout")
+                .and_then(|v| serde_json::from_value::<EditorLayout>(v).ok())
+                .unwrap_or_else(|| EditorLayout {
+                    bounds: store
+                        .get("windowBounds")
+                        .and_then(|v| serde_json::from_value(v).ok())
+                        .unwrap_or_default(),
+                    always_on_top: settings.always_on_top,
+                });
+            let bounds = layout.bounds;
+
+            window
+                .set_min_size(Some(tauri::LogicalSize::new(
+                    MIN_WINDOW_WIDTH,
+                    MIN_WINDOW_HEIGHT,
+                )))
+                .map_err(|e| e.to_string())?;
+            let pos = bounds.x.zip(bounds.y);
+            let width = bounds.width.max(MIN_WINDOW_WIDTH);
+            let height = bounds.height.max(MIN_WINDOW_HEIGHT);
+            let (pos, width, height) = match monitor_for_rect(&window, pos, width, height) {
+                Some(monitor) => clamp_rect_to_monitor(pos, width, height, &monitor),
+                None => (pos, width, height),
+            };
+            window
+                .set_size(PhysicalSize::new(width, height))
+                .map_err(|e| e.to_string())?;
+            match pos {
+                Some((x, y)) => {
+                    let _ = window.set_position(PhysicalPosition::new(x, y));
+                }
+                None => {
+                    let _ = window.center();
+                }
+            }
+            if bounds.fullscreen {
+                let _ = window.set_fullscreen(true);
+            } else if bounds.maximized {
+                let _ = window.maximize();
+            }
+            if layout.always_on_top != settings.always_on_top {
+                apply_always_on_top(&app, layout.always_on_top)?;
+            }
+            store.delete("editorLayout");
+        }
+        _ => return Err(format!("Unknown window preset: {}", name)),
     }
 
-    fs::write(&file_path, &buffer).map_err(|e| e.to_string())?;
+    store.set("windowPreset", name);
+    persist_store(&app, &store)
+}
 
-    Ok(file_path.to_string_lossy().to_string())
+// 托盘菜单和快捷键共用：切换置顶
+fn toggle_always_on_top(app: &AppHandle) {
+    let enabled = app
+        .store("config.json")
+        .ok()
+        .and_then(|store| store.get("settings"))
+        .and_then(|v| serde_json::from_value::<Settings>(v).ok())
+        .map(|s| s.always_on_top)
+        .unwrap_or(false);
+    if let Err(e) = apply_always_on_top(app, !enabled) {
+        eprintln!("Warning: Failed to toggle always on top: {}", e);
+    }
 }
 
-// 读取本地图片文件（用于上传到服务器）
+// Set always on top for a limited time; the persistent setting is left untouched
 #[tauri::command]
-fn read_image(state: State<'_, Mutex<AppState>>, hash: String, ext: String) -> Result<Vec<u8>, String> {
-    let state = state.lock().unwrap();
-    let filename = format!("{}{}", hash, ext);
-    let file_path = state.images_path.join(&filename);
+async fn set_always_on_top_temporary(app: AppHandle, duration_secs: u64) -> Result<(), String> {
+    let window = app
+        .get_webview_window("main")
+        .ok_or("Main window not found")?;
+    window.set_always_on_top(true).map_err(|e| e.to_string())?;
+
+    let app_handle = app.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_secs(duration_secs)).await;
+
+        let persistent = app_handle
+            .store("config.json")
+            .ok()
+            .and_then(|store| store.get("settings"))
+            .and_then(|v| serde_json::from_value::<Settings>(v).ok())
+            .map(|s| s.always_on_top)
+            .unwrap_or(false);
+
+        if !persistent {
+            if let Some(window) = app_handle.get_webview_window("main") {
+                let _ = window.set_always_on_top(false);
+            }
+        }
+        let _ = app_handle.emit("always-on-top-temporary-expired", persistent);
+    });
 
-    fs::read(&file_path).map_err(|e| e.to_string())
-}
+    if let Some(previous) = TEMPORARY_ON_TOP_TASK.lock().unwrap().replace(task) {
+        previous.abort();
+    }
 
-// 迁移结果
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct MigrateImageResult {
-    pub hash: String,
-    pub ext: String,
-    pub size: usize,
-    pub new_url: String,
+    Ok(())
 }
 
-// 迁移旧格式图片到新的 hash-based 格式
+// Queue note data for saving; writes are coalesced to at most one per save interval
 #[tauri::command]
-fn migrate_old_image(
-    state: State<'_, Mutex<AppState>>,
-    old_path: String,
-) -> Result<MigrateImageResult, String> {
-    // 尝试读取旧文件
-    let old_path = old_path.replace('/', "\\").replace("\\\\", "\\");
-    let old_file = std::path::Path::new(&old_path);
+async fn save_data(app: AppHandle, data: String) -> Result<(), String> {
+    if is_view_mode(&app) {
+        return Err("READ_ONLY_VIEW".to_string());
+    }
+    *PENDING_DATA.lock().unwrap() = Some(data);
 
-    if !old_file.exists() {
-        return Err(format!("文件不存在: {}", old_path));
+    // 已有计划中的写入，等它带走最新数据
+    if DATA_SAVE_SCHEDULED.swap(true, Ordering::SeqCst) {
+        return Ok(());
     }
 
-    // 读取文件内容
-    let buffer = fs::read(old_file).map_err(|e| format!("读取文件失败: {}", e))?;
+    let interval_ms = app
+        .store("config.json")
+        .ok()
+        .and_then(|store| store.get("settings"))
+        .and_then(|v| serde_json::from_value::<Settings>(v).ok())
+        .unwrap_or_default()
+        .save_interval_ms;
+
+    let app_handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_millis(interval_ms)).await;
+        DATA_SAVE_SCHEDULED.store(false, Ordering::SeqCst);
+        if let Err(e) = flush_pending_data(&app_handle) {
+            eprintln!("Warning: Failed to save data: {}", e);
+        }
+    });
 
-    // 计算 hash
-    let mut hasher = Sha256::new();
-    hasher.update(&buffer);
-    let hash = hex::encode(hasher.finalize());
+    Ok(())
+}
 
-    // 获取扩展名
-    let ext = old_file
-        .extension()
-        .and_then(|e| e.to_str())
-        .map(|e| format!(".{}", e))
-        .unwrap_or_else(|| ".png".to_string());
+const WINDOW_BOUNDS_SAVE_DELAY_MS: u64 = 500;
 
-    let state = state.lock().unwrap();
-    let new_filename = format!("{}{}", hash, ext);
-    let new_path = state.images_path.join(&new_filename);
+// 窗口位置变化后重新计时，停止变化后再落盘
+fn schedule_window_bounds_save(app: &AppHandle) {
+    let app_handle = app.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_millis(
+            WINDOW_BOUNDS_SAVE_DELAY_MS,
+        ))
+        .await;
+        if let Ok(store) = app_handle.store("config.json") {
+            if let Err(e) = persist_store(&app_handle, &store) {
+                eprintln!("Warning: Failed to save window bounds: {}", e);
+            }
+        }
+    });
 
-    // 如果新文件不存在，复制过去
-    if !new_path.exists() {
-        fs::write(&new_path, &buffer).map_err(|e| format!("写入文件失败: {}", e))?;
+    if let Some(previous) = WINDOW_BOUNDS_SAVE_TASK.lock().unwrap().replace(task) {
+        previous.abort();
     }
+}
 
-    Ok(MigrateImageResult {
-        hash: hash.clone(),
-        ext: ext.clone(),
-        size: buffer.len(),
-        new_url: format!("litepad://images/{}{}", hash, ext),
-    })
+// 立即写入尚未保存的窗口位置（关闭/退出时调用）
+fn flush_window_bounds(app: &AppHandle) {
+    if let Some(task) = WINDOW_BOUNDS_SAVE_TASK.lock().unwrap().take() {
+        task.abort();
+        if let Ok(store) = app.store("config.json") {
+            let _ = persist_store(app, &store);
+        }
+    }
 }
 
-// 批量检查旧图片是否存在
+// 退出前等待前端保存状态的最长时间
+const QUIT_FLUSH_TIMEOUT_MS: u64 = 1500;
+static QUIT_REQUESTED: AtomicBool = AtomicBool::new(false);
+static QUIT_READY: AtomicBool = AtomicBool::new(false);
+
+// 通知前端即将退出（app-will-quit），等待其确认或超时后再退出
+fn request_quit(app: &AppHandle) {
+    if QUIT_REQUESTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    QUIT_READY.store(false, Ordering::SeqCst);
+    if app.emit("app-will-quit", ()).is_err() {
+        app.exit(0);
+        return;
+    }
+
+    let app_handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let deadline =
+            std::time::Instant::now() + std::time::Duration::from_millis(QUIT_FLUSH_TIMEOUT_MS);
+        while !QUIT_READY.load(Ordering::SeqCst) && std::time::Instant::now() < deadline {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        app_handle.exit(0);
+    });
+}
+
+// 退出前写入所有尚未落盘的数据和配置
+fn flush_before_exit(app: &AppHandle) {
+    if let Err(e) = flush_pending_data(app) {
+        eprintln!("Warning: Failed to save data on exit: {}", e);
+    }
+    flush_window_bounds(app);
+    if let Ok(store) = app.store("config.json") {
+        if let Err(e) = persist_store(app, &store) {
+            eprintln!("Warning: Failed to save config on exit: {}", e);
+        }
+    }
+}
+
+// Signal that the frontend finished persisting after app-will-quit
 #[tauri::command]
-fn check_old_images_exist(paths: Vec<String>) -> Vec<bool> {
-    paths
-        .iter()
-        .map(|p| {
-            let path = p.replace('/', "\\").replace("\\\\", "\\");
-            std::path::Path::new(&path).exists()
-        })
-        .collect()
+fn confirm_quit_ready() {
+    QUIT_READY.store(true, Ordering::SeqCst);
 }
 
+// Force the pending save to be written immediately
 #[tauri::command]
-fn minimize_window(app: AppHandle) {
-    if let Some(window) = app.get_webview_window("main") {
-        let _ = window.minimize();
+fn flush_pending_save(app: AppHandle) -> Result<bool, String> {
+    flush_pending_data(&app)
+}
+
+// 崩溃恢复快照（data_path/recovery.json）；临时模式和只读查看模式下不写磁盘
+fn recovery_snapshot_path(app: &AppHandle) -> Option<PathBuf> {
+    if is_view_mode(app) {
+        return None;
     }
+    let state = app.state::<Mutex<AppState>>();
+    let state = state.lock().unwrap();
+    (!state.ephemeral).then(|| state.data_path.join("recovery.json"))
 }
 
+// Atomically write a crash-recovery snapshot of the current note data
 #[tauri::command]
-fn maximize_window(app: AppHandle) {
-    if let Some(window) = app.get_webview_window("main") {
-        if window.is_maximized().unwrap_or(false) {
-            let _ = window.unmaximize();
-        } else {
-            let _ = window.maximize();
-        }
+async fn write_recovery_snapshot(app: AppHandle, data: String) -> Result<(), String> {
+    match recovery_snapshot_path(&app) {
+        Some(path) => write_atomic(
+            &path,
+            format_json_snapshot(&data, pretty_json_enabled(&app)).as_bytes(),
+        ),
+        None => Ok(()),
     }
 }
 
+// Read the recovery snapshot left by a previous session, if any
 #[tauri::command]
-fn close_window(app: AppHandle) {
-    if let Some(window) = app.get_webview_window("main") {
-        let _ = window.hide();
+async fn read_recovery_snapshot(app: AppHandle) -> Result<Option<String>, String> {
+    match recovery_snapshot_path(&app) {
+        Some(path) if path.exists() => fs::read_to_string(&path)
+            .map(Some)
+            .map_err(|e| e.to_string()),
+        _ => Ok(None),
     }
 }
 
-// Check if path is inside installation directory
-fn is_inside_install_dir(path: &std::path::Path) -> bool {
-    if let Ok(exe_path) = std::env::current_exe() {
-        if let Some(exe_dir) = exe_path.parent() {
-            return path.starts_with(exe_dir);
-        }
+// Remove the recovery snapshot after a successful normal save
+#[tauri::command]
+async fn clear_recovery_snapshot(app: AppHandle) -> Result<(), String> {
+    match recovery_snapshot_path(&app) {
+        Some(path) if path.exists() => fs::remove_file(&path).map_err(|e| e.to_string()),
+        _ => Ok(()),
     }
-    false
 }
 
-// Select backup directory with installation directory check
-#[tauri::command]
-async fn select_backup_directory(app: AppHandle) -> Result<Option<String>, String> {
-    use tauri_plugin_dialog::DialogExt;
+// 当前注册的显示/隐藏快捷键
+static TOGGLE_SHORTCUT: Mutex<Option<Shortcut>> = Mutex::new(None);
 
-    let folder = app.dialog().file().blocking_pick_folder();
+const DEFAULT_TOGGLE_SHORTCUT: &str = "Alt+X";
 
-    match folder {
-        Some(file_path) => {
-            let path_buf = file_path.into_path().map_err(|e| e.to_string())?;
-            if is_inside_install_dir(&path_buf) {
-                Err("Cannot select installation directory as backup location".to_string())
-            } else {
-                Ok(Some(path_buf.to_string_lossy().to_string()))
-            }
+// 解析 "Alt+X"、"Ctrl+Shift+Space" 之类的快捷键字符串
+fn parse_shortcut(value: &str) -> Result<Shortcut, String> {
+    let mut modifiers = Modifiers::empty();
+    let mut code = None;
+
+    for part in value.split('+').map(|p| p.trim()) {
+        if part.is_empty() {
+            return Err(format!("Invalid shortcut \"{}\": empty key", value));
         }
-        None => Ok(None),
+        let modifier = match part.to_ascii_lowercase().as_str() {
+            "alt" | "option" => Some(Modifiers::ALT),
+            "ctrl" | "control" => Some(Modifiers::CONTROL),
+            "shift" => Some(Modifiers::SHIFT),
+            "super" | "meta" | "cmd" | "command" | "win" => Some(Modifiers::SUPER),
+            _ => None,
+        };
+        if let Some(modifier) = modifier {
+            modifiers |= modifier;
+            continue;
+        }
+        if code.is_some() {
+            return Err(format!(
+                "Invalid shortcut \"{}\": only one non-modifier key is allowed",
+                value
+            ));
+        }
+        code =
+            Some(parse_key_code(part).ok_or_else(|| {
+                format!("Invalid shortcut \"{}\": unknown key \"{}\"", value, part)
+            })?);
+    }
+
+    let code = code.ok_or_else(|| format!("Invalid shortcut \"{}\": missing key", value))?;
+    if modifiers.is_empty() {
+        return Err(format!(
+            "Invalid shortcut \"{}\": at least one modifier is required",
+            value
+        ));
     }
+
+    Ok(Shortcut::new(Some(modifiers), code))
 }
 
-// Get backup settings
-#[tauri::command]
-async fn get_backup_settings(app: AppHandle) -> Result<BackupSettings, String> {
-    let store = app.store("config.json").map_err(|e| e.to_string())?;
-    if let Some(value) = store.get("backupSettings") {
-        let mut settings: BackupSettings =
-            serde_json::from_value(value).map_err(|e| e.to_string())?;
-        if settings.backup_directory.is_none() {
-            settings.backup_directory = get_default_backup_directory();
+fn parse_key_code(key: &str) -> Option<Code> {
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if c.is_ascii_alphabetic() {
+            return format!("Key{}", c.to_ascii_uppercase()).parse().ok();
+        }
+        if c.is_ascii_digit() {
+            return format!("Digit{}", c).parse().ok();
         }
-        Ok(settings)
-    } else {
-        Ok(BackupSettings::default())
     }
-}
 
-// Save backup settings
-#[tauri::command]
-async fn set_backup_settings(app: AppHandle, settings: BackupSettings) -> Result<(), String> {
-    let store = app.store("config.json").map_err(|e| e.to_string())?;
-    store.set("backupSettings", serde_json::to_value(&settings).unwrap());
-    store.save().map_err(|e| e.to_string())?;
-    Ok(())
+    let name = match key.to_ascii_lowercase().as_str() {
+        "esc" => "Escape".to_string(),
+        "return" => "Enter".to_string(),
+        "up" | "down" | "left" | "right" => {
+            let lower = key.to_ascii_lowercase();
+            format!("Arrow{}{}", lower[..1].to_ascii_uppercase(), &lower[1..])
+        }
+        _ => {
+            // 其余按 W3C code 名称匹配（Space、Enter、F1、Backquote ...），忽略大小写
+            let mut name = key.to_ascii_lowercase();
+            if let Some(first) = name.get_mut(..1) {
+                first.make_ascii_uppercase();
+            }
+            name
+        }
+    };
+
+    name.parse().ok().or_else(|| key.parse().ok())
 }
 
-// Clean up old backups
-fn cleanup_old_backups(backup_dir: &str, max_backups: u32) -> Result<(), String> {
-    let mut backups: Vec<_> = fs::read_dir(backup_dir)
-        .map_err(|e| e.to_string())?
-        .filter_map(|e| e.ok())
-        .filter(|e| {
-            let name = e.file_name().to_string_lossy().to_string();
-            name.starts_with("litepad_backup_") && name.ends_with(".zip")
+// 注册显示/隐藏快捷键
+fn register_toggle_shortcut(app: &AppHandle, shortcut: Shortcut) -> Result<(), String> {
+    app.global_shortcut()
+        .on_shortcut(shortcut, move |app, _shortcut, event| {
+            match event.state {
+                ShortcutState::Pressed => {
+                    // 防止按住触发重复 Pressed；并避免 Pressed/Released 双触发导致“闪一下”
+                    let was_held = SHORTCUT_HELD.swap(true, Ordering::Relaxed);
+                    if was_held {
+                        return;
+                    }
+                    toggle_window(app);
+                }
+                ShortcutState::Released => {
+                    SHORTCUT_HELD.store(false, Ordering::Relaxed);
+                }
+            }
         })
-        .collect();
-
-    // Sort by filename descending (newest first)
-    backups.sort_by(|a, b| b.file_name().cmp(&a.file_name()));
-
-    // Delete excess backups
-    for backup in backups.iter().skip(max_backups as usize) {
-        let _ = fs::remove_file(backup.path());
-    }
+        .map_err(|e| e.to_string())?;
 
+    *TOGGLE_SHORTCUT.lock().unwrap() = Some(shortcut);
     Ok(())
 }
 
-// Perform backup
+// 修改显示/隐藏快捷键；注册失败时恢复原快捷键
 #[tauri::command]
-async fn perform_backup(
-    state: State<'_, Mutex<AppState>>,
-    app: AppHandle,
-    data: String,
-) -> Result<String, String> {
+async fn set_toggle_shortcut(app: AppHandle, shortcut: String) -> Result<(), String> {
+    let shortcut = shortcut.trim().to_string();
+    let parsed = parse_shortcut(&shortcut)?;
+    let previous = *TOGGLE_SHORTCUT.lock().unwrap();
+
+    if previous != Some(parsed) {
+        if let Some(previous) = previous {
+            let _ = app.global_shortcut().unregister(previous);
+            *TOGGLE_SHORTCUT.lock().unwrap() = None;
+        }
+        SHORTCUT_HELD.store(false, Ordering::Relaxed);
+
+        if let Err(e) = register_toggle_shortcut(&app, parsed) {
+            if let Some(previous) = previous {
+                let _ = register_toggle_shortcut(&app, previous);
+            }
+            return Err(format!(
+                "Failed to register shortcut {}: {}. Another application may be using it.",
+                shortcut, e
+            ));
+        }
+    }
+
+    // Save to store
     let store = app.store("config.json").map_err(|e| e.to_string())?;
-    let settings: BackupSettings = store
-        .get("backupSettings")
+    let mut settings: Settings = store
+        .get("settings")
         .and_then(|v| serde_json::from_value(v).ok())
         .unwrap_or_default();
-
-    let backup_dir = resolve_backup_directory(settings.backup_directory)
-        .ok_or("Backup directory not configured")?;
-    let backup_path = std::path::Path::new(&backup_dir);
-
-    if !backup_path.exists() {
-        fs::create_dir_all(backup_path).map_err(|e| e.to_string())?;
+    if let Some(item) = app.try_state::<MenuItem<tauri::Wry>>() {
+        let _ = item.set_text(format!("Show/Hide ({})", shortcut));
     }
+    set_tray_shortcut_label(&app, &shortcut);
+    settings.shortcut = shortcut;
+    store.set("settings", serde_json::to_value(&settings).unwrap());
+    persist_store(&app, &store)?;
 
-    // Generate filename with timestamp
-    let timestamp = Local::now().format("%Y%m%d_%H%M%S");
-    let filename = format!("litepad_backup_{}.zip", timestamp);
-    let zip_path = backup_path.join(&filename);
+    Ok(())
+}
 
-    // Get images path
-    let images_path = {
-        let state = state.lock().unwrap();
-        state.images_path.clone()
+// 快捷键注册失败信息（shortcut-registration-failed 事件）
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ShortcutRegistrationFailure {
+    // "toggle" / "alwaysOnTop"
+    pub action: String,
+    pub shortcut: String,
+    pub error: String,
+}
+
+// 启动时的注册失败会早于前端监听，保留下来供前端查询
+static SHORTCUT_FAILURES: Mutex<Vec<ShortcutRegistrationFailure>> = Mutex::new(Vec::new());
+
+fn report_shortcut_failure(app: &AppHandle, action: &str, shortcut: &str, error: String) {
+    eprintln!(
+        "Warning: Failed to register {} shortcut: {}. Another application may be using it.",
+        shortcut, error
+    );
+    let failure = ShortcutRegistrationFailure {
+        action: action.to_string(),
+        shortcut: shortcut.to_string(),
+        error,
     };
+    SHORTCUT_FAILURES.lock().unwrap().push(failure.clone());
+    let _ = app.emit("shortcut-registration-failed", failure);
+}
 
-    // Create ZIP file
-    let file = fs::File::create(&zip_path).map_err(|e| e.to_string())?;
-    let mut zip = zip::ZipWriter::new(file);
-    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+// Shortcut registrations that failed since startup
+#[tauri::command]
+fn get_shortcut_registration_failures() -> Vec<ShortcutRegistrationFailure> {
+    SHORTCUT_FAILURES.lock().unwrap().clone()
+}
 
-    // Add data.json
-    zip.start_file("data.json", options)
+// 当前注册的置顶快捷键
+static ALWAYS_ON_TOP_SHORTCUT: Mutex<Option<Shortcut>> = Mutex::new(None);
+static ALWAYS_ON_TOP_SHORTCUT_HELD: AtomicBool = AtomicBool::new(false);
+
+// 注册切换置顶快捷键
+fn register_always_on_top_shortcut(app: &AppHandle, shortcut: Shortcut) -> Result<(), String> {
+    app.global_shortcut()
+        .on_shortcut(shortcut, move |app, _shortcut, event| match event.state {
+            ShortcutState::Pressed => {
+                if !ALWAYS_ON_TOP_SHORTCUT_HELD.swap(true, Ordering::Relaxed) {
+                    toggle_always_on_top(app);
+                }
+            }
+            ShortcutState::Released => {
+                ALWAYS_ON_TOP_SHORTCUT_HELD.store(false, Ordering::Relaxed);
+            }
+        })
         .map_err(|e| e.to_string())?;
-    zip.write_all(data.as_bytes()).map_err(|e| e.to_string())?;
 
-    // Add images directory
-    if images_path.exists() {
-        for entry in WalkDir::new(&images_path)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            let path = entry.path();
-            if path.is_file() {
-                if let Ok(relative) = path.strip_prefix(&images_path) {
-                    let zip_path_str =
-                        format!("images/{}", relative.to_string_lossy().replace('\\', "/"));
+    *ALWAYS_ON_TOP_SHORTCUT.lock().unwrap() = Some(shortcut);
+    Ok(())
+}
 
-                    zip.start_file(&zip_path_str, options)
-                        .map_err(|e| e.to_string())?;
-                    let mut file = fs::File::open(path).map_err(|e| e.to_string())?;
-                    let mut buffer = Vec::new();
-                    file.read_to_end(&mut buffer).map_err(|e| e.to_string())?;
-                    zip.write_all(&buffer).map_err(|e| e.to_string())?;
+// 修改切换置顶快捷键；传入空值取消注册，注册失败时恢复原快捷键
+#[tauri::command]
+async fn set_always_on_top_shortcut(
+    app: AppHandle,
+    shortcut: Option<String>,
+) -> Result<(), String> {
+    let shortcut = shortcut
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+    let parsed = shortcut.as_deref().map(parse_shortcut).transpose()?;
+    if parsed.is_some() && parsed == *TOGGLE_SHORTCUT.lock().unwrap() {
+        return Err("Shortcut is already used for Show/Hide".to_string());
+    }
+    let previous = *ALWAYS_ON_TOP_SHORTCUT.lock().unwrap();
+
+    if previous != parsed {
+        if let Some(previous) = previous {
+            let _ = app.global_shortcut().unregister(previous);
+            *ALWAYS_ON_TOP_SHORTCUT.lock().unwrap() = None;
+        }
+        ALWAYS_ON_TOP_SHORTCUT_HELD.store(false, Ordering::Relaxed);
+
+        if let Some(parsed) = parsed {
+            if let Err(e) = register_always_on_top_shortcut(&app, parsed) {
+                if let Some(previous) = previous {
+                    let _ = register_always_on_top_shortcut(&app, previous);
                 }
+                return Err(format!(
+                    "Failed to register shortcut {}: {}. Another application may be using it.",
+                    shortcut.unwrap_or_default(),
+                    e
+                ));
             }
         }
     }
 
-    zip.finish().map_err(|e| e.to_string())?;
-
-    // Clean up old backups
-    cleanup_old_backups(&backup_dir, settings.max_backups)?;
-
-    Ok(filename)
-}
-
-// Get backup list
-#[tauri::command]
-async fn get_backup_list(app: AppHandle) -> Result<Vec<BackupInfo>, String> {
+    // Save to store
     let store = app.store("config.json").map_err(|e| e.to_string())?;
-    let settings: BackupSettings = store
-        .get("backupSettings")
+    let mut settings: Settings = store
+        .get("settings")
         .and_then(|v| serde_json::from_value(v).ok())
         .unwrap_or_default();
+    settings.always_on_top_shortcut = shortcut;
+    store.set("settings", serde_json::to_value(&settings).unwrap());
+    persist_store(&app, &store)?;
 
-    let backup_dir = match resolve_backup_directory(settings.backup_directory) {
-        Some(dir) => dir,
-        None => return Ok(vec![]),
-    };
+    Ok(())
+}
 
-    let backup_path = std::path::Path::new(&backup_dir);
-    if !backup_path.exists() {
-        return Ok(vec![]);
+#[tauri::command]
+async fn set_close_to_tray(app: AppHandle, enabled: bool) -> Result<(), String> {
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    let mut settings: Settings = store
+        .get("settings")
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    settings.close_to_tray = enabled;
+    store.set("settings", serde_json::to_value(&settings).unwrap());
+    persist_store(&app, &store)?;
+
+    Ok(())
+}
+
+// Pretty-print backend-written JSON snapshots with sorted keys
+#[tauri::command]
+async fn set_pretty_json(app: AppHandle, enabled: bool) -> Result<(), String> {
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    let mut settings: Settings = store
+        .get("settings")
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    settings.pretty_json = enabled;
+    store.set("settings", serde_json::to_value(&settings).unwrap());
+    persist_store(&app, &store)?;
+
+    Ok(())
+}
+
+// Limit the threads used for image hashing (0 = all cores)
+#[tauri::command]
+async fn set_hash_threads(app: AppHandle, threads: u32) -> Result<(), String> {
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    let mut settings: Settings = store
+        .get("settings")
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    settings.hash_threads = threads;
+    store.set("settings", serde_json::to_value(&settings).unwrap());
+    persist_store(&app, &store)?;
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn set_save_interval(app: AppHandle, interval_ms: u64) -> Result<(), String> {
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    let mut settings: Settings = store
+        .get("settings")
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    settings.save_interval_ms = interval_ms;
+    store.set("settings", serde_json::to_value(&settings).unwrap());
+    persist_store(&app, &store)?;
+
+    Ok(())
+}
+
+// 系统字体列表缓存：首次调用时枚举，之后在整个进程生命周期内复用，refresh_system_fonts 可清空
+struct FontCache(Mutex<Option<Vec<String>>>);
+
+#[tauri::command]
+fn get_system_fonts(cache: State<'_, FontCache>) -> Vec<String> {
+    cache
+        .0
+        .lock()
+        .unwrap()
+        .get_or_insert_with(enumerate_system_fonts)
+        .clone()
+}
+
+// Clear the font cache so newly installed fonts show up
+#[tauri::command]
+fn refresh_system_fonts(cache: State<'_, FontCache>) {
+    *cache.0.lock().unwrap() = None;
+}
+
+fn enumerate_system_fonts() -> Vec<String> {
+    let source = SystemSource::new();
+    let mut fonts: Vec<String> = source
+        .all_families()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|name| !name.starts_with('.') && !name.starts_with('@'))
+        .collect();
+    fonts.sort();
+    fonts.dedup();
+
+    // Fallback if empty
+    if fonts.is_empty() {
+        fonts = vec![
+            "SimSun".to_string(),
+            "Microsoft YaHei".to_string(),
+            "SimHei".to_string(),
+            "KaiTi".to_string(),
+            "FangSong".to_string(),
+            "Consolas".to_string(),
+            "Segoe UI".to_string(),
+        ];
     }
 
-    let mut backups = Vec::new();
-    for entry in fs::read_dir(&backup_dir).map_err(|e| e.to_string())? {
-        let entry = entry.map_err(|e| e.to_string())?;
-        let filename = entry.file_name().to_string_lossy().to_string();
+    fonts
+}
 
-        if filename.starts_with("litepad_backup_") && filename.ends_with(".zip") {
-            let metadata = entry.metadata().map_err(|e| e.to_string())?;
-            let created_at = metadata
-                .created()
-                .map(|t| t.duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64)
-                .unwrap_or(0);
+// 字体信息（用于字体选择器的分类）
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FontInfo {
+    pub name: String,
+    pub is_monospace: bool,
+    pub is_serif: bool,
+}
 
-            backups.push(BackupInfo {
-                filename,
-                created_at,
-                size: metadata.len(),
-            });
+// 通过 OS/2 表中的 PANOSE 判断是否为衬线字体；没有 PANOSE 信息时退回到名称判断
+fn font_is_serif(font: &font_kit::font::Font, name: &str) -> bool {
+    let panose = font
+        .load_font_table(u32::from_be_bytes(*b"OS/2"))
+        .filter(|table| table.len() > 33)
+        .map(|table| (table[32], table[33]));
+    match panose {
+        // bFamilyType 2 = Latin Text；bSerifStyle 11-13 为无衬线，2-10 为衬线
+        Some((2, serif_style)) if serif_style >= 2 => (2..=10).contains(&serif_style),
+        _ => {
+            let lower = name.to_lowercase();
+            lower.contains("serif") && !lower.contains("sans")
         }
     }
+}
 
-    // Sort by created_at descending
-    backups.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+fn inspect_font_family(source: &SystemSource, name: &str) -> (bool, bool) {
+    let font = source
+        .select_family_by_name(name)
+        .ok()
+        .and_then(|family| family.fonts().first().and_then(|handle| handle.load().ok()));
+    match font {
+        Some(font) => (font.is_monospace(), font_is_serif(&font, name)),
+        None => (false, false),
+    }
+}
 
-    Ok(backups)
+// 与 get_system_fonts 相同的列表，附带等宽/衬线标记；无法加载的字体标记为 false
+#[tauri::command]
+fn get_system_fonts_detailed(cache: State<'_, FontCache>) -> Vec<FontInfo> {
+    let source = SystemSource::new();
+    get_system_fonts(cache)
+        .into_iter()
+        .map(|name| {
+            let (is_monospace, is_serif) = inspect_font_family(&source, &name);
+            FontInfo {
+                name,
+                is_monospace,
+                is_serif,
+            }
+        })
+        .collect()
 }
 
-// Restore backup
+// 在系统字体中查找（忽略大小写与首尾空白），返回系统中的实际名称
+fn find_installed_font(cache: &State<'_, FontCache>, family: &str) -> Option<String> {
+    let family = family.trim();
+    if family.is_empty() {
+        return None;
+    }
+    let source = SystemSource::new();
+    let name = cache
+        .0
+        .lock()
+        .unwrap()
+        .get_or_insert_with(enumerate_system_fonts)
+        .iter()
+        .find(|name| name.eq_ignore_ascii_case(family))
+        .cloned()
+        .unwrap_or_else(|| family.to_string());
+    source.select_family_by_name(&name).ok().map(|_| name)
+}
+
+// 根据名称推断字体的通用类别
+fn generic_font_family(family: &str) -> FamilyName {
+    let lower = family.to_lowercase();
+    let is_any = |words: &[&str]| words.iter().any(|w| lower.contains(w));
+    if is_any(&["mono", "code", "consol", "courier", "terminal", "fixed"]) {
+        FamilyName::Monospace
+    } else if is_any(&[
+        "sans",
+        "gothic",
+        "hei",
+        "yahei",
+        "arial",
+        "helvetica",
+        "segoe",
+    ]) {
+        FamilyName::SansSerif
+    } else if is_any(&[
+        "serif", "times", "georgia", "garamond", "song", "ming", "kai", "fang",
+    ]) {
+        FamilyName::Serif
+    } else {
+        FamilyName::SansSerif
+    }
+}
+
+// Check whether a font family is installed (case-insensitive)
 #[tauri::command]
-async fn restore_backup(
-    state: State<'_, Mutex<AppState>>,
-    app: AppHandle,
-    filename: String,
-) -> Result<String, String> {
+fn has_font(cache: State<'_, FontCache>, family: String) -> bool {
+    find_installed_font(&cache, &family).is_some()
+}
+
+// 返回已安装的同名字体；不存在时按通用类别（等宽/衬线/无衬线）选择最接近的字体
+#[tauri::command]
+fn find_closest_font(cache: State<'_, FontCache>, family: String) -> Option<String> {
+    if let Some(name) = find_installed_font(&cache, &family) {
+        return Some(name);
+    }
+    SystemSource::new()
+        .select_best_match(&[generic_font_family(&family)], &Properties::new())
+        .ok()
+        .and_then(|handle| handle.load().ok())
+        .map(|font| font.family_name())
+}
+
+// 图片保存结果，包含 hash 和 URL
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaveImageResult {
+    pub hash: String,
+    pub url: String,
+    pub size: usize,
+    pub ext: String,
+}
+
+// 计算 SHA-256 hash
+fn hash_bytes(buffer: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(buffer);
+    hex::encode(hasher.finalize())
+}
+
+// 计算图片的 hash / URL 信息（不写入）
+fn describe_image(buffer: &[u8], ext: &str) -> SaveImageResult {
+    let hash = hash_bytes(buffer);
+    SaveImageResult {
+        url: format!("litepad://images/{}{}", hash, ext),
+        hash,
+        size: buffer.len(),
+        ext: ext.to_string(),
+    }
+}
+
+// 以 hash 为文件名保存图片（去重），返回 litepad:// 协议 URL
+fn store_image_bytes(
+    state: &mut AppState,
+    buffer: Vec<u8>,
+    ext: &str,
+) -> Result<SaveImageResult, String> {
+    let result = describe_image(&buffer, ext);
+    let filename = format!("{}{}", result.hash, ext);
+    let file_path = image_file_path(&state.images_path, &filename);
+
+    if state.ephemeral {
+        state.memory_images.insert(filename, buffer);
+    } else if !file_path.exists() {
+        // 如果文件已存在（相同 hash），直接返回，不重复写入；
+        // 原子写入保证 {hash}{ext} 文件的内容始终与 hash 一致
+        write_image_file(&state.images_path, &filename, &buffer)?;
+    }
+
+    Ok(result)
+}
+
+// 根据文件头识别图片格式
+fn sniff_image_extension(buffer: &[u8]) -> Option<&'static str> {
+    if buffer.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]) {
+        Some(".png")
+    } else if buffer.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some(".jpg")
+    } else if buffer.starts_with(b"GIF87a") || buffer.starts_with(b"GIF89a") {
+        Some(".gif")
+    } else if buffer.len() >= 12 && &buffer[0..4] == b"RIFF" && &buffer[8..12] == b"WEBP" {
+        Some(".webp")
+    } else if buffer.starts_with(b"BM") {
+        Some(".bmp")
+    } else if buffer.len() >= 12
+        && &buffer[4..8] == b"ftyp"
+        && matches!(&buffer[8..12], b"avif" | b"avis")
+    {
+        Some(".avif")
+    } else if buffer.starts_with(&[0x00, 0x00, 0x01, 0x00]) {
+        Some(".ico")
+    } else if buffer.starts_with(b"II*\0") || buffer.starts_with(b"MM\0*") {
+        Some(".tiff")
+    } else {
+        None
+    }
+}
+
+// 以文件头校正调用方传入的扩展名；声称是图片但无法识别时拒绝
+fn validated_image_extension(buffer: &[u8], ext: &str) -> Result<String, String> {
+    let claimed = ext.trim_start_matches('.').to_ascii_lowercase();
+    match sniff_image_extension(buffer) {
+        // jpg / jpeg 视为同一种格式，保留调用方的写法
+        Some(".jpg") if claimed == "jpeg" => Ok(".jpeg".to_string()),
+        Some(".tiff") if claimed == "tif" => Ok(".tif".to_string()),
+        Some(sniffed) => Ok(sniffed.to_string()),
+        None if claimed == "svg" => {
+            let head = String::from_utf8_lossy(&buffer[..buffer.len().min(1024)]).to_lowercase();
+            if head.contains("<svg") {
+                Ok(".svg".to_string())
+            } else {
+                Err("Buffer is not a valid SVG image".to_string())
+            }
+        }
+        None if image_mime_type(&claimed).starts_with("image/") => Err(format!(
+            "Buffer does not match any known image type (claimed .{})",
+            claimed
+        )),
+        None => Ok(ext.to_string()),
+    }
+}
+
+const DEFAULT_MAX_IMAGE_BYTES: u64 = 64 * 1024 * 1024;
+const MIN_MAX_IMAGE_BYTES: u64 = 1024 * 1024;
+
+fn max_image_bytes(app: &AppHandle) -> u64 {
+    app.store("config.json")
+        .ok()
+        .and_then(|store| store.get("settings"))
+        .and_then(|v| serde_json::from_value::<Settings>(v).ok())
+        .map(|s| s.max_image_bytes)
+        .unwrap_or(DEFAULT_MAX_IMAGE_BYTES)
+}
+
+// 写入前检查大小上限
+fn check_image_size(app: &AppHandle, len: usize) -> Result<(), String> {
+    if len as u64 > max_image_bytes(app) {
+        return Err("IMAGE_TOO_LARGE".to_string());
+    }
+    Ok(())
+}
+
+// Set the maximum size of a single saved image; returns the applied limit
+#[tauri::command]
+async fn set_image_limits(app: AppHandle, max_image_bytes: u64) -> Result<u64, String> {
+    let max_image_bytes = max_image_bytes.max(MIN_MAX_IMAGE_BYTES);
     let store = app.store("config.json").map_err(|e| e.to_string())?;
-    let settings: BackupSettings = store
-        .get("backupSettings")
+    let mut settings: Settings = store
+        .get("settings")
         .and_then(|v| serde_json::from_value(v).ok())
         .unwrap_or_default();
+    settings.max_image_bytes = max_image_bytes;
+    store.set("settings", serde_json::to_value(&settings).unwrap());
+    persist_store(&app, &store)?;
 
-    let backup_dir = resolve_backup_directory(settings.backup_directory)
-        .ok_or("Backup directory not configured")?;
-    let zip_path = std::path::Path::new(&backup_dir).join(&filename);
+    Ok(max_image_bytes)
+}
 
-    let file = fs::File::open(&zip_path).map_err(|e| e.to_string())?;
-    let mut archive = ZipArchive::new(file).map_err(|e| e.to_string())?;
+#[tauri::command]
+async fn save_image(
+    app: AppHandle,
+    buffer: Vec<u8>,
+    ext: String,
+) -> Result<SaveImageResult, String> {
+    check_image_size(&app, buffer.len())?;
+    // 大图的 hash 计算和写盘放到阻塞线程池，避免占用异步执行器
+    tauri::async_runtime::spawn_blocking(move || {
+        let ext = validated_image_extension(&buffer, &ext)?;
+        let state = app.state::<Mutex<AppState>>();
+        let mut state = state.lock().unwrap();
+        store_image_bytes(&mut state, buffer, &ext)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
 
-    // Extract data.json
-    let mut data_json = String::new();
+// Save several images (buffer, ext) under a single lock; results keep the input order
+#[tauri::command]
+async fn save_images_batch(
+    app: AppHandle,
+    state: State<'_, Mutex<AppState>>,
+    items: Vec<(Vec<u8>, String)>,
+) -> Result<Vec<SaveImageResult>, String> {
+    let limit = max_image_bytes(&app);
+    if items.iter().any(|(buffer, _)| buffer.len() as u64 > limit) {
+        return Err("IMAGE_TOO_LARGE".to_string());
+    }
+    // 先校验全部类型，任一无效则整批不写入
+    let items = items
+        .into_iter()
+        .map(|(buffer, ext)| validated_image_extension(&buffer, &ext).map(|ext| (buffer, ext)))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let mut state = state.lock().unwrap();
+    items
+        .into_iter()
+        .map(|(buffer, ext)| store_image_bytes(&mut state, buffer, &ext))
+        .collect()
+}
+
+// Compute the hash/URL an image would get from save_image, without writing it
+#[tauri::command]
+fn compute_image_hash(buffer: Vec<u8>, ext: String) -> Result<SaveImageResult, String> {
+    let ext = validated_image_extension(&buffer, &ext)?;
+    Ok(describe_image(&buffer, &ext))
+}
+
+const MAX_REMOTE_IMAGE_BYTES: usize = 32 * 1024 * 1024;
+
+fn is_offline_mode(app: &AppHandle) -> bool {
+    app.store("config.json")
+        .ok()
+        .and_then(|store| store.get("settings"))
+        .and_then(|v| serde_json::from_value::<Settings>(v).ok())
+        .map(|s| s.offline_mode)
+        .unwrap_or(false)
+}
+
+// 下载远程图片，校验类型和大小，返回 (内容, 扩展名)
+async fn fetch_remote_image(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<(Vec<u8>, String), String> {
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err(format!("Unsupported URL: {}", url));
+    }
+
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("Network error: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP error: {}", response.status()));
+    }
+    if response
+        .content_length()
+        .is_some_and(|len| len as usize > MAX_REMOTE_IMAGE_BYTES)
     {
-        let mut data_file = archive.by_name("data.json").map_err(|e| e.to_string())?;
-        data_file
-            .read_to_string(&mut data_json)
-            .map_err(|e| e.to_string())?;
+        return Err("IMAGE_TOO_LARGE".to_string());
     }
 
-    // Extract images
-    let images_path = {
-        let state = state.lock().unwrap();
-        state.images_path.clone()
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_lowercase();
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Network error: {}", e))?;
+    if bytes.len() > MAX_REMOTE_IMAGE_BYTES {
+        return Err("IMAGE_TOO_LARGE".to_string());
+    }
+
+    // 以文件头为准，SVG 只能依赖 Content-Type
+    let ext = match sniff_image_extension(&bytes) {
+        Some(ext) => ext.to_string(),
+        None if content_type.starts_with("image/svg+xml") => ".svg".to_string(),
+        None => return Err("NOT_AN_IMAGE".to_string()),
     };
 
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i).map_err(|e| e.to_string())?;
-        let name = file.name().to_string();
+    Ok((bytes.to_vec(), ext))
+}
+
+fn image_download_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .user_agent("LitePad")
+        .timeout(std::time::Duration::from_secs(30))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+// Download a remote image and store it locally
+#[tauri::command]
+async fn download_and_store_image(
+    state: State<'_, Mutex<AppState>>,
+    app: AppHandle,
+    url: String,
+) -> Result<SaveImageResult, String> {
+    if is_offline_mode(&app) {
+        return Err("OFFLINE_MODE".to_string());
+    }
+
+    let client = image_download_client()?;
+    let (buffer, ext) = fetch_remote_image(&client, &url).await?;
+    check_image_size(&app, buffer.len())?;
+
+    let mut state = state.lock().unwrap();
+    store_image_bytes(&mut state, buffer, &ext)
+}
+
+// 批量下载结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteImageResult {
+    pub source_url: String,
+    pub result: Option<SaveImageResult>,
+    pub error: Option<String>,
+}
+
+// Download all remote images of a note; failures are reported per URL
+#[tauri::command]
+async fn download_and_store_images(
+    state: State<'_, Mutex<AppState>>,
+    app: AppHandle,
+    urls: Vec<String>,
+) -> Result<Vec<RemoteImageResult>, String> {
+    if is_offline_mode(&app) {
+        return Err("OFFLINE_MODE".to_string());
+    }
+
+    let client = image_download_client()?;
+    let mut results = Vec::with_capacity(urls.len());
+    for url in urls {
+        let saved = match fetch_remote_image(&client, &url).await {
+            // 与 save_image 相同，受用户设置的单张图片上限约束
+            Ok((buffer, ext)) => check_image_size(&app, buffer.len()).and_then(|_| {
+                let mut state = state.lock().unwrap();
+                store_image_bytes(&mut state, buffer, &ext)
+            }),
+            Err(e) => Err(e),
+        };
+        results.push(match saved {
+            Ok(result) => RemoteImageResult {
+                source_url: url,
+                result: Some(result),
+                error: None,
+            },
+            Err(error) => RemoteImageResult {
+                source_url: url,
+                result: None,
+                error: Some(error),
+            },
+        });
+    }
+
+    Ok(results)
+}
+
+// 根据 hash 获取图片路径（用于 litepad:// 协议）
+#[tauri::command]
+fn get_image_path(state: State<'_, Mutex<AppState>>, hash: String, ext: String) -> Result<String, String> {
+    let state = state.lock().unwrap();
+    let filename = format!("{}{}", hash, ext);
+    let file_path = image_file_path(&state.images_path, &filename);
+
+    if file_path.exists() {
+        Ok(file_path.to_string_lossy().to_string())
+    } else {
+        Err(format!("Image not found: {}", filename))
+    }
+}
+
+// 检查图片是否存在
+#[tauri::command]
+fn has_image(
+    state: State<'_, Mutex<AppState>>,
+    packs: State<'_, PackIndexCache>,
+    hash: String,
+    ext: String,
+) -> bool {
+    let state = state.lock().unwrap();
+    let filename = format!("{}{}", hash, ext);
+    let file_path = image_file_path(&state.images_path, &filename);
+    state.memory_images.contains_key(&filename)
+        || file_path.exists()
+        || packs
+            .load(&state.images_path)
+            .entries
+            .contains_key(&filename)
+}
+
+// 保存从服务器下载的图片，返回其 litepad:// URL
+#[tauri::command]
+async fn save_downloaded_image(
+    app: AppHandle,
+    state: State<'_, Mutex<AppState>>,
+    hash: String,
+    ext: String,
+    buffer: Vec<u8>,
+) -> Result<String, String> {
+    check_image_size(&app, buffer.len())?;
+    let mut state = state.lock().unwrap();
+    let filename = format!("{}{}", hash, ext);
+
+    // 验证 hash
+    let mut hasher = Sha256::new();
+    hasher.update(&buffer);
+    let computed_hash = hex::encode(hasher.finalize());
+
+    if computed_hash != hash {
+        return Err(format!(
+            "Hash mismatch: expected {}, got {}",
+            hash, computed_hash
+        ));
+    }
+
+    // 统一返回 litepad:// URL：临时模式下图片只在内存中，没有磁盘路径
+    let url = format!("litepad://images/{}", filename);
+    if state.ephemeral {
+        state.memory_images.insert(filename, buffer);
+    } else {
+        // hash 已校验，原子写入避免留下截断的文件
+        write_image_file(&state.images_path, &filename, &buffer)?;
+    }
+
+    Ok(url)
+}
+
+// 读取本地图片文件（用于上传到服务器）
+#[tauri::command]
+fn read_image(
+    state: State<'_, Mutex<AppState>>,
+    packs: State<'_, PackIndexCache>,
+    hash: String,
+    ext: String,
+) -> Result<Vec<u8>, String> {
+    let state = state.lock().unwrap();
+    let filename = format!("{}{}", hash, ext);
+
+    if let Some(content) = state.memory_images.get(&filename) {
+        return Ok(content.clone());
+    }
+    read_stored_image(&state.images_path, &packs, &filename)
+        .ok_or_else(|| format!("Image not found: {}", filename))
+}
+
+// 用外部程序打开 pack 内图片时的落盘目录；外部程序可能仍在读取，所以只在启动时清空
+fn external_image_dir() -> PathBuf {
+    std::env::temp_dir().join("litepad-images")
+}
+
+// Open a stored image with the OS default application
+#[tauri::command]
+fn open_image_externally(
+    app: AppHandle,
+    state: State<'_, Mutex<AppState>>,
+    packs: State<'_, PackIndexCache>,
+    hash: String,
+    ext: String,
+) -> Result<(), String> {
+    let filename = format!("{}{}", hash, ext);
+    if !is_safe_image_filename(&filename) {
+        return Err(format!("Invalid image name: {}", filename));
+    }
+
+    let path = {
+        let state = state.lock().unwrap();
+        // 临时模式下图片只在内存中，交给外部程序就必须落盘
+        if state.ephemeral {
+            return Err("EPHEMERAL_MODE".to_string());
+        }
+        let file_path = image_file_path(&state.images_path, &filename);
+        if file_path.is_file() {
+            file_path
+        } else {
+            // 打包的图片先写出到专用临时目录，下次启动时清空
+            let content = read_stored_image(&state.images_path, &packs, &filename)
+                .ok_or_else(|| format!("Image not found: {}", filename))?;
+            let temp_dir = external_image_dir();
+            fs::create_dir_all(&temp_dir).map_err(|e| e.to_string())?;
+            let temp_path = temp_dir.join(&filename);
+            write_atomic(&temp_path, &content)?;
+            temp_path
+        }
+    };
+
+    #[allow(deprecated)]
+    app.shell()
+        .open(path.to_string_lossy().to_string(), None)
+        .map_err(|e| e.to_string())
+}
+
+// 在文件管理器中显示并选中文件（Explorer /select、Finder -R、Linux 走 FileManager1）
+fn reveal_in_file_manager(app: &AppHandle, path: &std::path::Path) -> Result<(), String> {
+    #[cfg(target_os = "windows")]
+    {
+        let _ = app;
+        std::process::Command::new("explorer")
+            .arg(format!("/select,{}", path.display()))
+            .spawn()
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let _ = app;
+        std::process::Command::new("open")
+            .arg("-R")
+            .arg(path)
+            .spawn()
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        let shown = tauri::Url::from_file_path(path)
+            .ok()
+            .and_then(|uri| {
+                std::process::Command::new("dbus-send")
+                    .args([
+                        "--session",
+                        "--print-reply",
+                        "--dest=org.freedesktop.FileManager1",
+                        "--type=method_call",
+                        "/org/freedesktop/FileManager1",
+                        "org.freedesktop.FileManager1.ShowItems",
+                    ])
+                    .arg(format!("array:string:{}", uri))
+                    .arg("string:")
+                    .output()
+                    .ok()
+            })
+            .is_some_and(|output| output.status.success());
+        if shown {
+            return Ok(());
+        }
+        // 没有支持该接口的文件管理器时只打开所在目录
+        let parent = path.parent().unwrap_or(path);
+        #[allow(deprecated)]
+        app.shell()
+            .open(parent.to_string_lossy().to_string(), None)
+            .map_err(|e| e.to_string())
+    }
+}
+
+// Show a stored image file selected in the OS file manager
+#[tauri::command]
+fn reveal_image_in_folder(
+    app: AppHandle,
+    state: State<'_, Mutex<AppState>>,
+    hash: String,
+    ext: String,
+) -> Result<(), String> {
+    let filename = format!("{}{}", hash, ext);
+    if !is_safe_image_filename(&filename) {
+        return Err(format!("Invalid image name: {}", filename));
+    }
+
+    let path = {
+        let state = state.lock().unwrap();
+        image_file_path(&state.images_path, &filename)
+    };
+    if !path.is_file() {
+        return Err(format!("Image file not found: {}", path.display()));
+    }
+    reveal_in_file_manager(&app, &path)
+}
+
+// 解析 litepad://images/{hash}{ext}，返回 (hash, ext)
+fn parse_image_url(url: &str) -> Option<(String, String)> {
+    let filename = url.strip_prefix("litepad://images/")?;
+    if !is_safe_image_filename(filename) {
+        return None;
+    }
+    let (hash, ext) = match filename.find('.') {
+        Some(dot) => (&filename[..dot], &filename[dot..]),
+        None => (filename, ""),
+    };
+    if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some((hash.to_lowercase(), ext.to_string()))
+}
+
+fn image_ref_from_url(url: &str) -> Result<(String, String), String> {
+    parse_image_url(url.trim()).ok_or_else(|| format!("Invalid image URL: {}", url))
+}
+
+// URL variant of get_image_path
+#[tauri::command]
+fn get_image_path_by_url(state: State<'_, Mutex<AppState>>, url: String) -> Result<String, String> {
+    let (hash, ext) = image_ref_from_url(&url)?;
+    get_image_path(state, hash, ext)
+}
+
+// URL variant of has_image; malformed URLs are reported as missing
+#[tauri::command]
+fn has_image_by_url(
+    state: State<'_, Mutex<AppState>>,
+    packs: State<'_, PackIndexCache>,
+    url: String,
+) -> bool {
+    match image_ref_from_url(&url) {
+        Ok((hash, ext)) => has_image(state, packs, hash, ext),
+        Err(_) => false,
+    }
+}
+
+// URL variant of read_image
+#[tauri::command]
+fn read_image_by_url(
+    state: State<'_, Mutex<AppState>>,
+    packs: State<'_, PackIndexCache>,
+    url: String,
+) -> Result<Vec<u8>, String> {
+    let (hash, ext) = image_ref_from_url(&url)?;
+    read_image(state, packs, hash, ext)
+}
+
+// URL variant of save_downloaded_image
+#[tauri::command]
+async fn save_downloaded_image_by_url(
+    app: AppHandle,
+    state: State<'_, Mutex<AppState>>,
+    url: String,
+    buffer: Vec<u8>,
+) -> Result<String, String> {
+    let (hash, ext) = image_ref_from_url(&url)?;
+    save_downloaded_image(app, state, hash, ext, buffer).await
+}
+
+// 协议请求中的图片文件名只允许单层文件名（拒绝路径分隔符、..、转义字符）
+fn is_safe_image_filename(filename: &str) -> bool {
+    !filename.is_empty()
+        && !filename.contains("..")
+        && !filename.contains(['/', '\\', '%', ':', '\0'])
+}
+
+// 解析单段 Range 头（bytes=start-end / bytes=start- / bytes=-suffix），返回闭区间
+// 外层 None 表示无需分段（头缺失或不支持的格式），内层 None 表示范围无法满足
+fn parse_byte_range(header: &str, len: u64) -> Option<Option<(u64, u64)>> {
+    let spec = header.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+
+    let range = if start.is_empty() {
+        let suffix: u64 = end.parse().ok()?;
+        (suffix > 0 && len > 0).then(|| (len.saturating_sub(suffix), len - 1))
+    } else {
+        let start: u64 = start.parse().ok()?;
+        let end = match end {
+            "" => len.saturating_sub(1),
+            end => end.parse::<u64>().ok()?.min(len.saturating_sub(1)),
+        };
+        (start < len && start <= end).then_some((start, end))
+    };
+    Some(range)
+}
+
+// 构建图片响应：带 Range 时返回 206 分段内容
+fn image_response(
+    content: Vec<u8>,
+    mime_type: &str,
+    range: Option<&str>,
+    immutable: bool,
+) -> Response<Vec<u8>> {
+    let len = content.len() as u64;
+    let builder = Response::builder()
+        .header("Content-Type", mime_type)
+        .header("Accept-Ranges", "bytes");
+
+    match range.and_then(|header| parse_byte_range(header, len)) {
+        Some(Some((start, end))) => builder
+            .status(206)
+            .header("Content-Range", format!("bytes {}-{}/{}", start, end, len))
+            .body(content[start as usize..=end as usize].to_vec())
+            .expect("Failed to build partial response"),
+        Some(None) => builder
+            .status(416)
+            .header("Content-Range", format!("bytes */{}", len))
+            .body(Vec::new())
+            .expect("Failed to build 416 response"),
+        None => {
+            let builder = if immutable {
+                builder.header("Cache-Control", "max-age=31536000, immutable")
+            } else {
+                builder
+            };
+            builder
+                .status(200)
+                .body(content)
+                .expect("Failed to build response")
+        }
+    }
+}
+
+// 根据扩展名获取 MIME 类型
+fn image_mime_type(ext: &str) -> &'static str {
+    match ext {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "bmp" => "image/bmp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "tif" | "tiff" => "image/tiff",
+        _ => "application/octet-stream",
+    }
+}
+
+// 扩展名未知时按文件头猜测 MIME 类型
+fn image_content_type(ext: &str, content: &[u8]) -> &'static str {
+    match image_mime_type(&ext.to_ascii_lowercase()) {
+        "application/octet-stream" => sniff_image_extension(content)
+            .map(|sniffed| image_mime_type(&sniffed[1..]))
+            .unwrap_or("application/octet-stream"),
+        mime_type => mime_type,
+    }
+}
+
+// 小图片打包：images/pack-NNN.litepack 顺序追加原始字节，images/pack-index.json 记录位置
+const PACK_INDEX_FILE: &str = "pack-index.json";
+const PACK_EXTENSION: &str = "litepack";
+const DEFAULT_PACK_THRESHOLD: u64 = 16 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct PackEntry {
+    pack: String,
+    offset: u64,
+    length: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct PackIndex {
+    // key: {hash}{ext}
+    entries: BTreeMap<String, PackEntry>,
+}
+
+fn load_pack_index(images_path: &std::path::Path) -> PackIndex {
+    fs::read_to_string(images_path.join(PACK_INDEX_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+// 解析后的 pack 索引缓存：按 images 目录和 index 文件的修改时间/大小判断是否过期，
+// 避免 has_image、litepad:// 等高频请求每次都重新解析 JSON
+struct PackIndexCache(Mutex<Option<CachedPackIndex>>);
+
+struct CachedPackIndex {
+    images_path: PathBuf,
+    stamp: Option<(std::time::SystemTime, u64)>,
+    index: Arc<PackIndex>,
+}
+
+impl PackIndexCache {
+    fn load(&self, images_path: &std::path::Path) -> Arc<PackIndex> {
+        let stamp = fs::metadata(images_path.join(PACK_INDEX_FILE))
+            .ok()
+            .and_then(|meta| Some((meta.modified().ok()?, meta.len())));
+        let mut cached = self.0.lock().unwrap();
+        if let Some(cached) = cached
+            .as_ref()
+            .filter(|c| c.images_path == images_path && c.stamp == stamp)
+        {
+            return cached.index.clone();
+        }
+        let index = Arc::new(load_pack_index(images_path));
+        *cached = Some(CachedPackIndex {
+            images_path: images_path.to_path_buf(),
+            stamp,
+            index: index.clone(),
+        });
+        index
+    }
+
+    // 本进程改写索引后立即失效，不依赖文件系统的时间精度
+    fn invalidate(&self) {
+        *self.0.lock().unwrap() = None;
+    }
+}
+
+fn save_pack_index(images_path: &std::path::Path, index: &PackIndex) -> Result<(), String> {
+    let content = serde_json::to_string(index).map_err(|e| e.to_string())?;
+    write_atomic(&images_path.join(PACK_INDEX_FILE), content.as_bytes())
+}
+
+fn is_pack_artifact(name: &str) -> bool {
+    name.starts_with(PACK_INDEX_FILE) || name.ends_with(&format!(".{}", PACK_EXTENSION))
+}
+
+// 分片布局：images/{hash 前两位}/{hash}{ext}，images 目录下存在 .sharded 标记时启用
+const SHARDED_LAYOUT_MARKER: &str = ".sharded";
+
+fn is_sharded_layout(images_path: &std::path::Path) -> bool {
+    images_path.join(SHARDED_LAYOUT_MARKER).exists()
+}
+
+fn image_shard(filename: &str) -> Option<&str> {
+    filename
+        .get(..2)
+        .filter(|prefix| prefix.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+// 按当前布局计算图片散文件的路径，所有读写都经过这里
+fn image_file_path(images_path: &std::path::Path, filename: &str) -> PathBuf {
+    let flat = images_path.join(filename);
+    match image_shard(filename) {
+        Some(shard) if is_sharded_layout(images_path) => {
+            let sharded = images_path.join(shard).join(filename);
+            // 未能迁移的平铺文件仍可读取
+            if !sharded.exists() && flat.exists() {
+                flat
+            } else {
+                sharded
+            }
+        }
+        _ => flat,
+    }
+}
+
+// 写入图片散文件（分片目录按需创建）
+fn write_image_file(
+    images_path: &std::path::Path,
+    filename: &str,
+    content: &[u8],
+) -> Result<PathBuf, String> {
+    let path = image_file_path(images_path, filename);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    write_atomic(&path, content)?;
+    Ok(path)
+}
+
+// 所有散文件图片（平铺和分片子目录），返回 (文件名, 路径)
+fn loose_image_files(images_path: &std::path::Path) -> Vec<(String, PathBuf)> {
+    let mut files = Vec::new();
+    let Ok(read_dir) = fs::read_dir(images_path) else {
+        return files;
+    };
+    for entry in read_dir.filter_map(|e| e.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = entry.path();
+        if path.is_file() {
+            if !is_pack_artifact(&name) && name != SHARDED_LAYOUT_MARKER {
+                files.push((name, path));
+            }
+        } else if path.is_dir() && name.len() == 2 && image_shard(&name).is_some() {
+            let shard_files = fs::read_dir(&path)
+                .into_iter()
+                .flatten()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.path().is_file())
+                .map(|e| (e.file_name().to_string_lossy().to_string(), e.path()));
+            files.extend(shard_files);
+        }
+    }
+    files
+}
+
+// 备份中的图片按目标目录的当前布局放置（备份可能来自另一种布局）
+fn restored_image_path(images_path: &std::path::Path, relative: &str) -> Option<PathBuf> {
+    let name = relative.rsplit('/').next()?;
+    if name == SHARDED_LAYOUT_MARKER || !is_safe_image_filename(name) {
+        return None;
+    }
+    if is_pack_artifact(name) {
+        return Some(images_path.join(name));
+    }
+    Some(image_file_path(images_path, name))
+}
+
+fn read_from_pack(images_path: &std::path::Path, entry: &PackEntry) -> Option<Vec<u8>> {
+    let mut file = fs::File::open(images_path.join(&entry.pack)).ok()?;
+    file.seek(SeekFrom::Start(entry.offset)).ok()?;
+    let mut buffer = vec![0u8; entry.length as usize];
+    file.read_exact(&mut buffer).ok()?;
+    Some(buffer)
+}
+
+// 按文件名读取图片：优先散文件，其次 pack
+fn read_stored_image(
+    images_path: &std::path::Path,
+    packs: &PackIndexCache,
+    filename: &str,
+) -> Option<Vec<u8>> {
+    if let Ok(content) = fs::read(image_file_path(images_path, filename)) {
+        return Some(content);
+    }
+    packs
+        .load(images_path)
+        .entries
+        .get(filename)
+        .and_then(|entry| read_from_pack(images_path, entry))
+}
+
+// 当前存储中所有图片文件名（散文件、pack、内存）
+fn stored_image_names(state: &AppState) -> Vec<String> {
+    let mut names: Vec<String> = loose_image_files(&state.images_path)
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    names.extend(load_pack_index(&state.images_path).entries.into_keys());
+    names.extend(state.memory_images.keys().cloned());
+    names
+}
+
+// 按扩展名统计
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionStats {
+    pub extension: String,
+    pub count: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LargestImage {
+    pub hash: String,
+    pub filename: String,
+    pub size: u64,
+}
+
+// 图片存储占用统计
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageStorageStats {
+    pub total_count: u64,
+    pub total_bytes: u64,
+    pub by_extension: Vec<ExtensionStats>,
+    pub largest: Option<LargestImage>,
+}
+
+// Summarize disk usage of stored images (loose files, packs and in-memory images)
+#[tauri::command]
+fn get_image_storage_stats(state: State<'_, Mutex<AppState>>) -> ImageStorageStats {
+    let (images_path, memory_sizes) = {
+        let state = state.lock().unwrap();
+        let memory_sizes: Vec<(String, u64)> = state
+            .memory_images
+            .iter()
+            .map(|(name, bytes)| (name.clone(), bytes.len() as u64))
+            .collect();
+        (state.images_path.clone(), memory_sizes)
+    };
+
+    let mut images: Vec<(String, u64)> = WalkDir::new(&images_path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            if is_pack_artifact(&name) || name == SHARDED_LAYOUT_MARKER {
+                return None;
+            }
+            let size = e.metadata().map(|m| m.len()).unwrap_or(0);
+            Some((name, size))
+        })
+        .collect();
+    images.extend(
+        load_pack_index(&images_path)
+            .entries
+            .into_iter()
+            .map(|(name, entry)| (name, entry.length)),
+    );
+    images.extend(memory_sizes);
+
+    let mut by_extension: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+    for (name, size) in &images {
+        let extension = name
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_lowercase())
+            .unwrap_or_default();
+        let stats = by_extension.entry(extension).or_default();
+        stats.0 += 1;
+        stats.1 += size;
+    }
+    let largest = images.iter().max_by_key(|(_, size)| *size);
+
+    ImageStorageStats {
+        total_count: images.len() as u64,
+        total_bytes: images.iter().map(|(_, size)| size).sum(),
+        by_extension: by_extension
+            .into_iter()
+            .map(|(extension, (count, bytes))| ExtensionStats {
+                extension,
+                count,
+                bytes,
+            })
+            .collect(),
+        largest: largest.map(|(name, size)| LargestImage {
+            hash: name.split('.').next().unwrap_or_default().to_string(),
+            filename: name.clone(),
+            size: *size,
+        }),
+    }
+}
+
+// 图片存储校验结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageStoreVerifyResult {
+    pub checked: u64,
+    // 内容与文件名中的 hash 不一致的图片
+    pub corrupt: Vec<String>,
+    // 已移入 data/quarantine 的文件（打包的图片无法单独隔离）
+    pub quarantined: Vec<String>,
+}
+
+// 文件名形如 {64 位 hex}{ext} 时返回其中的 hash
+fn hash_from_image_filename(filename: &str) -> Option<String> {
+    parse_image_url(&format!("litepad://images/{}", filename)).map(|(hash, _)| hash)
+}
+
+// Recompute the SHA-256 of every stored image and report files whose content doesn't match their name
+#[tauri::command]
+async fn verify_image_store(
+    app: AppHandle,
+    state: State<'_, Mutex<AppState>>,
+    quarantine: Option<bool>,
+) -> Result<ImageStoreVerifyResult, String> {
+    let (images_path, data_path, memory_images) = {
+        let state = state.lock().unwrap();
+        (
+            state.images_path.clone(),
+            state.data_path.clone(),
+            state.memory_images.clone(),
+        )
+    };
+
+    let mut checked = 0;
+    let mut corrupt = Vec::new();
+    let mut quarantined = Vec::new();
+    let quarantine_path = data_path.join("quarantine");
+
+    // 散文件并行计算 hash，隔离操作仍按顺序进行
+    let loose_files: Vec<(String, PathBuf, String)> = loose_image_files(&images_path)
+        .into_iter()
+        .filter_map(|(filename, path)| {
+            let expected = hash_from_image_filename(&filename)?;
+            Some((filename, path, expected))
+        })
+        .collect();
+    checked += loose_files.len() as u64;
+    let mismatched: Vec<(String, PathBuf)> = hashing_pool(&app)?.install(|| {
+        loose_files
+            .into_par_iter()
+            .filter(|(_, path, expected)| hash_file(path).ok().as_ref() != Some(expected))
+            .map(|(filename, path, _)| (filename, path))
+            .collect()
+    });
+
+    for (filename, path) in mismatched {
+        if quarantine.unwrap_or(false) {
+            fs::create_dir_all(&quarantine_path).map_err(|e| e.to_string())?;
+            if fs::rename(&path, quarantine_path.join(&filename)).is_ok() {
+                quarantined.push(filename.clone());
+            }
+        }
+        corrupt.push(filename);
+    }
+
+    // 打包存储与内存中的图片只检查不隔离
+    for (filename, entry) in load_pack_index(&images_path).entries {
+        let Some(expected) = hash_from_image_filename(&filename) else {
+            continue;
+        };
+        checked += 1;
+        let actual = read_from_pack(&images_path, &entry).map(|content| hash_bytes(&content));
+        if actual.as_deref() != Some(expected.as_str()) {
+            corrupt.push(filename);
+        }
+    }
+    for (filename, content) in &memory_images {
+        if let Some(expected) = hash_from_image_filename(filename) {
+            checked += 1;
+            if hash_bytes(content) != expected {
+                corrupt.push(filename.clone());
+            }
+        }
+    }
+
+    corrupt.sort();
+    Ok(ImageStoreVerifyResult {
+        checked,
+        corrupt,
+        quarantined,
+    })
+}
+
+// 引用修复结果：status 为 ok / fixed / missing
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageReferenceFix {
+    pub old_url: String,
+    pub new_url: Option<String>,
+    pub status: String,
+}
+
+// 存储中的图片（按 {hash}{ext} 命名）
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredImage {
+    pub hash: String,
+    pub ext: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredImageList {
+    pub images: Vec<StoredImage>,
+    // 不符合命名规则的文件
+    pub unrecognized: Vec<String>,
+}
+
+// List every image in the store (loose, packed and in-memory) with its hash, extension and size
+#[tauri::command]
+fn list_stored_images(state: State<'_, Mutex<AppState>>) -> StoredImageList {
+    let state = state.lock().unwrap();
+
+    let mut files: Vec<(String, u64)> = loose_image_files(&state.images_path)
+        .into_iter()
+        .map(|(name, path)| {
+            let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            (name, size)
+        })
+        .collect();
+    files.extend(
+        load_pack_index(&state.images_path)
+            .entries
+            .into_iter()
+            .map(|(name, entry)| (name, entry.length)),
+    );
+    files.extend(
+        state
+            .memory_images
+            .iter()
+            .map(|(name, content)| (name.clone(), content.len() as u64)),
+    );
+
+    let mut list = StoredImageList {
+        images: Vec::new(),
+        unrecognized: Vec::new(),
+    };
+    for (name, size) in files {
+        match parse_image_url(&format!("litepad://images/{}", name)) {
+            Some((hash, ext)) if !ext.is_empty() => {
+                list.images.push(StoredImage { hash, ext, size })
+            }
+            _ => list.unrecognized.push(name),
+        }
+    }
+    list.images
+        .sort_by(|a, b| a.hash.cmp(&b.hash).then_with(|| a.ext.cmp(&b.ext)));
+    list.unrecognized.sort();
+    list
+}
+
+// images 目录监听：外部工具（同步盘等）增删图片时通知前端，批量变化合并为一次事件
+const IMAGE_WATCH_DEBOUNCE_MS: u64 = 800;
+
+struct ImageWatcherState(Mutex<Option<notify::RecommendedWatcher>>);
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImagesChanged {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+// 只关心图片文件本身，忽略临时文件、打包文件和布局标记
+fn watched_image_name(path: &std::path::Path) -> Option<String> {
+    let name = path.file_name()?.to_string_lossy().to_string();
+    let ignored = name.starts_with('.')
+        || name.ends_with(".tmp")
+        || name.ends_with(".part")
+        || is_pack_artifact(&name)
+        || !is_safe_image_filename(&name);
+    (!ignored).then_some(name)
+}
+
+fn start_image_watcher(app: &AppHandle) -> Result<(), String> {
+    use notify::Watcher;
+
+    let images_path = {
+        let state = app.state::<Mutex<AppState>>();
+        let state = state.lock().unwrap();
+        if state.ephemeral {
+            return Ok(());
+        }
+        state.images_path.clone()
+    };
+
+    let (tx, rx) = std::sync::mpsc::channel::<PathBuf>();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            for path in event.paths {
+                let _ = tx.send(path);
+            }
+        }
+    })
+    .map_err(|e| e.to_string())?;
+    watcher
+        .watch(&images_path, notify::RecursiveMode::Recursive)
+        .map_err(|e| e.to_string())?;
+
+    // watcher 被释放后 tx 随之释放，线程自然退出
+    let app_handle = app.clone();
+    std::thread::spawn(move || {
+        let debounce = std::time::Duration::from_millis(IMAGE_WATCH_DEBOUNCE_MS);
+        while let Ok(first) = rx.recv() {
+            let mut paths = BTreeSet::from([first]);
+            let mut disconnected = false;
+            loop {
+                match rx.recv_timeout(debounce) {
+                    Ok(path) => {
+                        paths.insert(path);
+                    }
+                    Err(std::sync::mpsc::RecvTimeoutError::Timeout) => break,
+                    Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+                        disconnected = true;
+                        break;
+                    }
+                }
+            }
+
+            // 以最终状态为准：重命名、先删后建等都归为新增或删除
+            let mut change = ImagesChanged {
+                added: Vec::new(),
+                removed: Vec::new(),
+            };
+            for path in &paths {
+                let Some(name) = watched_image_name(path) else {
+                    continue;
+                };
+                if path.is_file() {
+                    change.added.push(name);
+                } else if !path.exists() {
+                    change.removed.push(name);
+                }
+            }
+            if !change.added.is_empty() || !change.removed.is_empty() {
+                let _ = app_handle.emit("images-changed", change);
+            }
+            if disconnected {
+                break;
+            }
+        }
+    });
+
+    *app.state::<ImageWatcherState>().0.lock().unwrap() = Some(watcher);
+    Ok(())
+}
+
+// Enable or disable watching the images directory for external changes
+#[tauri::command]
+async fn set_image_watcher(app: AppHandle, enabled: bool) -> Result<(), String> {
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    let mut settings: Settings = store
+        .get("settings")
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    settings.watch_images = enabled;
+    store.set("settings", serde_json::to_value(&settings).unwrap());
+    persist_store(&app, &store)?;
+
+    // 先停止旧的监听，避免重复事件
+    app.state::<ImageWatcherState>().0.lock().unwrap().take();
+    if enabled {
+        start_image_watcher(&app)?;
+    }
+    Ok(())
+}
+
+// Map note image URLs to their current location in the store
+#[tauri::command]
+fn reconcile_image_references(
+    state: State<'_, Mutex<AppState>>,
+    urls: Vec<String>,
+) -> Vec<ImageReferenceFix> {
+    let state = state.lock().unwrap();
+
+    let names = stored_image_names(&state);
+    // hash -> 实际文件名
+    let mut by_hash: HashMap<String, &String> = HashMap::new();
+    for name in &names {
+        let hash = name.split('.').next().unwrap_or("").to_lowercase();
+        by_hash.entry(hash).or_insert(name);
+    }
+
+    urls.into_iter()
+        .map(|old_url| {
+            let (hash, ext) = match parse_image_url(&old_url) {
+                Some(parsed) => parsed,
+                None => {
+                    return ImageReferenceFix {
+                        old_url,
+                        new_url: None,
+                        status: "missing".to_string(),
+                    }
+                }
+            };
+            let filename = format!("{}{}", hash, ext);
+
+            if names.contains(&filename) {
+                return ImageReferenceFix {
+                    new_url: Some(old_url.clone()),
+                    old_url,
+                    status: "ok".to_string(),
+                };
+            }
+
+            // 同 hash 不同扩展名（或位于 pack 中）时给出修正后的 URL
+            match by_hash.get(&hash) {
+                Some(name) => ImageReferenceFix {
+                    old_url,
+                    new_url: Some(format!("litepad://images/{}", name)),
+                    status: "fixed".to_string(),
+                },
+                None => ImageReferenceFix {
+                    old_url,
+                    new_url: None,
+                    status: "missing".to_string(),
+                },
+            }
+        })
+        .collect()
+}
+
+// 打包结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackResult {
+    pub packed_count: u32,
+    pub packed_bytes: u64,
+    pub pack_file: Option<String>,
+}
+
+// Bundle small loose images into a new pack file
+#[tauri::command]
+fn pack_small_images(
+    state: State<'_, Mutex<AppState>>,
+    packs: State<'_, PackIndexCache>,
+    threshold_bytes: Option<u64>,
+) -> Result<PackResult, String> {
+    let state = state.lock().unwrap();
+    if state.ephemeral {
+        return Err("EPHEMERAL_MODE".to_string());
+    }
+    let images_path = &state.images_path;
+    let threshold = threshold_bytes.unwrap_or(DEFAULT_PACK_THRESHOLD);
+    let mut index = load_pack_index(images_path);
+
+    let candidates: Vec<(String, PathBuf, u64)> = loose_image_files(images_path)
+        .into_iter()
+        .filter_map(|(name, path)| {
+            let size = fs::metadata(&path).ok()?.len();
+            (size < threshold).then_some((name, path, size))
+        })
+        .collect();
+
+    if candidates.is_empty() {
+        return Ok(PackResult {
+            packed_count: 0,
+            packed_bytes: 0,
+            pack_file: None,
+        });
+    }
+
+    // 下一个可用的 pack 编号
+    let next_number = (0..)
+        .find(|n| {
+            !images_path
+                .join(format!("pack-{:03}.{}", n, PACK_EXTENSION))
+                .exists()
+        })
+        .unwrap_or(0);
+    let pack_name = format!("pack-{:03}.{}", next_number, PACK_EXTENSION);
+    let mut pack_file =
+        fs::File::create(images_path.join(&pack_name)).map_err(|e| e.to_string())?;
+
+    let mut offset = 0u64;
+    let mut packed = Vec::new();
+    for (name, path, _) in &candidates {
+        let content = fs::read(path).map_err(|e| e.to_string())?;
+        pack_file.write_all(&content).map_err(|e| e.to_string())?;
+        index.entries.insert(
+            name.clone(),
+            PackEntry {
+                pack: pack_name.clone(),
+                offset,
+                length: content.len() as u64,
+            },
+        );
+        offset += content.len() as u64;
+        packed.push(path.clone());
+    }
+    pack_file.sync_all().map_err(|e| e.to_string())?;
+
+    // 索引落盘后才删除散文件
+    save_pack_index(images_path, &index)?;
+    packs.invalidate();
+    for path in &packed {
+        let _ = fs::remove_file(path);
+    }
+
+    Ok(PackResult {
+        packed_count: packed.len() as u32,
+        packed_bytes: offset,
+        pack_file: Some(pack_name),
+    })
+}
+
+// Extract all packed images back into loose files and remove the packs
+#[tauri::command]
+fn unpack_images(
+    state: State<'_, Mutex<AppState>>,
+    packs: State<'_, PackIndexCache>,
+) -> Result<u32, String> {
+    let state = state.lock().unwrap();
+    if state.ephemeral {
+        return Err("EPHEMERAL_MODE".to_string());
+    }
+    let images_path = &state.images_path;
+    let index = load_pack_index(images_path);
+
+    let mut count = 0;
+    for (name, entry) in &index.entries {
+        if !image_file_path(images_path, name).exists() {
+            let content = read_from_pack(images_path, entry)
+                .ok_or_else(|| format!("Failed to read {} from {}", name, entry.pack))?;
+            write_image_file(images_path, name, &content)?;
+        }
+        count += 1;
+    }
+
+    let _ = fs::remove_file(images_path.join(PACK_INDEX_FILE));
+    packs.invalidate();
+    for entry in fs::read_dir(images_path)
+        .map_err(|e| e.to_string())?
+        .filter_map(|e| e.ok())
+    {
+        if is_pack_artifact(&entry.file_name().to_string_lossy()) {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+
+    Ok(count)
+}
+
+// 分片迁移结果；校验失败的文件保留在原位置
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShardMigrationResult {
+    pub moved: u32,
+    pub failed: Vec<String>,
+}
+
+// Switch the image store to the sharded layout (images/ab/{hash}{ext}), moving and verifying loose files
+#[tauri::command]
+fn migrate_to_sharded_layout(
+    app: AppHandle,
+    state: State<'_, Mutex<AppState>>,
+) -> Result<ShardMigrationResult, String> {
+    let pool = hashing_pool(&app)?;
+    let state = state.lock().unwrap();
+    if state.ephemeral {
+        return Err("EPHEMERAL_MODE".to_string());
+    }
+    let images_path = &state.images_path;
+    fs::create_dir_all(images_path).map_err(|e| e.to_string())?;
+    fs::write(images_path.join(SHARDED_LAYOUT_MARKER), b"").map_err(|e| e.to_string())?;
+
+    // (文件名, 当前路径, 分片路径, 期望 hash)
+    let candidates: Vec<(String, PathBuf, PathBuf, String)> = loose_image_files(images_path)
+        .into_iter()
+        .filter_map(|(filename, path)| {
+            let dest = images_path.join(image_shard(&filename)?).join(&filename);
+            let expected = hash_from_image_filename(&filename)?;
+            (path != dest).then_some((filename, path, dest, expected))
+        })
+        .collect();
+    let matches = |path: &std::path::Path, expected: &str| {
+        hash_file(path).is_ok_and(|actual| actual == expected)
+    };
+
+    // 移动前并行校验内容与文件名中的 hash 一致
+    let verified: Vec<bool> = pool.install(|| {
+        candidates
+            .par_iter()
+            .map(|(_, path, _, expected)| matches(path, expected))
+            .collect()
+    });
+
+    let mut moved = 0;
+    let mut failed = Vec::new();
+    let mut renamed = Vec::new();
+    for ((filename, path, dest, expected), ok) in candidates.into_iter().zip(verified) {
+        if !ok {
+            failed.push(filename);
+            continue;
+        }
+        fs::create_dir_all(dest.parent().unwrap()).map_err(|e| e.to_string())?;
+        if dest.exists() && matches(&dest, &expected) {
+            // 分片目录中已有相同内容的文件
+            fs::remove_file(&path).map_err(|e| e.to_string())?;
+            moved += 1;
+            continue;
+        }
+        fs::rename(&path, &dest).map_err(|e| e.to_string())?;
+        renamed.push((filename, dest, expected));
+    }
+
+    // 移动后再次校验
+    let verified: Vec<bool> = pool.install(|| {
+        renamed
+            .par_iter()
+            .map(|(_, dest, expected)| matches(dest, expected))
+            .collect()
+    });
+    for ((filename, _, _), ok) in renamed.into_iter().zip(verified) {
+        if ok {
+            moved += 1;
+        } else {
+            failed.push(filename);
+        }
+    }
+
+    Ok(ShardMigrationResult { moved, failed })
+}
+
+// 图片回收站（data_path/trash/），保留原 hash 文件名；删除时间记录在 trash.json
+const TRASH_DIR: &str = "trash";
+const TRASH_INDEX_FILE: &str = "trash.json";
+
+fn load_trash_index(trash_path: &std::path::Path) -> BTreeMap<String, i64> {
+    fs::read_to_string(trash_path.join(TRASH_INDEX_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_trash_index(
+    trash_path: &std::path::Path,
+    index: &BTreeMap<String, i64>,
+) -> Result<(), String> {
+    let content = serde_json::to_vec(index).map_err(|e| e.to_string())?;
+    write_atomic(&trash_path.join(TRASH_INDEX_FILE), &content)
+}
+
+// 将图片移入回收站；打包存储的图片取出内容后从 pack 索引中移除
+fn move_image_to_trash(
+    state: &AppState,
+    packs: &PackIndexCache,
+    filename: &str,
+) -> Result<(), String> {
+    if state.ephemeral {
+        return Err("EPHEMERAL_MODE".to_string());
+    }
+    let trash_path = state.data_path.join(TRASH_DIR);
+    fs::create_dir_all(&trash_path).map_err(|e| e.to_string())?;
+
+    let source = image_file_path(&state.images_path, filename);
+    if source.is_file() {
+        fs::rename(&source, trash_path.join(filename)).map_err(|e| e.to_string())?;
+    } else {
+        let mut pack_index = load_pack_index(&state.images_path);
+        let entry = pack_index
+            .entries
+            .get(filename)
+            .ok_or_else(|| format!("Image not found: {}", filename))?;
+        let content = read_from_pack(&state.images_path, entry)
+            .ok_or_else(|| format!("Failed to read {} from {}", filename, entry.pack))?;
+        write_atomic(&trash_path.join(filename), &content)?;
+        pack_index.entries.remove(filename);
+        save_pack_index(&state.images_path, &pack_index)?;
+        packs.invalidate();
+    }
+
+    let mut index = load_trash_index(&trash_path);
+    index.insert(filename.to_string(), Local::now().timestamp_millis());
+    save_trash_index(&trash_path, &index)
+}
+
+// 删除时间；索引缺失时以文件时间代替
+fn trashed_at(index: &BTreeMap<String, i64>, filename: &str, metadata: &fs::Metadata) -> i64 {
+    index.get(filename).copied().unwrap_or_else(|| {
+        metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(0)
+    })
+}
+
+// 回收站中的图片
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashedImage {
+    pub hash: String,
+    pub ext: String,
+    pub size: u64,
+    // 删除时间（毫秒）
+    pub deleted_at: i64,
+}
+
+// Move a stored image to the trash instead of deleting it
+#[tauri::command]
+fn trash_image(
+    state: State<'_, Mutex<AppState>>,
+    packs: State<'_, PackIndexCache>,
+    hash: String,
+    ext: String,
+) -> Result<(), String> {
+    let filename = format!("{}{}", hash, ext);
+    if !is_safe_image_filename(&filename) {
+        return Err(format!("Invalid image name: {}", filename));
+    }
+    let state = state.lock().unwrap();
+    move_image_to_trash(&state, &packs, &filename)
+}
+
+// List trashed images, newest first
+#[tauri::command]
+fn list_trashed_images(state: State<'_, Mutex<AppState>>) -> Vec<TrashedImage> {
+    let trash_path = state.lock().unwrap().data_path.join(TRASH_DIR);
+    let index = load_trash_index(&trash_path);
+
+    let mut images: Vec<TrashedImage> = fs::read_dir(&trash_path)
+        .map(|read_dir| {
+            read_dir
+                .filter_map(|e| e.ok())
+                .filter_map(|e| {
+                    let filename = e.file_name().to_string_lossy().to_string();
+                    let (hash, ext) = parse_image_url(&format!("litepad://images/{}", filename))?;
+                    let metadata = e.metadata().ok()?;
+                    let deleted_at = trashed_at(&index, &filename, &metadata);
+                    Some(TrashedImage {
+                        hash,
+                        ext,
+                        size: metadata.len(),
+                        deleted_at,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    images.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+    images
+}
+
+// Move a trashed image back into the image store
+#[tauri::command]
+fn restore_trashed_image(
+    state: State<'_, Mutex<AppState>>,
+    hash: String,
+    ext: String,
+) -> Result<String, String> {
+    let filename = format!("{}{}", hash, ext);
+    if !is_safe_image_filename(&filename) {
+        return Err(format!("Invalid image name: {}", filename));
+    }
+    let state = state.lock().unwrap();
+    let trash_path = state.data_path.join(TRASH_DIR);
+    let trashed = trash_path.join(&filename);
+    if !trashed.is_file() {
+        return Err(format!("Image not in trash: {}", filename));
+    }
+
+    let dest = image_file_path(&state.images_path, &filename);
+    if dest.exists() {
+        // 相同 hash 的图片已重新保存过，回收站中的副本可直接删除
+        fs::remove_file(&trashed).map_err(|e| e.to_string())?;
+    } else {
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        fs::rename(&trashed, &dest).map_err(|e| e.to_string())?;
+    }
+
+    let mut index = load_trash_index(&trash_path);
+    index.remove(&filename);
+    save_trash_index(&trash_path, &index)?;
+
+    Ok(format!("litepad://images/{}", filename))
+}
+
+// Permanently delete trashed images older than the given number of days (all when omitted)
+#[tauri::command]
+fn empty_trash(
+    state: State<'_, Mutex<AppState>>,
+    older_than_days: Option<u32>,
+) -> Result<u32, String> {
+    let trash_path = state.lock().unwrap().data_path.join(TRASH_DIR);
+    let cutoff = older_than_days
+        .map(|days| Local::now().timestamp_millis() - days as i64 * 24 * 60 * 60 * 1000);
+    let mut index = load_trash_index(&trash_path);
+
+    let mut removed = 0;
+    for entry in fs::read_dir(&trash_path)
+        .map(|read_dir| read_dir.filter_map(|e| e.ok()).collect::<Vec<_>>())
+        .unwrap_or_default()
+    {
+        let filename = entry.file_name().to_string_lossy().to_string();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if filename == TRASH_INDEX_FILE || !metadata.is_file() {
+            continue;
+        }
+        let deleted_at = trashed_at(&index, &filename, &metadata);
+        if cutoff.is_some_and(|cutoff| deleted_at > cutoff) {
+            continue;
+        }
+        if fs::remove_file(entry.path()).is_ok() {
+            index.remove(&filename);
+            removed += 1;
+        }
+    }
+
+    if trash_path.exists() {
+        save_trash_index(&trash_path, &index)?;
+    }
+    Ok(removed)
+}
+
+// 重新压缩结果；新文件不更小时 skipped 为 true，返回原图信息
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecompressResult {
+    pub hash: String,
+    pub ext: String,
+    pub url: String,
+    pub saved_bytes: u64,
+    pub skipped: bool,
+}
+
+// 按目标格式重新编码：jpeg 使用 quality，webp 为无损编码，png 使用最高压缩
+fn encode_image(
+    image: &image::DynamicImage,
+    format: &str,
+    quality: u8,
+) -> Result<(Vec<u8>, &'static str), String> {
+    use image::codecs::{jpeg::JpegEncoder, png, webp::WebPEncoder};
+
+    let mut output = Vec::new();
+    let ext = match format.trim_start_matches('.').to_ascii_lowercase().as_str() {
+        "jpg" | "jpeg" => {
+            // JPEG 不支持透明通道
+            JpegEncoder::new_with_quality(&mut output, quality.clamp(1, 100))
+                .encode_image(&image.to_rgb8())
+                .map_err(|e| e.to_string())?;
+            ".jpg"
+        }
+        "webp" => {
+            // WebP 编码器只接受 RGB(A)8
+            image::DynamicImage::ImageRgba8(image.to_rgba8())
+                .write_with_encoder(WebPEncoder::new_lossless(&mut output))
+                .map_err(|e| e.to_string())?;
+            ".webp"
+        }
+        "png" => {
+            image
+                .write_with_encoder(png::PngEncoder::new_with_quality(
+                    &mut output,
+                    png::CompressionType::Best,
+                    png::FilterType::Adaptive,
+                ))
+                .map_err(|e| e.to_string())?;
+            ".png"
+        }
+        other => return Err(format!("Unsupported target format: {}", other)),
+    };
+    Ok((output, ext))
+}
+
+// Re-encode a stored image to save space; the result is stored under its new hash
+#[tauri::command]
+async fn recompress_image(
+    state: State<'_, Mutex<AppState>>,
+    packs: State<'_, PackIndexCache>,
+    hash: String,
+    ext: String,
+    format: String,
+    quality: Option<u8>,
+    trash_original: Option<bool>,
+) -> Result<RecompressResult, String> {
+    let filename = format!("{}{}", hash, ext);
+    if !is_safe_image_filename(&filename) {
+        return Err(format!("Invalid image name: {}", filename));
+    }
+    let original = {
+        let state = state.lock().unwrap();
+        match state.memory_images.get(&filename) {
+            Some(content) => Some(content.clone()),
+            None => read_stored_image(&state.images_path, &packs, &filename),
+        }
+    }
+    .ok_or_else(|| format!("Image not found: {}", filename))?;
+
+    // 动图重新编码会丢失动画
+    if sniff_image_extension(&original) == Some(".gif") {
+        return Err("Animated images are not recompressed".to_string());
+    }
+    let decoded = image::load_from_memory(&original).map_err(|e| e.to_string())?;
+    let (encoded, new_ext) = encode_image(&decoded, &format, quality.unwrap_or(80))?;
+
+    if encoded.len() >= original.len() {
+        return Ok(RecompressResult {
+            url: format!("litepad://images/{}", filename),
+            hash,
+            ext,
+            saved_bytes: 0,
+            skipped: true,
+        });
+    }
+
+    let saved_bytes = (original.len() - encoded.len()) as u64;
+    let mut state = state.lock().unwrap();
+    let saved = store_image_bytes(&mut state, encoded, new_ext)?;
+    if trash_original.unwrap_or(false) && saved.hash != hash {
+        if state.ephemeral {
+            state.memory_images.remove(&filename);
+        } else {
+            move_image_to_trash(&state, &packs, &filename)?;
+        }
+    }
+
+    Ok(RecompressResult {
+        hash: saved.hash,
+        ext: saved.ext,
+        url: saved.url,
+        saved_bytes,
+        skipped: false,
+    })
+}
+
+// 缩略图缓存（data_path/thumbs/{hash}_{max_dim}{ext}），通过 litepad://thumbs/ 访问
+const MIN_THUMBNAIL_DIM: u32 = 16;
+const MAX_THUMBNAIL_DIM: u32 = 2048;
+
+// Get (and cache) a downscaled version of a stored image; images already within max_dim return their original URL
+#[tauri::command]
+async fn get_thumbnail(
+    state: State<'_, Mutex<AppState>>,
+    packs: State<'_, PackIndexCache>,
+    hash: String,
+    ext: String,
+    max_dim: u32,
+) -> Result<String, String> {
+    let filename = format!("{}{}", hash, ext);
+    if !is_safe_image_filename(&filename) {
+        return Err(format!("Invalid image name: {}", filename));
+    }
+    let original_url = format!("litepad://images/{}", filename);
+    let max_dim = max_dim.clamp(MIN_THUMBNAIL_DIM, MAX_THUMBNAIL_DIM);
+
+    // JPEG 生成 JPEG 缩略图，其余格式（可能带透明）生成 PNG
+    let thumb_ext = match ext.to_ascii_lowercase().as_str() {
+        ".jpg" | ".jpeg" => ".jpg",
+        ".svg" => return Ok(original_url),
+        _ => ".png",
+    };
+    let thumb_name = format!("{}_{}{}", hash, max_dim, thumb_ext);
+
+    let (thumbs_path, ephemeral, content) = {
+        let state = state.lock().unwrap();
+        let content = match state.memory_images.get(&filename) {
+            Some(content) => Some(content.clone()),
+            None if state.data_path.join(THUMBS_DIR).join(&thumb_name).exists() => {
+                return Ok(format!("litepad://thumbs/{}", thumb_name));
+            }
+            None => read_stored_image(&state.images_path, &packs, &filename),
+        };
+        (state.data_path.join(THUMBS_DIR), state.ephemeral, content)
+    };
+    let content = content.ok_or_else(|| format!("Image not found: {}", filename))?;
+
+    // 只读取文件头判断尺寸，不超过 max_dim 时不放大
+    let (width, height) = image::ImageReader::new(std::io::Cursor::new(&content))
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?
+        .into_dimensions()
+        .map_err(|e| e.to_string())?;
+    if (width <= max_dim && height <= max_dim) || ephemeral {
+        return Ok(original_url);
+    }
+
+    let thumbnail = image::load_from_memory(&content)
+        .map_err(|e| e.to_string())?
+        .thumbnail(max_dim, max_dim);
+    let (encoded, _) = encode_image(&thumbnail, thumb_ext, 85)?;
+
+    fs::create_dir_all(&thumbs_path).map_err(|e| e.to_string())?;
+    write_atomic(&thumbs_path.join(&thumb_name), &encoded)?;
+    Ok(format!("litepad://thumbs/{}", thumb_name))
+}
+
+// 图片元信息：仅解析文件头获取尺寸，不解码位图
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageInfo {
+    pub width: u32,
+    pub height: u32,
+    pub format: String,
+    pub size: u64,
+}
+
+fn probe_image<R: std::io::BufRead + std::io::Seek>(
+    reader: R,
+    size: u64,
+) -> Result<ImageInfo, String> {
+    let reader = image::ImageReader::new(reader)
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?;
+    let format = reader
+        .format()
+        .ok_or_else(|| "UNRECOGNIZED_IMAGE".to_string())?;
+    let (width, height) = reader.into_dimensions().map_err(|e| e.to_string())?;
+    Ok(ImageInfo {
+        width,
+        height,
+        format: format.extensions_str().first().unwrap_or(&"").to_string(),
+        size,
+    })
+}
+
+// Get an image's dimensions, format and byte size without decoding it
+#[tauri::command]
+fn get_image_info(
+    state: State<'_, Mutex<AppState>>,
+    hash: String,
+    ext: String,
+) -> Result<ImageInfo, String> {
+    let filename = format!("{}{}", hash, ext);
+    if !is_safe_image_filename(&filename) {
+        return Err(format!("Invalid image name: {}", filename));
+    }
+
+    let state = state.lock().unwrap();
+    if let Some(content) = state.memory_images.get(&filename) {
+        return probe_image(std::io::Cursor::new(content), content.len() as u64);
+    }
+    if let Ok(file) = fs::File::open(image_file_path(&state.images_path, &filename)) {
+        let size = file.metadata().map_err(|e| e.to_string())?.len();
+        return probe_image(std::io::BufReader::new(file), size);
+    }
+    let index = load_pack_index(&state.images_path);
+    let content = index
+        .entries
+        .get(&filename)
+        .and_then(|entry| read_from_pack(&state.images_path, entry))
+        .ok_or_else(|| format!("Image not found: {}", filename))?;
+    probe_image(std::io::Cursor::new(&content), content.len() as u64)
+}
+
+// 旧版本记录的图片路径可能来自任意平台，统一按当前平台的分隔符解析
+fn legacy_image_path(raw: &str) -> PathBuf {
+    #[cfg(windows)]
+    let normalized = raw.replace('/', "\\");
+    #[cfg(not(windows))]
+    let normalized = raw.replace('\\', "/");
+    // components() 会合并重复的分隔符，同时保留盘符和 UNC 前缀
+    std::path::Path::new(&normalized).components().collect()
+}
+
+// 迁移结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrateImageResult {
+    pub hash: String,
+    pub ext: String,
+    pub size: usize,
+    pub new_url: String,
+}
+
+// 读取旧图片并计算 hash，返回 (hash, ext, 内容)
+fn read_legacy_image(old_path: &str) -> Result<(String, String, Vec<u8>), String> {
+    // 尝试读取旧文件
+    let old_file = legacy_image_path(old_path);
+
+    if !old_file.exists() {
+        return Err(format!("文件不存在: {}", old_file.display()));
+    }
+
+    // 读取文件内容
+    let buffer = fs::read(&old_file).map_err(|e| format!("读取文件失败: {}", e))?;
+
+    // 计算 hash
+    let mut hasher = Sha256::new();
+    hasher.update(&buffer);
+    let hash = hex::encode(hasher.finalize());
+
+    // 获取扩展名
+    let ext = old_file
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| format!(".{}", e))
+        .unwrap_or_else(|| ".png".to_string());
+
+    Ok((hash, ext, buffer))
+}
+
+// 将读取到的旧图片写入新的存储（调用方需持有锁）
+fn store_migrated_image(
+    state: &mut AppState,
+    hash: String,
+    ext: String,
+    buffer: Vec<u8>,
+) -> Result<MigrateImageResult, String> {
+    let new_filename = format!("{}{}", hash, ext);
+    let new_path = image_file_path(&state.images_path, &new_filename);
+    let size = buffer.len();
+
+    if state.ephemeral {
+        state.memory_images.insert(new_filename, buffer);
+    } else if !new_path.exists() {
+        // 如果新文件不存在，复制过去
+        write_image_file(&state.images_path, &new_filename, &buffer)
+            .map_err(|e| format!("写入文件失败: {}", e))?;
+    }
+
+    Ok(MigrateImageResult {
+        hash: hash.clone(),
+        ext: ext.clone(),
+        size,
+        new_url: format!("litepad://images/{}{}", hash, ext),
+    })
+}
+
+// 迁移旧格式图片到新的 hash-based 格式
+#[tauri::command]
+fn migrate_old_image(
+    state: State<'_, Mutex<AppState>>,
+    old_path: String,
+) -> Result<MigrateImageResult, String> {
+    let (hash, ext, buffer) = read_legacy_image(&old_path)?;
+    let mut state = state.lock().unwrap();
+    store_migrated_image(&mut state, hash, ext, buffer)
+}
+
+// Migrate several legacy images at once; each path reports its own result
+#[tauri::command]
+fn migrate_old_images_batch(
+    state: State<'_, Mutex<AppState>>,
+    old_paths: Vec<String>,
+) -> Vec<Result<MigrateImageResult, String>> {
+    // 先在锁外读取和计算 hash，再一次性加锁写入
+    let loaded: Vec<_> = old_paths.iter().map(|p| read_legacy_image(p)).collect();
+    let mut state = state.lock().unwrap();
+    loaded
+        .into_iter()
+        .map(|item| {
+            item.and_then(|(hash, ext, buffer)| store_migrated_image(&mut state, hash, ext, buffer))
+        })
+        .collect()
+}
+
+// 批量检查旧图片是否存在
+#[tauri::command]
+fn check_old_images_exist(paths: Vec<String>) -> Vec<bool> {
+    paths
+        .iter()
+        .map(|p| legacy_image_path(p).exists())
+        .collect()
+}
+
+// 剪贴板内容类型
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClipboardKind {
+    pub has_image: bool,
+    pub has_text: bool,
+    pub has_files: bool,
+    // "image" / "text" / "files"，全部为空时为 ["none"]
+    pub kinds: Vec<String>,
+}
+
+// Windows: 只查询格式是否存在，不读取内容
+#[cfg(windows)]
+fn probe_clipboard() -> (bool, bool, bool) {
+    use windows_sys::Win32::System::DataExchange::{
+        IsClipboardFormatAvailable, RegisterClipboardFormatW,
+    };
+    const CF_BITMAP: u32 = 2;
+    const CF_DIB: u32 = 8;
+    const CF_UNICODETEXT: u32 = 13;
+    const CF_HDROP: u32 = 15;
+    const CF_DIBV5: u32 = 17;
+
+    let png_name: Vec<u16> = "PNG\0".encode_utf16().collect();
+    unsafe {
+        let cf_png = RegisterClipboardFormatW(png_name.as_ptr());
+        let has_image = [CF_BITMAP, CF_DIB, CF_DIBV5, cf_png]
+            .iter()
+            .any(|&f| f != 0 && IsClipboardFormatAvailable(f) != 0);
+        let has_text = IsClipboardFormatAvailable(CF_UNICODETEXT) != 0;
+        let has_files = IsClipboardFormatAvailable(CF_HDROP) != 0;
+        (has_image, has_text, has_files)
+    }
+}
+
+// macOS / Linux: 通过 arboard 探测（文件列表仅 Windows 支持检测）
+#[cfg(not(windows))]
+fn probe_clipboard() -> (bool, bool, bool) {
+    match arboard::Clipboard::new() {
+        Ok(mut clipboard) => {
+            let has_text = clipboard.get_text().is_ok();
+            let has_image = clipboard.get_image().is_ok();
+            (has_image, has_text, false)
+        }
+        Err(_) => (false, false, false),
+    }
+}
+
+// Inspect which kinds of content the clipboard holds, without consuming it
+#[tauri::command]
+async fn get_clipboard_kind() -> ClipboardKind {
+    let (has_image, has_text, has_files) = probe_clipboard();
+
+    let mut kinds = Vec::new();
+    if has_image {
+        kinds.push("image".to_string());
+    }
+    if has_text {
+        kinds.push("text".to_string());
+    }
+    if has_files {
+        kinds.push("files".to_string());
+    }
+    if kinds.is_empty() {
+        kinds.push("none".to_string());
+    }
+
+    ClipboardKind {
+        has_image,
+        has_text,
+        has_files,
+        kinds,
+    }
+}
+
+#[tauri::command]
+fn minimize_window(app: AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.minimize();
+    }
+}
+
+#[tauri::command]
+fn maximize_window(app: AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        if window.is_maximized().unwrap_or(false) {
+            let _ = window.unmaximize();
+        } else {
+            let _ = window.maximize();
+        }
+    }
+}
+
+#[tauri::command]
+fn close_window(app: AppHandle) {
+    let _ = flush_pending_data(&app);
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.hide();
+    }
+}
+
+// Check if path is inside installation directory
+fn is_inside_install_dir(path: &std::path::Path) -> bool {
+    if let Ok(exe_path) = std::env::current_exe() {
+        if let Some(exe_dir) = exe_path.parent() {
+            return path.starts_with(exe_dir);
+        }
+    }
+    false
+}
+
+// 复制数据目录中的全部文件，返回复制的相对路径
+fn copy_data_directory(
+    from: &std::path::Path,
+    to: &std::path::Path,
+) -> Result<Vec<PathBuf>, String> {
+    let mut copied = Vec::new();
+    for entry in WalkDir::new(from).into_iter().filter_map(|e| e.ok()) {
+        let relative = match entry.path().strip_prefix(from) {
+            Ok(relative) if !relative.as_os_str().is_empty() => relative.to_path_buf(),
+            _ => continue,
+        };
+        let dest = to.join(&relative);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&dest).map_err(|e| e.to_string())?;
+        } else if entry.file_type().is_file() {
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            fs::copy(entry.path(), &dest).map_err(|e| e.to_string())?;
+            copied.push(relative);
+        }
+    }
+    Ok(copied)
+}
+
+// Move the data directory (notes, images and sidecar files) to a new location
+#[tauri::command]
+async fn migrate_data_directory(
+    app: AppHandle,
+    state: State<'_, Mutex<AppState>>,
+    new_path: String,
+) -> Result<String, String> {
+    let new_path = PathBuf::from(new_path.trim());
+    if !new_path.is_absolute() {
+        return Err("Data directory must be an absolute path".to_string());
+    }
+    if is_inside_install_dir(&new_path) {
+        return Err("Cannot move data into the installation directory".to_string());
+    }
+    if new_path.exists()
+        && fs::read_dir(&new_path)
+            .map_err(|e| e.to_string())?
+            .next()
+            .is_some()
+    {
+        return Err("Target directory is not empty".to_string());
+    }
+
+    flush_pending_data(&app)?;
+
+    // 迁移期间持有锁，避免新图片写入旧目录
+    let mut state = state.lock().unwrap();
+    if state.ephemeral {
+        return Err("EPHEMERAL_MODE".to_string());
+    }
+    let old_path = state.data_path.clone();
+    if new_path.starts_with(&old_path) {
+        return Err(
+            "Cannot move data into a subdirectory of the current data directory".to_string(),
+        );
+    }
+    let images_relative = state
+        .images_path
+        .strip_prefix(&old_path)
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(|_| PathBuf::from("images"));
+
+    fs::create_dir_all(&new_path).map_err(|e| e.to_string())?;
+    let copied = match copy_data_directory(&old_path, &new_path) {
+        Ok(copied) => copied,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&new_path);
+            return Err(e);
+        }
+    };
+
+    // 校验复制后的图片 hash
+    for relative in copied.iter().filter(|r| r.starts_with(&images_relative)) {
+        let filename = relative.file_name().unwrap_or_default().to_string_lossy();
+        let Some(expected) = hash_from_image_filename(&filename) else {
+            continue;
+        };
+        let actual = fs::read(new_path.join(relative)).map(|content| hash_bytes(&content));
+        if actual.ok().as_deref() != Some(expected.as_str()) {
+            let _ = fs::remove_dir_all(&new_path);
+            return Err(format!(
+                "Image verification failed after copy: {}",
+                filename
+            ));
+        }
+    }
+
+    // 记录新位置，下次启动时使用
+    if let Err(e) = write_data_location_pointer(&new_path) {
+        let _ = fs::remove_dir_all(&new_path);
+        return Err(e);
+    }
+
+    state.data_path = new_path.clone();
+    state.images_path = new_path.join(&images_relative);
+    fs::create_dir_all(&state.images_path).map_err(|e| e.to_string())?;
+    drop(state);
+
+    // 新位置生效后再删除原文件
+    if let Err(e) = fs::remove_dir_all(&old_path) {
+        eprintln!("Warning: Failed to remove old data directory: {}", e);
+    }
+
+    Ok(new_path.to_string_lossy().to_string())
+}
+
+// Select backup directory with installation directory check
+#[tauri::command]
+async fn select_backup_directory(app: AppHandle) -> Result<Option<String>, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let folder = app.dialog().file().blocking_pick_folder();
+
+    match folder {
+        Some(file_path) => {
+            let path_buf = file_path.into_path().map_err(|e| e.to_string())?;
+            if is_inside_install_dir(&path_buf) {
+                Err("Cannot select installation directory as backup location".to_string())
+            } else {
+                Ok(Some(path_buf.to_string_lossy().to_string()))
+            }
+        }
+        None => Ok(None),
+    }
+}
+
+// Get backup settings
+#[tauri::command]
+async fn get_backup_settings(app: AppHandle) -> Result<BackupSettings, String> {
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    if let Some(value) = store.get("backupSettings") {
+        let mut settings: BackupSettings =
+            serde_json::from_value(value).map_err(|e| e.to_string())?;
+        if settings.backup_directory.is_none() {
+            settings.backup_directory = get_default_backup_directory();
+        }
+        Ok(settings)
+    } else {
+        Ok(BackupSettings::default())
+    }
+}
+
+// Save backup settings
+#[tauri::command]
+async fn set_backup_settings(app: AppHandle, settings: BackupSettings) -> Result<(), String> {
+    if settings
+        .auto_backup_time
+        .as_deref()
+        .is_some_and(|time| parse_backup_time(time).is_none())
+    {
+        return Err("INVALID_BACKUP_TIME".to_string());
+    }
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    store.set("backupSettings", serde_json::to_value(&settings).unwrap());
+    persist_store(&app, &store)?;
+    Ok(())
+}
+
+// Clean up old backups
+// 从文件名中解析备份时间（litepad_backup_[tag_]%Y%m%d_%H%M%S...）
+fn backup_timestamp(filename: &str) -> Option<chrono::NaiveDateTime> {
+    let rest = filename.strip_prefix("litepad_backup_")?;
+    let parse = |s: &str| chrono::NaiveDateTime::parse_from_str(s.get(..15)?, "%Y%m%d_%H%M%S").ok();
+    parse(rest).or_else(|| parse(rest.split_once('_')?.1))
+}
+
+// 文件名中的机器标识；旧格式（无标识）返回 None
+fn backup_tag(filename: &str) -> Option<String> {
+    let rest = filename.strip_prefix("litepad_backup_")?;
+    if chrono::NaiveDateTime::parse_from_str(rest.get(..15)?, "%Y%m%d_%H%M%S").is_ok() {
+        return None;
+    }
+    let (tag, _) = rest.split_once('_')?;
+    Some(tag.to_string())
+}
+
+// 标识中只保留字母、数字和 '-'（'_' 用作字段分隔）
+fn sanitize_backup_tag(value: &str) -> String {
+    value
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>()
+        .trim_matches('-')
+        .to_string()
+}
+
+fn get_hostname() -> Option<String> {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .or_else(|| {
+            std::process::Command::new("hostname")
+                .output()
+                .ok()
+                .and_then(|output| String::from_utf8(output.stdout).ok())
+        })
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+// 按设置生成本机备份的标识：prefix 与主机名用 '-' 连接
+fn backup_filename_tag(settings: &BackupSettings) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(prefix) = &settings.filename_prefix {
+        parts.push(sanitize_backup_tag(prefix));
+    }
+    if settings.hostname_in_filename {
+        if let Some(hostname) = get_hostname() {
+            parts.push(sanitize_backup_tag(&hostname));
+        }
+    }
+    parts.retain(|p| !p.is_empty());
+    (!parts.is_empty()).then(|| parts.join("-"))
+}
+
+// 增量备份依赖的 parent 链
+fn backup_ancestors<'a>(
+    names: impl Iterator<Item = &'a String>,
+    parents: &BTreeMap<String, String>,
+) -> BTreeSet<String> {
+    let mut ancestors = BTreeSet::new();
+    for name in names {
+        let mut current = name;
+        while let Some(parent) = parents.get(current) {
+            if !ancestors.insert(parent.clone()) {
+                break;
+            }
+            current = parent;
+        }
+    }
+    ancestors
+}
+
+// 依次按时间（max_age_days）、数量（max_backups）、总大小（max_total_bytes）清理旧备份；
+// 保留的增量备份依赖的 parent 链不会被删除；只清理与本机标识相同的备份
+fn cleanup_old_backups(
+    backup_dir: &str,
+    settings: &BackupSettings,
+    parents: &BTreeMap<String, String>,
+) -> Result<(), String> {
+    let tag = backup_filename_tag(settings);
+    let mut backups: Vec<(String, PathBuf, u64)> = fs::read_dir(backup_dir)
+        .map_err(|e| e.to_string())?
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            if !is_backup_filename(&name) || backup_tag(&name) != tag {
+                return None;
+            }
+            let size = e.metadata().map(|m| m.len()).unwrap_or(0);
+            Some((name, e.path(), size))
+        })
+        .collect();
+
+    // Newest first (by embedded timestamp, then filename)
+    backups.sort_by(|a, b| {
+        backup_timestamp(&b.0)
+            .cmp(&backup_timestamp(&a.0))
+            .then_with(|| b.0.cmp(&a.0))
+    });
+
+    let mut kept: Vec<&(String, PathBuf, u64)> = backups.iter().collect();
+
+    if let Some(days) = settings.max_age_days {
+        let cutoff = Local::now().naive_local() - chrono::Duration::days(days as i64);
+        // 无法解析时间的备份不按时间清理
+        kept.retain(|(name, _, _)| !matches!(backup_timestamp(name), Some(t) if t < cutoff));
+    }
+
+    kept.truncate(settings.max_backups as usize);
+
+    if let Some(max_total_bytes) = settings.max_total_bytes {
+        // 从最旧的开始删除，至少保留最新的一个
+        while kept.len() > 1 {
+            let ancestors = backup_ancestors(kept.iter().map(|(name, _, _)| name), parents);
+            let total: u64 = backups
+                .iter()
+                .filter(|(name, _, _)| {
+                    ancestors.contains(name)
+                        || kept.iter().any(|(kept_name, _, _)| kept_name == name)
+                })
+                .map(|(_, _, size)| size)
+                .sum();
+            if total <= max_total_bytes {
+                break;
+            }
+            kept.pop();
+        }
+    }
+
+    let ancestors = backup_ancestors(kept.iter().map(|(name, _, _)| name), parents);
+    let mut removed = Vec::new();
+    for (name, path, _) in &backups {
+        let retained =
+            ancestors.contains(name) || kept.iter().any(|(kept_name, _, _)| kept_name == name);
+        if !retained && fs::remove_file(path).is_ok() {
+            removed.push(name.clone());
+        }
+    }
+
+    remove_backup_labels(std::path::Path::new(backup_dir), &removed)
+}
+
+// 备份备注（备份目录下）：filename -> label，不影响文件名
+const BACKUP_LABELS_FILE: &str = "backups.json";
+
+fn load_backup_labels(backup_dir: &std::path::Path) -> BTreeMap<String, String> {
+    fs::read_to_string(backup_dir.join(BACKUP_LABELS_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_backup_labels(
+    backup_dir: &std::path::Path,
+    labels: &BTreeMap<String, String>,
+) -> Result<(), String> {
+    let content = serde_json::to_string(labels).map_err(|e| e.to_string())?;
+    write_atomic(&backup_dir.join(BACKUP_LABELS_FILE), content.as_bytes())
+}
+
+// 删除备份时同步移除备注
+fn remove_backup_labels(backup_dir: &std::path::Path, filenames: &[String]) -> Result<(), String> {
+    let mut labels = load_backup_labels(backup_dir);
+    let before = labels.len();
+    for filename in filenames {
+        labels.remove(filename);
+    }
+    if labels.len() == before {
+        return Ok(());
+    }
+    save_backup_labels(backup_dir, &labels)
+}
+
+// 加密备份：MAGIC + salt(16) + nonce 前缀(7) + 分块 AES-256-GCM 密文，密钥由口令经 PBKDF2 派生
+// 每块 64KB 明文独立认证，nonce = 前缀 + 块序号(4) + 末块标记(1)，截断或调换顺序都会认证失败
+const ENCRYPTED_BACKUP_EXTENSION: &str = ".litepad.enc";
+const ENCRYPTED_BACKUP_MAGIC: &[u8] = b"LPENC\x02";
+const BACKUP_SALT_LEN: usize = 16;
+const BACKUP_NONCE_LEN: usize = 12;
+const BACKUP_NONCE_PREFIX_LEN: usize = 7;
+const BACKUP_CHUNK_LEN: usize = 64 * 1024;
+const BACKUP_TAG_LEN: usize = 16;
+const BACKUP_KDF_ROUNDS: u32 = 600_000;
+
+fn is_backup_filename(name: &str) -> bool {
+    name.starts_with("litepad_backup_")
+        && (name.ends_with(".zip") || name.ends_with(ENCRYPTED_BACKUP_EXTENSION))
+}
+
+// 按扩展名或文件头判断是否为加密备份
+fn is_encrypted_backup(path: &std::path::Path) -> bool {
+    if path.to_string_lossy().ends_with(ENCRYPTED_BACKUP_EXTENSION) {
+        return true;
+    }
+    let mut header = [0u8; ENCRYPTED_BACKUP_MAGIC.len()];
+    fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .map(|_| header == ENCRYPTED_BACKUP_MAGIC)
+        .unwrap_or(false)
+}
+
+fn derive_backup_key(passphrase: &str, salt: &[u8]) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, BACKUP_KDF_ROUNDS, &mut key);
+    key
+}
+
+// 尽量读满 buf，返回实际读取的字节数（0 表示已到末尾）
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+fn backup_chunk_nonce(prefix: &[u8], counter: u32, last: bool) -> [u8; BACKUP_NONCE_LEN] {
+    let mut nonce = [0u8; BACKUP_NONCE_LEN];
+    nonce[..BACKUP_NONCE_PREFIX_LEN].copy_from_slice(prefix);
+    nonce[BACKUP_NONCE_PREFIX_LEN..BACKUP_NONCE_LEN - 1].copy_from_slice(&counter.to_be_bytes());
+    nonce[BACKUP_NONCE_LEN - 1] = last as u8;
+    nonce
+}
+
+// 分块加密，明文不需要整体读入内存
+fn encrypt_backup_stream(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    passphrase: &str,
+) -> Result<(), String> {
+    use aes_gcm::aead::rand_core::RngCore;
+    use aes_gcm::aead::{Aead, KeyInit, OsRng};
+
+    let mut salt = [0u8; BACKUP_SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let mut prefix = [0u8; BACKUP_NONCE_PREFIX_LEN];
+    OsRng.fill_bytes(&mut prefix);
+    let key = derive_backup_key(passphrase, &salt);
+    let cipher = aes_gcm::Aes256Gcm::new_from_slice(&key).map_err(|e| e.to_string())?;
+
+    writer
+        .write_all(ENCRYPTED_BACKUP_MAGIC)
+        .and_then(|_| writer.write_all(&salt))
+        .and_then(|_| writer.write_all(&prefix))
+        .map_err(|e| e.to_string())?;
+
+    // 多读一块用来判断当前块是否为末块
+    let mut current = vec![0u8; BACKUP_CHUNK_LEN];
+    let mut next = vec![0u8; BACKUP_CHUNK_LEN];
+    let mut current_len = read_full(reader, &mut current).map_err(|e| e.to_string())?;
+    let mut counter: u32 = 0;
+    loop {
+        let next_len = if current_len == BACKUP_CHUNK_LEN {
+            read_full(reader, &mut next).map_err(|e| e.to_string())?
+        } else {
+            0
+        };
+        let last = next_len == 0;
+        let nonce = backup_chunk_nonce(&prefix, counter, last);
+        let ciphertext = cipher
+            .encrypt(aes_gcm::Nonce::from_slice(&nonce), &current[..current_len])
+            .map_err(|e| e.to_string())?;
+        writer.write_all(&ciphertext).map_err(|e| e.to_string())?;
+        if last {
+            break;
+        }
+        std::mem::swap(&mut current, &mut next);
+        current_len = next_len;
+        counter = counter.checked_add(1).ok_or("Backup too large")?;
+    }
+    writer.flush().map_err(|e| e.to_string())
+}
+
+// 分块解密到内存；首块认证失败视为口令错误，之后的失败视为文件损坏
+fn decrypt_backup_stream(reader: &mut impl Read, passphrase: &str) -> Result<Vec<u8>, String> {
+    use aes_gcm::aead::{Aead, KeyInit};
+
+    let mut header =
+        [0u8; ENCRYPTED_BACKUP_MAGIC.len() + BACKUP_SALT_LEN + BACKUP_NONCE_PREFIX_LEN];
+    if read_full(reader, &mut header).map_err(|e| e.to_string())? != header.len()
+        || !header.starts_with(ENCRYPTED_BACKUP_MAGIC)
+    {
+        return Err("Not an encrypted LitePad backup".to_string());
+    }
+    let salt_start = ENCRYPTED_BACKUP_MAGIC.len();
+    let prefix_start = salt_start + BACKUP_SALT_LEN;
+    let key = derive_backup_key(passphrase, &header[salt_start..prefix_start]);
+    let cipher = aes_gcm::Aes256Gcm::new_from_slice(&key).map_err(|e| e.to_string())?;
+    let prefix = &header[prefix_start..];
+
+    let mut plain = Vec::new();
+    let mut current = vec![0u8; BACKUP_CHUNK_LEN + BACKUP_TAG_LEN];
+    let mut next = vec![0u8; BACKUP_CHUNK_LEN + BACKUP_TAG_LEN];
+    let mut current_len = read_full(reader, &mut current).map_err(|e| e.to_string())?;
+    let mut counter: u32 = 0;
+    loop {
+        let next_len = if current_len == current.len() {
+            read_full(reader, &mut next).map_err(|e| e.to_string())?
+        } else {
+            0
+        };
+        let last = next_len == 0;
+        let nonce = backup_chunk_nonce(prefix, counter, last);
+        let chunk = cipher
+            .decrypt(aes_gcm::Nonce::from_slice(&nonce), &current[..current_len])
+            .map_err(|_| match counter {
+                0 => "INVALID_PASSWORD".to_string(),
+                _ => "Encrypted backup is damaged".to_string(),
+            })?;
+        plain.extend_from_slice(&chunk);
+        if last {
+            return Ok(plain);
+        }
+        std::mem::swap(&mut current, &mut next);
+        current_len = next_len;
+        counter = counter
+            .checked_add(1)
+            .ok_or("Encrypted backup is damaged")?;
+    }
+}
+
+// 按 zip 读取的备份；加密备份只在内存中解密，明文不落盘
+struct BackupArchive {
+    path: PathBuf,
+    decrypted: Option<Vec<u8>>,
+}
+
+enum BackupReader<'a> {
+    File(fs::File),
+    Memory(std::io::Cursor<&'a [u8]>),
+}
+
+impl Read for BackupReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            BackupReader::File(file) => file.read(buf),
+            BackupReader::Memory(cursor) => cursor.read(buf),
+        }
+    }
+}
+
+impl Seek for BackupReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match self {
+            BackupReader::File(file) => file.seek(pos),
+            BackupReader::Memory(cursor) => cursor.seek(pos),
+        }
+    }
+}
+
+impl BackupArchive {
+    fn plain(path: &std::path::Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            decrypted: None,
+        }
+    }
+
+    fn zip(&self) -> Result<ZipArchive<BackupReader<'_>>, String> {
+        let reader = match &self.decrypted {
+            Some(bytes) => BackupReader::Memory(std::io::Cursor::new(bytes.as_slice())),
+            None => BackupReader::File(fs::File::open(&self.path).map_err(|e| e.to_string())?),
+        };
+        ZipArchive::new(reader).map_err(|e| e.to_string())
+    }
+}
+
+// 加密备份写入时的明文/密文暂存文件：位于备份目录、以点开头（不出现在备份列表中），
+// 类 Unix 系统上权限为 0600，离开作用域时删除
+const BACKUP_STAGING_PREFIX: &str = ".litepad_staging_";
+
+struct BackupStagingFile(PathBuf);
+
+impl BackupStagingFile {
+    fn create(backup_dir: &std::path::Path, extension: &str) -> Result<Self, String> {
+        let path = backup_dir.join(format!(
+            "{}{}.{}",
+            BACKUP_STAGING_PREFIX,
+            Uuid::new_v4(),
+            extension
+        ));
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options.open(&path).map_err(|e| e.to_string())?;
+        Ok(Self(path))
+    }
+}
+
+impl Drop for BackupStagingFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+// 进程被强制结束时暂存文件可能残留，下次备份前清理
+fn remove_stale_backup_staging(backup_dir: &std::path::Path) {
+    let Ok(read_dir) = fs::read_dir(backup_dir) else {
+        return;
+    };
+    for entry in read_dir.filter_map(|e| e.ok()) {
+        if entry
+            .file_name()
+            .to_string_lossy()
+            .starts_with(BACKUP_STAGING_PREFIX)
+        {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+fn open_backup_zip(
+    path: &std::path::Path,
+    passphrase: Option<&str>,
+) -> Result<BackupArchive, String> {
+    if !is_encrypted_backup(path) {
+        return Ok(BackupArchive::plain(path));
+    }
+
+    let passphrase = passphrase
+        .filter(|p| !p.is_empty())
+        .ok_or("PASSWORD_REQUIRED")?;
+    let mut file = std::io::BufReader::new(fs::File::open(path).map_err(|e| e.to_string())?);
+    let plain = decrypt_backup_stream(&mut file, passphrase)?;
+
+    Ok(BackupArchive {
+        path: path.to_path_buf(),
+        decrypted: Some(plain),
+    })
+}
+
+// 备份清单：记录各条目的 SHA-256，用于校验完整性
+const BACKUP_MANIFEST_FILE: &str = "manifest.json";
+const BACKUP_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BackupManifest {
+    format_version: u32,
+    app_version: String,
+    created_at: i64,
+    // key: zip 内路径, value: SHA-256
+    entries: BTreeMap<String, String>,
+    // 增量备份的上一级备份文件名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parent: Option<String>,
+}
+
+// 增量备份索引（data 目录下）：记录备份链已包含的图片
+const BACKUP_INDEX_FILE: &str = "backup_index.json";
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct BackupIndex {
+    // 最近一次备份，作为下一次增量备份的 parent
+    last_backup: Option<String>,
+    // 备份链中已包含的图片（images/ 下的相对路径）
+    images: BTreeSet<String>,
+    // 增量备份 -> parent
+    parents: BTreeMap<String, String>,
+}
+
+fn load_backup_index(data_path: &std::path::Path) -> BackupIndex {
+    fs::read_to_string(data_path.join(BACKUP_INDEX_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_backup_index(data_path: &std::path::Path, index: &BackupIndex) -> Result<(), String> {
+    let content = serde_json::to_string(index).map_err(|e| e.to_string())?;
+    write_atomic(&data_path.join(BACKUP_INDEX_FILE), content.as_bytes())
+}
+
+// 增量备份的基准：parent 备份及其链上已包含的图片
+struct IncrementalBase<'a> {
+    parent: &'a str,
+    images: &'a BTreeSet<String>,
+}
+
+fn read_backup_manifest<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+) -> Result<Option<BackupManifest>, String> {
+    let mut entry = match archive.by_name(BACKUP_MANIFEST_FILE) {
+        Ok(entry) => entry,
+        Err(_) => return Ok(None),
+    };
+    let mut content = String::new();
+    entry
+        .read_to_string(&mut content)
+        .map_err(|e| e.to_string())?;
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+// 备份校验结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupVerifyResult {
+    pub valid: bool,
+    pub has_manifest: bool,
+    pub format_version: Option<u32>,
+    pub app_version: Option<String>,
+    pub checked_count: usize,
+    pub mismatched: Vec<String>,
+    pub missing: Vec<String>,
+}
+
+// 按 manifest 重新计算备份内各条目的 hash；旧备份没有 manifest 时只检查 data.json 可读
+fn verify_backup_archive(backup: &BackupArchive) -> Result<BackupVerifyResult, String> {
+    let mut archive = backup.zip()?;
+
+    let manifest = read_backup_manifest(&mut archive)?;
+
+    let expected = match &manifest {
+        Some(manifest) => manifest.entries.clone(),
+        None => BTreeMap::from([("data.json".to_string(), String::new())]),
+    };
+
+    let mut mismatched = Vec::new();
+    let mut missing = Vec::new();
+    for (name, hash) in &expected {
+        let mut entry = match archive.by_name(name) {
+            Ok(entry) => entry,
+            Err(_) => {
+                missing.push(name.clone());
+                continue;
+            }
+        };
+        let mut buffer = Vec::new();
+        // 读取失败（CRC 错误、截断）同样视为不匹配
+        if entry.read_to_end(&mut buffer).is_err()
+            || (manifest.is_some() && &hash_bytes(&buffer) != hash)
+        {
+            mismatched.push(name.clone());
+        }
+    }
+
+    Ok(BackupVerifyResult {
+        valid: mismatched.is_empty() && missing.is_empty(),
+        has_manifest: manifest.is_some(),
+        format_version: manifest.as_ref().map(|m| m.format_version),
+        app_version: manifest.map(|m| m.app_version),
+        checked_count: expected.len(),
+        mismatched,
+        missing,
+    })
+}
+
+// Verify a backup against its manifest
+#[tauri::command]
+async fn verify_backup(
+    app: AppHandle,
+    filename: String,
+    passphrase: Option<String>,
+) -> Result<BackupVerifyResult, String> {
+    let backup = open_backup_zip(&backup_file_path(&app, &filename)?, passphrase.as_deref())?;
+    verify_backup_archive(&backup)
+}
+
+// 读取时同步计算 SHA-256
+struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> HashingReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    fn finish(self) -> String {
+        hex::encode(self.hasher.finalize())
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+// 流式计算文件的 SHA-256，不把整个文件读入内存
+fn hash_file(path: &std::path::Path) -> Result<String, String> {
+    let file = fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut reader = HashingReader::new(file);
+    std::io::copy(&mut reader, &mut std::io::sink()).map_err(|e| e.to_string())?;
+    Ok(reader.finish())
+}
+
+// 图片 hash 计算用的线程池；hash_threads 为 0 时使用全部核心
+fn hashing_pool(app: &AppHandle) -> Result<rayon::ThreadPool, String> {
+    let threads = app
+        .store("config.json")
+        .ok()
+        .and_then(|store| store.get("settings"))
+        .and_then(|v| serde_json::from_value::<Settings>(v).ok())
+        .map(|s| s.hash_threads)
+        .unwrap_or(0);
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads as usize)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+// 将 data.json、图片目录和附属数据写入 zip；增量备份时跳过 base 中已有的图片。
+// on_progress(current, total, file) 在每张图片写入前调用，返回 Err 时中止；返回写入的图片（images/ 下的相对路径）
+fn write_backup_archive(
+    zip_path: &std::path::Path,
+    data: &str,
+    data_path: &std::path::Path,
+    images_path: &std::path::Path,
+    sidecars: &[&str],
+    base: Option<&IncrementalBase>,
+    compression: BackupCompression,
+    pool: &rayon::ThreadPool,
+    on_progress: &dyn Fn(u64, u64, &str) -> Result<(), String>,
+) -> Result<BTreeSet<String>, String> {
+    // Create ZIP file
+    let file = fs::File::create(zip_path).map_err(|e| e.to_string())?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = compression.file_options();
+
+    // Add data.json
+    zip.start_file("data.json", options)
+        .map_err(|e| e.to_string())?;
+    zip.write_all(data.as_bytes()).map_err(|e| e.to_string())?;
+
+    let mut manifest = BackupManifest {
+        format_version: BACKUP_FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: Local::now().timestamp_millis(),
+        entries: BTreeMap::new(),
+        parent: base.map(|b| b.parent.to_string()),
+    };
+    let mut written = BTreeSet::new();
+    manifest
+        .entries
+        .insert("data.json".to_string(), hash_bytes(data.as_bytes()));
+
+    // Add images directory
+    let image_files: Vec<(PathBuf, String)> = WalkDir::new(images_path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let relative = e
+                .path()
+                .strip_prefix(images_path)
+                .ok()?
+                .to_string_lossy()
+                .replace('\\', "/");
+            if relative == SHARDED_LAYOUT_MARKER {
+                return None;
+            }
+            // 散文件按 hash 命名、内容不变；pack 文件会追加，始终写入
+            if base.is_some_and(|b| b.images.contains(&relative)) && !is_pack_artifact(&relative) {
+                return None;
+            }
+            Some((e.into_path(), relative))
+        })
+        .collect();
+
+    // hash 在线程池中并行计算，同时在当前线程顺序写入 zip（ZipWriter 不支持并发）
+    // 写入出错或备份被取消时哈希也立即停止，否则要等全部图片算完 scope 才会返回
+    let total = image_files.len() as u64;
+    let abort = AtomicBool::new(false);
+    let hashes = std::thread::scope(|scope| {
+        let hashing = scope.spawn(|| {
+            pool.install(|| {
+                image_files
+                    .par_iter()
+                    .map(|(path, _)| {
+                        if abort.load(Ordering::Relaxed) || BACKUP_CANCELLED.load(Ordering::Relaxed)
+                        {
+                            return Err("BACKUP_CANCELLED".to_string());
+                        }
+                        hash_file(path)
+                    })
+                    .collect::<Result<Vec<_>, String>>()
+            })
+        });
+
+        let copied = image_files
+            .iter()
+            .enumerate()
+            .try_for_each(|(index, (path, relative))| {
+                on_progress(index as u64 + 1, total, relative)?;
+
+                zip.start_file(format!("images/{}", relative), options)
+                    .map_err(|e| e.to_string())?;
+                let mut file = fs::File::open(path).map_err(|e| e.to_string())?;
+                std::io::copy(&mut file, &mut zip).map_err(|e| e.to_string())?;
+                Ok::<(), String>(())
+            });
+        if let Err(e) = copied {
+            abort.store(true, Ordering::Relaxed);
+            return Err(e);
+        }
+
+        hashing
+            .join()
+            .map_err(|_| "Image hashing failed".to_string())?
+    })?;
+    for ((_, relative), hash) in image_files.into_iter().zip(hashes) {
+        manifest
+            .entries
+            .insert(format!("images/{}", relative), hash);
+        written.insert(relative);
+    }
+
+    // Add sidecars (image metadata, labels, thumbnails)
+    for &sidecar in sidecars {
+        let sidecar_path = data_path.join(sidecar);
+        if sidecar_path.is_file() {
+            zip.start_file(sidecar, options)
+                .map_err(|e| e.to_string())?;
+            let mut file = fs::File::open(&sidecar_path).map_err(|e| e.to_string())?;
+            std::io::copy(&mut file, &mut zip).map_err(|e| e.to_string())?;
+        } else if sidecar_path.is_dir() {
+            for entry in WalkDir::new(&sidecar_path)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+            {
+                if let Ok(relative) = entry.path().strip_prefix(data_path) {
+                    let entry_name = relative.to_string_lossy().replace('\\', "/");
+                    zip.start_file(&entry_name, options)
+                        .map_err(|e| e.to_string())?;
+                    let mut file = fs::File::open(entry.path()).map_err(|e| e.to_string())?;
+                    std::io::copy(&mut file, &mut zip).map_err(|e| e.to_string())?;
+                }
+            }
+        }
+    }
+
+    // Add manifest.json (checksums for integrity verification)
+    zip.start_file(BACKUP_MANIFEST_FILE, options)
+        .map_err(|e| e.to_string())?;
+    let manifest_json = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+    zip.write_all(manifest_json.as_bytes())
+        .map_err(|e| e.to_string())?;
+
+    zip.finish().map_err(|e| e.to_string())?;
+
+    Ok(written)
+}
+
+// 备份 / 恢复进度事件
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferProgress {
+    pub current: u64,
+    pub total: u64,
+    pub current_file: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferComplete {
+    pub filename: String,
+    pub total_bytes: u64,
+}
+
+// 备份进行中标记，run_backup 返回（含出错）时自动清除；已有备份在进行时拒绝再次开始
+struct BackupRunningGuard;
+
+impl BackupRunningGuard {
+    fn start() -> Result<Self, String> {
+        BACKUP_RUNNING
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .map_err(|_| "BACKUP_IN_PROGRESS".to_string())?;
+        Ok(BackupRunningGuard)
+    }
+}
+
+impl Drop for BackupRunningGuard {
+    fn drop(&mut self) {
+        BACKUP_RUNNING.store(false, Ordering::Release);
+    }
+}
+
+// 磁盘空间（路径所在的卷）
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskSpace {
+    pub total: u64,
+    pub available: u64,
+}
+
+// 路径不存在时使用最近的已存在上级目录
+fn disk_space(path: &std::path::Path) -> Result<DiskSpace, String> {
+    let existing = path
+        .ancestors()
+        .find(|p| p.exists())
+        .ok_or_else(|| format!("Path not found: {}", path.display()))?;
+    Ok(DiskSpace {
+        total: fs2::total_space(existing).map_err(|e| e.to_string())?,
+        available: fs2::available_space(existing).map_err(|e| e.to_string())?,
+    })
+}
+
+// Get total and available bytes of the volume containing the given path
+#[tauri::command]
+fn get_disk_space(path: String) -> Result<DiskSpace, String> {
+    disk_space(std::path::Path::new(&path))
+}
+
+// 存储健康报告：抽样校验的图片数量，以及低于多少可用空间时提示
+const STORAGE_REPORT_SAMPLE_SIZE: usize = 32;
+const LOW_DISK_SPACE_BYTES: u64 = 200 * 1024 * 1024;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageIssue {
+    // DATA_DIR_NOT_WRITABLE / LOW_DISK_SPACE / CORRUPT_IMAGE / ORPHANED_FILE
+    pub code: String,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageReport {
+    pub data_path: String,
+    pub portable: bool,
+    pub ephemeral: bool,
+    pub image_count: u64,
+    pub image_bytes: u64,
+    pub backup_count: u64,
+    pub backup_bytes: u64,
+    pub newest_backup_at: Option<i64>,
+    pub config_bytes: Option<u64>,
+    pub update_cache_bytes: u64,
+    pub disk: Option<DiskSpace>,
+    pub sampled_images: u64,
+    pub issues: Vec<StorageIssue>,
+}
+
+// Summarize the data directory for the storage health screen
+#[tauri::command]
+async fn get_storage_report(app: AppHandle) -> Result<StorageReport, String> {
+    let directory = get_data_directory(app.state());
+    let image_stats = get_image_storage_stats(app.state());
+    let stored = list_stored_images(app.state());
+    let backups = get_backup_list(app.clone(), None).await?;
+    let images_path = app
+        .state::<Mutex<AppState>>()
+        .lock()
+        .unwrap()
+        .images_path
+        .clone();
+    let data_path = PathBuf::from(&directory.data_path);
+
+    let mut issues = Vec::new();
+    let issue = |code: &str, detail: Option<String>| StorageIssue {
+        code: code.to_string(),
+        detail,
+    };
+
+    if !directory.ephemeral && !validate_backup_path(directory.data_path.clone()).is_writable {
+        issues.push(issue(
+            "DATA_DIR_NOT_WRITABLE",
+            Some(directory.data_path.clone()),
+        ));
+    }
+
+    let disk = disk_space(&data_path).ok();
+    if let Some(disk) = disk.as_ref().filter(|d| d.available < LOW_DISK_SPACE_BYTES) {
+        issues.push(issue("LOW_DISK_SPACE", Some(disk.available.to_string())));
+    }
+
+    // 均匀抽样部分散文件校验 hash，避免大图库下耗时过长
+    let loose: Vec<(String, PathBuf)> = loose_image_files(&images_path)
+        .into_iter()
+        .filter(|(name, _)| hash_from_image_filename(name).is_some())
+        .collect();
+    let step = loose.len().div_ceil(STORAGE_REPORT_SAMPLE_SIZE).max(1);
+    let sample: Vec<(String, PathBuf)> = loose.into_iter().step_by(step).collect();
+    let sampled_images = sample.len() as u64;
+    let corrupt: Vec<String> = hashing_pool(&app)?.install(|| {
+        sample
+            .into_par_iter()
+            .filter(|(name, path)| hash_file(path).ok() != hash_from_image_filename(name))
+            .map(|(name, _)| name)
+            .collect()
+    });
+    issues.extend(
+        corrupt
+            .into_iter()
+            .map(|name| issue("CORRUPT_IMAGE", Some(name))),
+    );
+    issues.extend(
+        stored
+            .unrecognized
+            .into_iter()
+            .map(|name| issue("ORPHANED_FILE", Some(name))),
+    );
+
+    let config_bytes = app
+        .path()
+        .app_data_dir()
+        .ok()
+        .and_then(|dir| fs::metadata(dir.join("config.json")).ok())
+        .map(|m| m.len());
+
+    Ok(StorageReport {
+        data_path: directory.data_path,
+        portable: directory.portable,
+        ephemeral: directory.ephemeral,
+        image_count: image_stats.total_count,
+        image_bytes: image_stats.total_bytes,
+        backup_count: backups.len() as u64,
+        backup_bytes: backups.iter().map(|b| b.size).sum(),
+        newest_backup_at: backups.iter().map(|b| b.created_at).max(),
+        config_bytes,
+        update_cache_bytes: collect_update_cache_stats().total_bytes,
+        disk,
+        sampled_images,
+        issues,
+    })
+}
+
+// 执行一次备份，返回备份文件名
+fn run_backup(
+    app: &AppHandle,
+    data: &str,
+    passphrase: Option<&str>,
+    incremental: bool,
+) -> Result<String, String> {
+    // 临时模式下不产生任何磁盘写入
+    if is_ephemeral(app) {
+        return Err("EPHEMERAL_MODE".to_string());
+    }
+    let _running = BackupRunningGuard::start()?;
+    // 拿到运行标记后再清除上一次遗留的取消请求，避免吞掉正在进行的备份的取消
+    BACKUP_CANCELLED.store(false, Ordering::Relaxed);
+
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    let settings: BackupSettings = store
+        .get("backupSettings")
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    let sidecars = backup_sidecars(&settings);
+    let passphrase = match settings.encrypt_backups {
+        true => Some(
+            passphrase
+                .filter(|p| !p.is_empty())
+                .ok_or("PASSWORD_REQUIRED")?,
+        ),
+        false => None,
+    };
+
+    let backup_dir = resolve_backup_directory(settings.backup_directory.clone())
+        .ok_or("Backup directory not configured")?;
+    let backup_path = std::path::Path::new(&backup_dir);
+
+    if !backup_path.exists() {
+        fs::create_dir_all(backup_path).map_err(|e| e.to_string())?;
+    }
+    remove_stale_backup_staging(backup_path);
+
+    // Generate filename with timestamp
+    let timestamp = Local::now().format("%Y%m%d_%H%M%S");
+    let extension = match passphrase {
+        Some(_) => ENCRYPTED_BACKUP_EXTENSION,
+        None => ".zip",
+    };
+    let filename = match backup_filename_tag(&settings) {
+        Some(tag) => format!("litepad_backup_{}_{}{}", tag, timestamp, extension),
+        None => format!("litepad_backup_{}{}", timestamp, extension),
+    };
+    let zip_path = backup_path.join(&filename);
+
+    // Get data and images path
+    let (data_path, images_path) = {
+        let state = app.state::<Mutex<AppState>>();
+        let state = state.lock().unwrap();
+        (state.data_path.clone(), state.images_path.clone())
+    };
+
+    // 预估备份大小（未压缩的图片 + data.json），空间不足时提前失败，避免写出截断的 zip
+    let estimated_bytes = data.len() as u64
+        + WalkDir::new(&images_path)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| e.metadata().map(|m| m.len()).unwrap_or(0))
+            .sum::<u64>();
+    if disk_space(backup_path).is_ok_and(|space| space.available < estimated_bytes) {
+        return Err("INSUFFICIENT_SPACE".to_string());
+    }
+
+    // 增量备份需要上一次备份仍在当前备份目录中，否则退回完整备份
+    let mut index = load_backup_index(&data_path);
+    let parent = index
+        .last_backup
+        .clone()
+        .filter(|last| incremental && *last != filename && backup_path.join(last).is_file());
+    let base = parent.as_deref().map(|parent| IncrementalBase {
+        parent,
+        images: &index.images,
+    });
+
+    let on_progress = |current: u64, total: u64, file: &str| {
+        // 每个文件之间检查取消标记
+        if BACKUP_CANCELLED.load(Ordering::Relaxed) {
+            return Err("BACKUP_CANCELLED".to_string());
+        }
+        let _ = app.emit(
+            "backup-progress",
+            TransferProgress {
+                current,
+                total,
+                current_file: file.to_string(),
+            },
+        );
+        Ok(())
+    };
+
+    let data = &format_json_snapshot(data, pretty_json_enabled(app));
+    let pool = hashing_pool(app)?;
+    let written = match passphrase {
+        Some(passphrase) => {
+            // zip 需要可随机写入，明文先暂存在备份目录（仅当前用户可读），再分块加密写入目标文件
+            let plain = BackupStagingFile::create(backup_path, "zip")?;
+            let written = write_backup_archive(
+                &plain.0,
+                data,
+                &data_path,
+                &images_path,
+                &sidecars,
+                base.as_ref(),
+                settings.compression,
+                &pool,
+                &on_progress,
+            )?;
+            let encrypted = BackupStagingFile::create(backup_path, "enc")?;
+            {
+                let mut reader =
+                    std::io::BufReader::new(fs::File::open(&plain.0).map_err(|e| e.to_string())?);
+                let mut writer = std::io::BufWriter::new(
+                    fs::OpenOptions::new()
+                        .write(true)
+                        .truncate(true)
+                        .open(&encrypted.0)
+                        .map_err(|e| e.to_string())?,
+                );
+                encrypt_backup_stream(&mut reader, &mut writer, passphrase)?;
+                writer
+                    .into_inner()
+                    .map_err(|e| e.to_string())?
+                    .sync_all()
+                    .map_err(|e| e.to_string())?;
+            }
+            fs::rename(&encrypted.0, &zip_path).map_err(|e| e.to_string())?;
+            written
+        }
+        None => write_backup_archive(
+            &zip_path,
+            data,
+            &data_path,
+            &images_path,
+            &sidecars,
+            base.as_ref(),
+            settings.compression,
+            &pool,
+            &on_progress,
+        )
+        .map_err(|e| {
+            // 失败或取消时删除不完整的 zip
+            let _ = fs::remove_file(&zip_path);
+            e
+        })?,
+    };
+
+    // 更新增量备份索引
+    match parent {
+        Some(parent) => {
+            index.images.extend(written);
+            index.parents.insert(filename.clone(), parent);
+        }
+        None => index.images = written,
+    }
+    index.last_backup = Some(filename.clone());
+    index
+        .parents
+        .retain(|child, _| backup_path.join(child).is_file());
+    save_backup_index(&data_path, &index)?;
+
+    // Clean up old backups
+    cleanup_old_backups(&backup_dir, &settings, &index.parents)?;
+
+    let _ = app.emit(
+        "backup-complete",
+        TransferComplete {
+            filename: filename.clone(),
+            total_bytes: fs::metadata(&zip_path).map(|m| m.len()).unwrap_or(0),
+        },
+    );
+
+    Ok(filename)
+}
+
+// 最近一次备份的结果
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LastBackupStatus {
+    pub success: bool,
+    pub filename: Option<String>,
+    pub error: Option<String>,
+    pub timestamp: i64,
+}
+
+const TRAY_ID: &str = "main";
+
+// 前端设置的托盘提示（如笔记数量）；None 时使用默认提示
+static CUSTOM_TRAY_TOOLTIP: Mutex<Option<String>> = Mutex::new(None);
+// 当前显示/隐藏快捷键的文本，默认提示随快捷键修改而更新
+static TRAY_SHORTCUT_LABEL: Mutex<String> = Mutex::new(String::new());
+
+fn default_tray_tooltip() -> String {
+    CUSTOM_TRAY_TOOLTIP
+        .lock()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| {
+            format!(
+                "LitePad - {} to toggle",
+                TRAY_SHORTCUT_LABEL.lock().unwrap()
+            )
+        })
+}
+
+fn set_tray_shortcut_label(app: &AppHandle, label: &str) {
+    *TRAY_SHORTCUT_LABEL.lock().unwrap() = label.to_string();
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        let _ = tray.set_tooltip(Some(default_tray_tooltip()));
+    }
+}
+
+// Set the tray tooltip; an empty text restores the default
+#[tauri::command]
+fn set_tray_tooltip(app: AppHandle, text: String) -> Result<(), String> {
+    let text = text.trim();
+    *CUSTOM_TRAY_TOOLTIP.lock().unwrap() = (!text.is_empty()).then(|| text.to_string());
+    let tray = app.tray_by_id(TRAY_ID).ok_or("Tray icon not found")?;
+    tray.set_tooltip(Some(default_tray_tooltip()))
+        .map_err(|e| e.to_string())
+}
+
+// Show a count badge (None or 0 clears it): tray title on macOS, window badge on Linux
+#[tauri::command]
+fn set_tray_badge(app: AppHandle, count: Option<u32>) -> Result<(), String> {
+    let count = count.filter(|c| *c > 0);
+
+    #[cfg(target_os = "macos")]
+    {
+        let tray = app.tray_by_id(TRAY_ID).ok_or("Tray icon not found")?;
+        tray.set_title(count.map(|c| c.to_string()))
+            .map_err(|e| e.to_string())
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let window = app
+            .get_webview_window("main")
+            .ok_or("Main window not found")?;
+        window
+            .set_badge_count(count.map(i64::from))
+            .map_err(|e| e.to_string())
+    }
+
+    // Windows 托盘图标不支持角标
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    {
+        let _ = (app, count);
+        Err("UNSUPPORTED".to_string())
+    }
+}
+
+fn record_backup_status(app: &AppHandle, result: &Result<String, String>) {
+    let status = LastBackupStatus {
+        success: result.is_ok(),
+        filename: result.as_ref().ok().cloned(),
+        error: result.as_ref().err().cloned(),
+        timestamp: Local::now().timestamp(),
+    };
+    if let Ok(store) = app.store("config.json") {
+        // lastBackupAt 只记录成功的备份
+        if status.success {
+            store.set("lastBackupAt", serde_json::json!(status.timestamp));
+        }
+        store.set("lastBackupStatus", serde_json::to_value(&status).unwrap());
+        let _ = persist_store(app, &store);
+    }
+}
+
+// 备份失败时的托盘图标：在默认图标右下角叠加红点
+fn backup_alert_icon(icon: &tauri::image::Image<'_>) -> tauri::image::Image<'static> {
+    let (width, height) = (icon.width(), icon.height());
+    let mut rgba = icon.rgba().to_vec();
+    let radius = width.min(height) as f32 * 0.22;
+    let (cx, cy) = (width as f32 - radius, height as f32 - radius);
+    for y in 0..height {
+        for x in 0..width {
+            let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+            if dx * dx + dy * dy <= radius * radius {
+                let i = ((y * width + x) * 4) as usize;
+                rgba[i..i + 4].copy_from_slice(&[0xE5, 0x39, 0x35, 0xFF]);
+            }
+        }
+    }
+    tauri::image::Image::new_owned(rgba, width, height)
+}
+
+// 托盘提示：备份失败时切换为带红点的图标并提示原因，成功后恢复默认图标
+fn set_backup_alert(app: &AppHandle, reason: Option<&str>) {
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        let tooltip = match reason {
+            Some(reason) => format!("LitePad - Backup failed: {}", reason),
+            None => default_tray_tooltip(),
+        };
+        let _ = tray.set_tooltip(Some(tooltip));
+        if let Some(icon) = app.default_window_icon() {
+            let icon = match reason {
+                Some(_) => backup_alert_icon(icon),
+                None => icon.clone(),
+            };
+            let _ = tray.set_icon(Some(icon));
+        }
+    }
+}
+
+// Perform backup
+#[tauri::command]
+async fn perform_backup(
+    app: AppHandle,
+    data: String,
+    passphrase: Option<String>,
+) -> Result<String, String> {
+    let result = run_backup(&app, &data, passphrase.as_deref(), false);
+    record_backup_status(&app, &result);
+    if result.is_ok() {
+        set_backup_alert(&app, None);
+    }
+    result
+}
+
+// Request cancellation of the running backup; it stops before the next file
+#[tauri::command]
+fn cancel_backup() {
+    BACKUP_CANCELLED.store(true, Ordering::Relaxed);
+}
+
+// Perform an incremental backup: only images not in the previous backup chain are added
+#[tauri::command]
+async fn perform_incremental_backup(
+    app: AppHandle,
+    data: String,
+    passphrase: Option<String>,
+) -> Result<String, String> {
+    let result = run_backup(&app, &data, passphrase.as_deref(), true);
+    record_backup_status(&app, &result);
+    if result.is_ok() {
+        set_backup_alert(&app, None);
+    }
+    result
+}
+
+// 自动备份调度：定时重新读取设置，到期时通过 auto-backup-request 向前端索取当前数据，
+// 前端收到后调用 perform_auto_backup
+const AUTO_BACKUP_TICK_SECS: u64 = 30;
+
+fn parse_backup_time(value: &str) -> Option<chrono::NaiveTime> {
+    chrono::NaiveTime::parse_from_str(value.trim(), "%H:%M").ok()
+}
+
+// 最近一个已到达的每日备份时间点（今天或昨天）
+fn last_scheduled_backup(
+    now: chrono::NaiveDateTime,
+    time: chrono::NaiveTime,
+) -> chrono::NaiveDateTime {
+    let today = now.date().and_time(time);
+    if now >= today {
+        today
+    } else {
+        today - chrono::Duration::days(1)
+    }
+}
+
+// 最近一次备份（无论成败）的本地时间
+fn last_backup_attempt(app: &AppHandle) -> Option<chrono::NaiveDateTime> {
+    let status: LastBackupStatus = app
+        .store("config.json")
+        .ok()?
+        .get("lastBackupStatus")
+        .and_then(|v| serde_json::from_value(v).ok())?;
+    chrono::DateTime::from_timestamp(status.timestamp, 0)
+        .map(|t| t.with_timezone(&Local).naive_local())
+}
+
+fn spawn_auto_backup_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut last_request: Option<std::time::Instant> = None;
+        let mut last_daily_request: Option<chrono::NaiveDateTime> = None;
+        loop {
+            tokio::time::sleep(std::time::Duration::from_secs(AUTO_BACKUP_TICK_SECS)).await;
+
+            let settings: BackupSettings = app
+                .store("config.json")
+                .ok()
+                .and_then(|store| store.get("backupSettings"))
+                .and_then(|v| serde_json::from_value(v).ok())
+                .unwrap_or_default();
+            if !settings.auto_backup_enabled
+                || is_ephemeral(&app)
+                || resolve_backup_directory(settings.backup_directory).is_none()
+            {
+                last_request = None;
+                continue;
+            }
+
+            // 每日定时：按挂钟时间判断，睡眠中错过时间点时醒来后立即补做
+            if let Some(time) = settings
+                .auto_backup_time
+                .as_deref()
+                .and_then(parse_backup_time)
+            {
+                let due = last_scheduled_backup(Local::now().naive_local(), time);
+                let done = last_daily_request == Some(due)
+                    || last_backup_attempt(&app).is_some_and(|at| at >= due);
+                if !done {
+                    last_daily_request = Some(due);
+                    let _ = app.emit("auto-backup-request", ());
+                }
+                continue;
+            }
+
+            let interval =
+                std::time::Duration::from_secs(settings.auto_backup_interval.max(1) as u64 * 60);
+            if last_request.is_some_and(|at| at.elapsed() < interval) {
+                continue;
+            }
+            last_request = Some(std::time::Instant::now());
+            let _ = app.emit("auto-backup-request", ());
+        }
+    });
+}
+
+// Perform an automatic backup, retrying transient failures with backoff
+#[tauri::command]
+async fn perform_auto_backup(
+    app: AppHandle,
+    data: String,
+    passphrase: Option<String>,
+) -> Result<String, String> {
+    let retries = app
+        .store("config.json")
+        .ok()
+        .and_then(|store| store.get("backupSettings"))
+        .and_then(|v| serde_json::from_value::<BackupSettings>(v).ok())
+        .unwrap_or_default()
+        .auto_backup_retries;
+
+    let mut attempt = 0;
+    let result = loop {
+        let result = run_backup(&app, &data, passphrase.as_deref(), false);
+        // 已有备份在进行（例如手动备份），本次自动备份直接让位，不算失败
+        if matches!(&result, Err(e) if e == "BACKUP_IN_PROGRESS") {
+            return result;
+        }
+        let cancelled = matches!(&result, Err(e) if e == "BACKUP_CANCELLED");
+        if result.is_ok() || cancelled || attempt >= retries {
+            break result;
+        }
+        // 5s, 10s, 20s ...
+        let delay = 5u64 << attempt.min(6);
+        tokio::time::sleep(std::time::Duration::from_secs(delay)).await;
+        attempt += 1;
+    };
+
+    record_backup_status(&app, &result);
+    match &result {
+        Ok(_) => set_backup_alert(&app, None),
+        Err(reason) => {
+            set_backup_alert(&app, Some(reason));
+            let _ = app.emit("backup-failed", reason.clone());
+        }
+    }
+    result
+}
+
+// 备份状态：最近一次成功时间、最近一次结果、是否正在备份
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupStatus {
+    pub last_backup_at: Option<i64>,
+    pub last_result: Option<LastBackupStatus>,
+    pub running: bool,
+}
+
+// Get the last successful backup time, the most recent result and whether a backup is running
+#[tauri::command]
+async fn get_last_backup_status(app: AppHandle) -> Result<BackupStatus, String> {
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    let last_result: Option<LastBackupStatus> = store
+        .get("lastBackupStatus")
+        .and_then(|v| serde_json::from_value(v).ok());
+    // 旧版本只记录了 lastBackupStatus
+    let last_backup_at = store
+        .get("lastBackupAt")
+        .and_then(|v| v.as_i64())
+        .or_else(|| {
+            last_result
+                .as_ref()
+                .filter(|status| status.success)
+                .map(|status| status.timestamp)
+        });
+    Ok(BackupStatus {
+        last_backup_at,
+        last_result,
+        running: BACKUP_RUNNING.load(Ordering::Relaxed),
+    })
+}
+
+fn backup_info(filename: String, metadata: &fs::Metadata) -> BackupInfo {
+    // 优先使用文件名中的时间戳，解析失败时才回退到文件系统时间
+    let created_at = backup_timestamp(&filename)
+        .and_then(|t| t.and_local_timezone(Local).earliest())
+        .map(|t| t.timestamp())
+        .or_else(|| {
+            metadata
+                .created()
+                .or_else(|_| metadata.modified())
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64)
+        })
+        .unwrap_or(0);
+
+    BackupInfo {
+        encrypted: filename.ends_with(ENCRYPTED_BACKUP_EXTENSION),
+        tag: backup_tag(&filename),
+        filename,
+        created_at,
+        size: metadata.len(),
+        label: None,
+        readable: None,
+    }
+}
+
+// 备份可读性检查：只枚举 zip 条目，不解压
+#[derive(Debug, Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupValidity {
+    pub readable: bool,
+    pub has_data_json: bool,
+    pub image_count: u32,
+    pub app_version: Option<String>,
+}
+
+fn inspect_backup(backup: &BackupArchive) -> BackupValidity {
+    let mut archive = match backup.zip() {
+        Ok(archive) => archive,
+        Err(_) => return BackupValidity::default(),
+    };
+
+    let has_data_json = archive.file_names().any(|name| name == "data.json");
+    let image_count = archive
+        .file_names()
+        .filter(|name| name.starts_with("images/") && !name.ends_with('/'))
+        .count() as u32;
+    // manifest 无法解析时视为不可读
+    let manifest = read_backup_manifest(&mut archive);
+    BackupValidity {
+        readable: manifest.is_ok(),
+        has_data_json,
+        image_count,
+        app_version: manifest.ok().flatten().map(|m| m.app_version),
+    }
+}
+
+// Check that a backup opens and list what it contains, without extracting anything
+#[tauri::command]
+async fn is_valid_backup(
+    app: AppHandle,
+    filename: String,
+    passphrase: Option<String>,
+) -> Result<BackupValidity, String> {
+    let backup = open_backup_zip(&backup_file_path(&app, &filename)?, passphrase.as_deref())?;
+    Ok(inspect_backup(&backup))
+}
+
+// Get backup list
+#[tauri::command]
+async fn get_backup_list(
+    app: AppHandle,
+    validate: Option<bool>,
+) -> Result<Vec<BackupInfo>, String> {
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    let settings: BackupSettings = store
+        .get("backupSettings")
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+
+    let backup_dir = match resolve_backup_directory(settings.backup_directory) {
+        Some(dir) => dir,
+        None => return Ok(vec![]),
+    };
+
+    let backup_path = std::path::Path::new(&backup_dir);
+    if !backup_path.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(&backup_dir).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let filename = entry.file_name().to_string_lossy().to_string();
+
+        if is_backup_filename(&filename) {
+            let metadata = entry.metadata().map_err(|e| e.to_string())?;
+            backups.push(backup_info(filename, &metadata));
+        }
+    }
+
+    let mut labels = load_backup_labels(backup_path);
+    for backup in &mut backups {
+        backup.label = labels.remove(&backup.filename);
+        // 损坏的备份只做标记，不影响整个列表
+        if validate.unwrap_or(false) && !backup.encrypted {
+            let validity =
+                inspect_backup(&BackupArchive::plain(&backup_path.join(&backup.filename)));
+            backup.readable = Some(validity.readable && validity.has_data_json);
+        }
+    }
+
+    // Sort by created_at descending (filename breaks ties)
+    backups.sort_by(|a, b| {
+        b.created_at
+            .cmp(&a.created_at)
+            .then_with(|| b.filename.cmp(&a.filename))
+    });
+
+    Ok(backups)
+}
+
+// 解压备份：图片写入 images_path，附属数据写入 data_path，返回 data.json 内容
+fn extract_backup_archive(
+    backup: &BackupArchive,
+    data_path: &std::path::Path,
+    images_path: &std::path::Path,
+    on_progress: &dyn Fn(u64, u64, &str) -> Result<(), String>,
+) -> Result<String, String> {
+    let mut archive = backup.zip()?;
+    let total = archive
+        .file_names()
+        .filter(|name| name.starts_with("images/") && !name.ends_with('/'))
+        .count() as u64;
+    let mut current = 0;
+
+    // Extract data.json
+    let mut data_json = String::new();
+    {
+        let mut data_file = archive.by_name("data.json").map_err(|e| e.to_string())?;
+        data_file
+            .read_to_string(&mut data_json)
+            .map_err(|e| e.to_string())?;
+    }
+
+    // Extract images
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(|e| e.to_string())?;
+        let name = file.name().to_string();
+
+        if name.starts_with("images/") && !name.ends_with('/') {
+            if let Some(relative) = name.strip_prefix("images/") {
+                current += 1;
+                on_progress(current, total, relative)?;
+                let Some(dest_path) = restored_image_path(images_path, relative) else {
+                    continue;
+                };
+
+                if let Some(parent) = dest_path.parent() {
+                    let _ = fs::create_dir_all(parent);
+                }
+
+                write_atomic_from(&dest_path, &mut file)?;
+            }
+        } else if is_sidecar_entry(&name) && !name.ends_with('/') {
+            // 附属数据：存在则恢复，缺失则跳过
+            if let Some(relative) = file.enclosed_name() {
+                let dest_path = data_path.join(relative);
+                if let Some(parent) = dest_path.parent() {
+                    let _ = fs::create_dir_all(parent);
+                }
+                write_atomic_from(&dest_path, &mut file)?;
+            }
+        }
+    }
+
+    Ok(data_json)
+}
+
+// 备份中的笔记摘要
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupNoteSummary {
+    pub id: String,
+    pub title: String,
+    pub created_at: Option<i64>,
+    pub updated_at: Option<i64>,
+    pub image_count: usize,
+}
+
+// 从备份中恢复的单条笔记
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoredNote {
+    pub note: serde_json::Value,
+    pub restored_images: Vec<String>,
+    pub missing_images: Vec<String>,
+}
+
+// 前端传入的备份文件名只能是备份目录下的单层文件名，拒绝 ../ 等越界路径
+fn validate_backup_filename(filename: &str) -> Result<(), String> {
+    if !is_backup_filename(filename) || filename.contains(['/', '\\']) {
+        return Err(format!("Invalid backup filename: {}", filename));
+    }
+    Ok(())
+}
+
+// 解析已配置的备份目录中的备份文件路径
+fn backup_file_path(app: &AppHandle, filename: &str) -> Result<PathBuf, String> {
+    validate_backup_filename(filename)?;
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    let settings: BackupSettings = store
+        .get("backupSettings")
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+
+    let backup_dir = resolve_backup_directory(settings.backup_directory)
+        .ok_or("Backup directory not configured")?;
+    Ok(std::path::Path::new(&backup_dir).join(filename))
+}
+
+fn read_backup_data_json<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Result<String, String> {
+    let mut data_json = String::new();
+    archive
+        .by_name("data.json")
+        .map_err(|e| e.to_string())?
+        .read_to_string(&mut data_json)
+        .map_err(|e| e.to_string())?;
+    Ok(data_json)
+}
+
+// 从 data.json 中取出笔记列表，兼容 localStorage 快照（flashpad-data 为字符串）、
+// { tabs: [...] } 对象以及直接的数组
+fn notes_from_data_json(data_json: &str) -> Result<Vec<serde_json::Value>, String> {
+    let mut value: serde_json::Value =
+        serde_json::from_str(data_json).map_err(|e| e.to_string())?;
+
+    if let Some(inner) = value.get("flashpad-data").cloned() {
+        value = match inner {
+            serde_json::Value::String(s) => serde_json::from_str(&s).map_err(|e| e.to_string())?,
+            other => other,
+        };
+    }
+
+    match value {
+        serde_json::Value::Array(notes) => Ok(notes),
+        serde_json::Value::Object(mut map) => match map.remove("tabs") {
+            Some(serde_json::Value::Array(notes)) => Ok(notes),
+            _ => Err("No notes found in backup data".to_string()),
+        },
+        _ => Err("No notes found in backup data".to_string()),
+    }
+}
+
+// 只读查看模式（--view <backup.zip>）：数据来自备份，不写入当前数据
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewModeData {
+    pub source: String,
+    pub notes: Vec<serde_json::Value>,
+}
+
+struct ViewModeState(Mutex<Option<ViewModeData>>);
+
+fn is_view_mode(app: &AppHandle) -> bool {
+    app.try_state::<ViewModeState>()
+        .is_some_and(|state| state.0.lock().unwrap().is_some())
+}
+
+// 从命令行参数中取出 --view <path> 或 --view=<path>，相对路径按 cwd 解析
+fn view_path_from_args(args: &[String], cwd: &std::path::Path) -> Option<PathBuf> {
+    let value = args.iter().enumerate().find_map(|(i, arg)| {
+        if arg == "--view" {
+            args.get(i + 1).cloned()
+        } else {
+            arg.strip_prefix("--view=").map(str::to_string)
+        }
+    })?;
+    let path = PathBuf::from(value);
+    Some(if path.is_absolute() {
+        path
+    } else {
+        cwd.join(path)
+    })
+}
+
+// 读取备份中的笔记供只读查看，不在磁盘上留下任何副本
+fn load_view_data(path: &std::path::Path) -> Result<ViewModeData, String> {
+    let backup = open_backup_zip(path, None)?;
+    let data_json = {
+        let mut archive = backup.zip()?;
+        read_backup_data_json(&mut archive)?
+    };
+    let notes = notes_from_data_json(&data_json)?;
+
+    Ok(ViewModeData {
+        source: path.to_string_lossy().to_string(),
+        notes,
+    })
+}
+
+// 进入只读查看模式并通知前端（view-mode）
+fn enter_view_mode(app: &AppHandle, path: &std::path::Path) {
+    match load_view_data(path) {
+        Ok(view) => {
+            *app.state::<ViewModeState>().0.lock().unwrap() = Some(view.clone());
+            let _ = app.emit("view-mode", view);
+        }
+        Err(e) => {
+            eprintln!(
+                "Warning: Failed to open {} for viewing: {}",
+                path.display(),
+                e
+            );
+            let _ = app.emit("view-mode-error", e);
+        }
+    }
+}
+
+// Get the backup opened with --view, if the app is in read-only view mode
+#[tauri::command]
+fn get_view_mode(view: State<'_, ViewModeState>) -> Option<ViewModeData> {
+    view.0.lock().unwrap().clone()
+}
+
+// Leave read-only view mode and re-enable saving
+#[tauri::command]
+fn exit_view_mode(view: State<'_, ViewModeState>) {
+    view.0.lock().unwrap().take();
+}
+
+// 提取文本中引用的图片 URL（litepad://images/{hash}{ext}），返回 (原始 URL, 文件名)
+fn referenced_image_urls(content: &str) -> Vec<(String, String)> {
+    let mut urls: Vec<(String, String)> = Vec::new();
+    for (start, _) in content.match_indices("litepad://images/") {
+        let url: String = content[start..]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, ':' | '/' | '.'))
+            .collect();
+        let url = url.trim_end_matches('.');
+        if let Some((hash, ext)) = parse_image_url(url) {
+            if !urls.iter().any(|(existing, _)| existing == url) {
+                urls.push((url.to_string(), format!("{}{}", hash, ext)));
+            }
+        }
+    }
+    urls
+}
+
+fn referenced_image_names(content: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for (_, name) in referenced_image_urls(content) {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+fn note_id_of(note: &serde_json::Value) -> Option<String> {
+    match note.get("id")? {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+// List the notes contained in a backup without touching live data
+#[tauri::command]
+async fn peek_backup(
+    app: AppHandle,
+    filename: String,
+    passphrase: Option<String>,
+) -> Result<Vec<BackupNoteSummary>, String> {
+    let backup = open_backup_zip(&backup_file_path(&app, &filename)?, passphrase.as_deref())?;
+    let mut archive = backup.zip()?;
+    let notes = notes_from_data_json(&read_backup_data_json(&mut archive)?)?;
+
+    Ok(notes
+        .iter()
+        .filter_map(|note| {
+            Some(BackupNoteSummary {
+                id: note_id_of(note)?,
+                title: note
+                    .get("title")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                created_at: note.get("createdAt").and_then(|v| v.as_i64()),
+                updated_at: note.get("updatedAt").and_then(|v| v.as_i64()),
+                image_count: referenced_image_names(
+                    note.get("content")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or_default(),
+                )
+                .len(),
+            })
+        })
+        .collect())
+}
+
+// 从备份中读取单个图片：优先散文件，其次备份内的 pack
+fn read_backup_image<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Option<Vec<u8>> {
+    let mut buffer = Vec::new();
+    if let Ok(mut file) = archive.by_name(&format!("images/{}", name)) {
+        file.read_to_end(&mut buffer).ok()?;
+        return Some(buffer);
+    }
+
+    let mut index_json = String::new();
+    archive
+        .by_name(&format!("images/{}", PACK_INDEX_FILE))
+        .ok()?
+        .read_to_string(&mut index_json)
+        .ok()?;
+    let index: PackIndex = serde_json::from_str(&index_json).ok()?;
+    let entry = index.entries.get(name)?;
+
+    let pack = archive.by_name(&format!("images/{}", entry.pack)).ok()?;
+    let mut pack = pack.take(entry.offset + entry.length);
+    std::io::copy(&mut (&mut pack).take(entry.offset), &mut std::io::sink()).ok()?;
+    pack.read_to_end(&mut buffer).ok()?;
+    (buffer.len() as u64 == entry.length).then_some(buffer)
+}
+
+// Restore a single note from a backup; only the images it references are extracted
+#[tauri::command]
+async fn restore_note_from_backup(
+    state: State<'_, Mutex<AppState>>,
+    packs: State<'_, PackIndexCache>,
+    app: AppHandle,
+    filename: String,
+    note_id: String,
+    passphrase: Option<String>,
+) -> Result<RestoredNote, String> {
+    let backup = open_backup_zip(&backup_file_path(&app, &filename)?, passphrase.as_deref())?;
+    let mut archive = backup.zip()?;
+    let notes = notes_from_data_json(&read_backup_data_json(&mut archive)?)?;
+
+    let note = notes
+        .into_iter()
+        .find(|note| note_id_of(note).as_deref() == Some(note_id.as_str()))
+        .ok_or_else(|| format!("Note {} not found in backup", note_id))?;
+
+    let content = note
+        .get("content")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    let mut restored_images = Vec::new();
+    let mut missing_images = Vec::new();
+
+    for name in referenced_image_names(content) {
+        let (hash, ext) = name.split_at(name.find('.').unwrap_or(name.len()));
+        {
+            let state = state.lock().unwrap();
+            if state.memory_images.contains_key(&name)
+                || read_stored_image(&state.images_path, &packs, &name).is_some()
+            {
+                continue;
+            }
+        }
+
+        match read_backup_image(&mut archive, &name) {
+            // 内容与文件名 hash 不一致视为损坏，不写入
+            Some(bytes) if hash_bytes(&bytes) == hash => {
+                let mut state = state.lock().unwrap();
+                store_image_bytes(&mut state, bytes, ext)?;
+                restored_images.push(name);
+            }
+            _ => missing_images.push(name),
+        }
+    }
+
+    Ok(RestoredNote {
+        note,
+        restored_images,
+        missing_images,
+    })
+}
+
+fn backup_parent(backup: &BackupArchive) -> Result<Option<String>, String> {
+    let mut archive = backup.zip()?;
+    Ok(read_backup_manifest(&mut archive)?.and_then(|manifest| manifest.parent))
+}
+
+// 沿 manifest.parent 收集增量备份的 parent 链（由近及远，不含自身）；
+// parent 已被删除或链条异常时返回 BACKUP_CHAIN_BROKEN
+fn resolve_backup_chain(
+    backup_dir: &std::path::Path,
+    backup: &BackupArchive,
+    passphrase: Option<&str>,
+) -> Result<Vec<BackupArchive>, String> {
+    let mut chain = Vec::new();
+    let mut visited = BTreeSet::new();
+    let mut current = backup_parent(backup)?;
+
+    while let Some(parent) = current {
+        let parent_path = backup_dir.join(&parent);
+        if !is_backup_filename(&parent)
+            || parent.contains(['/', '\\'])
+            || !visited.insert(parent.clone())
+            || !parent_path.is_file()
+        {
+            eprintln!("Backup chain broken: parent {} is unavailable", parent);
+            return Err("BACKUP_CHAIN_BROKEN".to_string());
+        }
+        let archive = open_backup_zip(&parent_path, passphrase)?;
+        current = backup_parent(&archive)?;
+        chain.push(archive);
+    }
+
+    Ok(chain)
+}
+
+// 从 parent 备份中补齐缺失的图片（已存在的文件以较新的备份为准）
+fn extract_missing_backup_images(
+    backup: &BackupArchive,
+    images_path: &std::path::Path,
+    on_progress: &dyn Fn(u64, u64, &str) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut archive = backup.zip()?;
+    let total = archive
+        .file_names()
+        .filter(|name| name.starts_with("images/") && !name.ends_with('/'))
+        .count() as u64;
+    let mut current = 0;
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(|e| e.to_string())?;
+        let name = file.name().to_string();
+        if name.ends_with('/') {
+            continue;
+        }
+        if let Some(relative) = name.strip_prefix("images/") {
+            current += 1;
+            on_progress(current, total, relative)?;
+            let Some(dest_path) = restored_image_path(images_path, relative) else {
+                continue;
+            };
+            if dest_path.exists() {
+                continue;
+            }
+            if let Some(parent) = dest_path.parent() {
+                let _ = fs::create_dir_all(parent);
+            }
+            write_atomic_from(&dest_path, &mut file)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Markdown 导出结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkdownExportResult {
+    pub exported_count: usize,
+    pub written_paths: Vec<String>,
+    pub missing_images: Vec<String>,
+}
+
+// 由标题生成可用的文件名（不含扩展名）
+fn sanitize_file_stem(title: &str) -> String {
+    let cleaned: String = title
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(100)
+        .collect();
+    let cleaned = cleaned.trim().trim_end_matches('.').trim();
+
+    if cleaned.is_empty() {
+        return "Untitled".to_string();
+    }
+
+    // Windows 保留设备名
+    let upper = cleaned.to_ascii_uppercase();
+    let reserved = matches!(upper.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || ((upper.starts_with("COM") || upper.starts_with("LPT"))
+            && upper.len() == 4
+            && upper.as_bytes()[3].is_ascii_digit());
+    if reserved {
+        format!("{}_", cleaned)
+    } else {
+        cleaned.to_string()
+    }
+}
+
+// Export notes as one Markdown file per note; referenced images are copied to assets/
+#[tauri::command]
+async fn export_markdown(
+    state: State<'_, Mutex<AppState>>,
+    packs: State<'_, PackIndexCache>,
+    output_dir: String,
+    data: String,
+) -> Result<MarkdownExportResult, String> {
+    let notes = notes_from_data_json(&data)?;
+    let output_dir = PathBuf::from(output_dir);
+    let assets_dir = output_dir.join("assets");
+    fs::create_dir_all(&output_dir).map_err(|e| e.to_string())?;
+
+    let mut used_names: HashSet<String> = HashSet::new();
+    let mut written_paths = Vec::new();
+    let mut missing_images = Vec::new();
+
+    for note in &notes {
+        let title = note
+            .get("title")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        let mut content = note
+            .get("content")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+
+        // 复制引用的图片并改写为相对路径
+        for (url, name) in referenced_image_urls(&content) {
+            let dest_path = assets_dir.join(&name);
+            if !dest_path.exists() {
+                let bytes = {
+                    let state = state.lock().unwrap();
+                    state
+                        .memory_images
+                        .get(&name)
+                        .cloned()
+                        .or_else(|| read_stored_image(&state.images_path, &packs, &name))
+                };
+                match bytes {
+                    Some(bytes) => {
+                        fs::create_dir_all(&assets_dir).map_err(|e| e.to_string())?;
+                        fs::write(&dest_path, bytes).map_err(|e| e.to_string())?;
+                    }
+                    None => {
+                        if !missing_images.contains(&name) {
+                            missing_images.push(name);
+                        }
+                        continue;
+                    }
+                }
+            }
+            content = content.replace(&url, &format!("assets/{}", name));
+        }
+
+        // 重名时追加数字后缀
+        let stem = sanitize_file_stem(title);
+        let mut filename = format!("{}.md", stem);
+        let mut suffix = 2;
+        while !used_names.insert(filename.to_lowercase()) || output_dir.join(&filename).exists() {
+            filename = format!("{} ({}).md", stem, suffix);
+            suffix += 1;
+        }
+
+        let path = output_dir.join(&filename);
+        fs::write(&path, content).map_err(|e| e.to_string())?;
+        written_paths.push(path.to_string_lossy().to_string());
+    }
+
+    Ok(MarkdownExportResult {
+        exported_count: written_paths.len(),
+        written_paths,
+        missing_images,
+    })
+}
+
+// 从 Markdown 导入的笔记
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedNote {
+    pub title: String,
+    pub content: String,
+    pub created_at: i64,
+}
+
+// 单个文件的导入结果：成功时 note 有值，失败时 error 有值
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkdownImportResult {
+    pub path: String,
+    pub note: Option<ImportedNote>,
+    pub error: Option<String>,
+}
+
+// 提取 Markdown 图片链接 ![alt](target "title") 中的 target
+fn markdown_image_targets(content: &str) -> Vec<String> {
+    let mut targets: Vec<String> = Vec::new();
+    for (start, _) in content.match_indices("![") {
+        let rest = &content[start + 2..];
+        let Some(open) = rest.find("](") else {
+            continue;
+        };
+        let rest = &rest[open + 2..];
+        let Some(close) = rest.find(')') else {
+            continue;
+        };
+        let inner = rest[..close].trim();
+        let target = match inner.strip_prefix('<') {
+            Some(quoted) => quoted.split('>').next().unwrap_or_default(),
+            None => inner.split_whitespace().next().unwrap_or_default(),
+        };
+        if !target.is_empty() && !targets.iter().any(|t| t == target) {
+            targets.push(target.to_string());
+        }
+    }
+    targets
+}
+
+fn import_markdown_file(
+    state: &Mutex<AppState>,
+    path: &std::path::Path,
+) -> Result<ImportedNote, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    let mut content =
+        String::from_utf8(bytes).map_err(|_| "File is not valid UTF-8".to_string())?;
+    let base_dir = path.parent().unwrap_or(std::path::Path::new("."));
+
+    // 本地图片走 hash 存储，链接改写为 litepad:// URL
+    for target in markdown_image_targets(&content) {
+        let lower = target.to_ascii_lowercase();
+        if lower.starts_with("http://")
+            || lower.starts_with("https://")
+            || lower.starts_with("data:")
+            || lower.starts_with("litepad://")
+        {
+            continue;
+        }
+
+        let local = target
+            .strip_prefix("file://")
+            .unwrap_or(&target)
+            .replace("%20", " ");
+        let image_path = base_dir.join(&local);
+        let Ok(buffer) = fs::read(&image_path) else {
+            continue;
+        };
+        let ext = sniff_image_extension(&buffer)
+            .map(|ext| ext.to_string())
+            .or_else(|| {
+                image_path
+                    .extension()
+                    .map(|ext| format!(".{}", ext.to_string_lossy().to_lowercase()))
+            })
+            .unwrap_or_default();
+
+        let saved = {
+            let mut state = state.lock().unwrap();
+            store_image_bytes(&mut state, buffer, &ext)?
+        };
+        content = content.replace(&format!("]({}", target), &format!("]({}", saved.url));
+        content = content.replace(&format!("](<{}>", target), &format!("]({}", saved.url));
+    }
+
+    // 标题取第一个一级/二级标题，否则用文件名
+    let title = content
+        .lines()
+        .map(|line| line.trim())
+        .find(|line| line.starts_with('#'))
+        .map(|line| line.trim_start_matches('#').trim().to_string())
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+
+    let created_at = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_else(|| Local::now().timestamp_millis());
+
+    Ok(ImportedNote {
+        title,
+        content,
+        created_at,
+    })
+}
+
+// Import Markdown files as notes; local images are copied into the image store
+#[tauri::command]
+async fn import_markdown(
+    state: State<'_, Mutex<AppState>>,
+    paths: Vec<String>,
+) -> Result<Vec<MarkdownImportResult>, String> {
+    Ok(paths
+        .into_iter()
+        .map(
+            |path| match import_markdown_file(&state, std::path::Path::new(&path)) {
+                Ok(note) => MarkdownImportResult {
+                    path,
+                    note: Some(note),
+                    error: None,
+                },
+                Err(e) => MarkdownImportResult {
+                    path,
+                    note: None,
+                    error: Some(e),
+                },
+            },
+        )
+        .collect())
+}
+
+// Restore backup
+#[tauri::command]
+async fn restore_backup(
+    state: State<'_, Mutex<AppState>>,
+    app: AppHandle,
+    filename: String,
+    target_dir: Option<String>,
+    verify: Option<bool>,
+    passphrase: Option<String>,
+) -> Result<String, String> {
+    if is_ephemeral(&app) {
+        return Err("EPHEMERAL_MODE".to_string());
+    }
+
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    let settings: BackupSettings = store
+        .get("backupSettings")
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+
+    let backup_dir = resolve_backup_directory(settings.backup_directory)
+        .ok_or("Backup directory not configured")?;
+    let backup_dir = std::path::Path::new(&backup_dir);
+    let backup = open_backup_zip(&backup_file_path(&app, &filename)?, passphrase.as_deref())?;
+    // 增量备份：先确认整条 parent 链可用，再开始写入
+    let chain = resolve_backup_chain(backup_dir, &backup, passphrase.as_deref())?;
+
+    // 可选：恢复前按 manifest 校验，损坏则拒绝恢复
+    if verify.unwrap_or(false)
+        && !std::iter::once(&backup).chain(&chain).all(|archive| {
+            verify_backup_archive(archive)
+                .map(|result| result.valid)
+                .unwrap_or(false)
+        })
+    {
+        return Err("BACKUP_CORRUPT".to_string());
+    }
+
+    // 指定了目标目录时恢复到该目录（不影响当前数据），否则原地恢复
+    let target_dir = match target_dir {
+        Some(dir) => {
+            let dir = PathBuf::from(dir);
+            let data_path = state.lock().unwrap().data_path.clone();
+            if dir == data_path {
+                return Err(
+                    "Target directory must differ from the current data directory".to_string(),
+                );
+            }
+            fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            Some(dir)
+        }
+        None => None,
+    };
+
+    let (data_path, images_path) = match &target_dir {
+        Some(dir) => (dir.clone(), dir.join("images")),
+        None => {
+            let state = state.lock().unwrap();
+            (state.data_path.clone(), state.images_path.clone())
+        }
+    };
+
+    let on_progress = |current: u64, total: u64, file: &str| -> Result<(), String> {
+        let _ = app.emit(
+            "restore-progress",
+            TransferProgress {
+                current,
+                total,
+                current_file: file.to_string(),
+            },
+        );
+        Ok(())
+    };
+
+    let data_json = extract_backup_archive(&backup, &data_path, &images_path, &on_progress)?;
+    let mut total_bytes = fs::metadata(&backup.path).map(|m| m.len()).unwrap_or(0);
+    for archive in &chain {
+        extract_missing_backup_images(archive, &images_path, &on_progress)?;
+        total_bytes += fs::metadata(&archive.path).map(|m| m.len()).unwrap_or(0);
+    }
+
+    let _ = app.emit(
+        "restore-complete",
+        TransferComplete {
+            filename: filename.clone(),
+            total_bytes,
+        },
+    );
+
+    if let Some(dir) = target_dir {
+        fs::write(dir.join("data.json"), &data_json).map_err(|e| e.to_string())?;
+        return Ok(dir.to_string_lossy().to_string());
+    }
+
+    Ok(data_json)
+}
+
+// Import a backup's images without touching data.json; returns the backup's notes for the frontend to merge
+#[tauri::command]
+async fn restore_backup_merge(
+    state: State<'_, Mutex<AppState>>,
+    app: AppHandle,
+    filename: String,
+    passphrase: Option<String>,
+) -> Result<Vec<serde_json::Value>, String> {
+    if is_ephemeral(&app) {
+        return Err("EPHEMERAL_MODE".to_string());
+    }
+
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    let settings: BackupSettings = store
+        .get("backupSettings")
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+
+    let backup_dir = resolve_backup_directory(settings.backup_directory)
+        .ok_or("Backup directory not configured")?;
+    let backup_dir = std::path::Path::new(&backup_dir);
+    let backup = open_backup_zip(&backup_file_path(&app, &filename)?, passphrase.as_deref())?;
+    let chain = resolve_backup_chain(backup_dir, &backup, passphrase.as_deref())?;
+
+    // 先解析笔记，data.json 无效时不写入任何图片
+    let notes = {
+        let mut archive = backup.zip()?;
+        notes_from_data_json(&read_backup_data_json(&mut archive)?)?
+    };
+
+    let on_progress = |current: u64, total: u64, file: &str| -> Result<(), String> {
+        let _ = app.emit(
+            "restore-progress",
+            TransferProgress {
+                current,
+                total,
+                current_file: file.to_string(),
+            },
+        );
+        Ok(())
+    };
+
+    // 图片按 hash 命名，只补充缺失的文件，不覆盖现有图片和附属数据
+    let images_path = state.lock().unwrap().images_path.clone();
+    let mut total_bytes = 0;
+    for archive in std::iter::once(&backup).chain(&chain) {
+        extract_missing_backup_images(archive, &images_path, &on_progress)?;
+        total_bytes += fs::metadata(&archive.path).map(|m| m.len()).unwrap_or(0);
+    }
+
+    let _ = app.emit(
+        "restore-complete",
+        TransferComplete {
+            filename,
+            total_bytes,
+        },
+    );
+
+    Ok(notes)
+}
+
+// 恢复预览：与当前图片库对比，不写入任何文件
+#[derive(Debug, Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RestorePreview {
+    pub new_images: usize,
+    pub existing_images: usize,
+    // 同名但内容不同，恢复时会被覆盖
+    pub conflicting_images: Vec<String>,
+    pub note_count: usize,
+}
+
+// only_missing 对应增量链的 parent：只有当前缺失的图片才会被恢复
+fn preview_backup_images(
+    backup: &BackupArchive,
+    images_path: &std::path::Path,
+    packs: &PackIndexCache,
+    only_missing: bool,
+    seen: &mut HashSet<String>,
+    preview: &mut RestorePreview,
+) -> Result<(), String> {
+    let mut archive = backup.zip()?;
+    let pack_index = packs.load(images_path);
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(|e| e.to_string())?;
+        let name = file.name().to_string();
+        let relative = match name.strip_prefix("images/") {
+            Some(relative) if !name.ends_with('/') => relative,
+            _ => continue,
+        };
+        // 按文件名比较，备份与当前存储的布局（平铺/分片）可能不同
+        let Some(dest_path) = restored_image_path(images_path, relative) else {
+            continue;
+        };
+        let relative = relative.rsplit('/').next().unwrap_or(relative).to_string();
+        if !seen.insert(relative.clone()) {
+            continue;
+        }
+
+        let exists = dest_path.exists() || pack_index.entries.contains_key(&relative);
+        if !exists {
+            preview.new_images += 1;
+            continue;
+        }
+        if only_missing {
+            preview.existing_images += 1;
+            continue;
+        }
+
+        let mut content = Vec::new();
+        file.read_to_end(&mut content).map_err(|e| e.to_string())?;
+        let identical = read_stored_image(images_path, packs, &relative)
+            .is_some_and(|stored| hash_bytes(&stored) == hash_bytes(&content));
+        if identical {
+            preview.existing_images += 1;
+        } else {
+            preview.conflicting_images.push(relative);
+        }
+    }
+
+    Ok(())
+}
+
+// Preview what restore_backup would change, without writing anything
+#[tauri::command]
+async fn preview_restore(
+    state: State<'_, Mutex<AppState>>,
+    packs: State<'_, PackIndexCache>,
+    app: AppHandle,
+    filename: String,
+    passphrase: Option<String>,
+) -> Result<RestorePreview, String> {
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    let settings: BackupSettings = store
+        .get("backupSettings")
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+
+    let backup_dir = resolve_backup_directory(settings.backup_directory)
+        .ok_or("Backup directory not configured")?;
+    let backup_dir = std::path::Path::new(&backup_dir);
+    let backup = open_backup_zip(&backup_file_path(&app, &filename)?, passphrase.as_deref())?;
+    let chain = resolve_backup_chain(backup_dir, &backup, passphrase.as_deref())?;
+
+    let data_json = {
+        let mut archive = backup.zip()?;
+        read_backup_data_json(&mut archive)?
+    };
+
+    let images_path = state.lock().unwrap().images_path.clone();
+    let mut preview = RestorePreview {
+        note_count: notes_from_data_json(&data_json)?.len(),
+        ..Default::default()
+    };
+    let mut seen = HashSet::new();
+    preview_backup_images(
+        &backup,
+        &images_path,
+        &packs,
+        false,
+        &mut seen,
+        &mut preview,
+    )?;
+    for archive in &chain {
+        preview_backup_images(archive, &images_path, &packs, true, &mut seen, &mut preview)?;
+    }
+
+    Ok(preview)
+}
+
+// 备份自检结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSelfTestResult {
+    pub passed: bool,
+    pub discrepancies: Vec<String>,
+    pub image_count: usize,
+    pub archive_bytes: u64,
+    pub backup_ms: u64,
+    pub restore_ms: u64,
+    pub verify_ms: u64,
+}
+
+// 在临时目录中完成一次备份 -> 恢复往返，不接触用户数据
+fn run_backup_self_test(scratch: &std::path::Path) -> Result<BackupSelfTestResult, String> {
+    let source_path = scratch.join("source");
+    let source_images = source_path.join("images");
+    let restored_path = scratch.join("restored");
+    let restored_images = restored_path.join("images");
+    fs::create_dir_all(&source_images).map_err(|e| e.to_string())?;
+    fs::create_dir_all(&restored_images).map_err(|e| e.to_string())?;
+
+    // 样例数据：一个笔记 + 若干大小不同的伪图片
+    let now = Local::now().timestamp_millis();
+    let notes = serde_json::json!({
+        "tabs": [{
+            "id": "selftest",
+            "title": "Self test",
+            "content": "LitePad backup self test",
+            "createdAt": now,
+            "updatedAt": now,
+        }],
+        "activeTabId": "selftest",
+    });
+    let data = serde_json::json!({ "flashpad-data": notes.to_string() }).to_string();
+
+    let mut expected: HashMap<String, String> = HashMap::new();
+    let mut seed: u32 = 0x2545_f491;
+    for size in [0usize, 1, 64, 4096, 70_000] {
+        let mut buffer = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+        for _ in 0..size {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            buffer.push(seed as u8);
+        }
+        let hash = hash_bytes(&buffer);
+        let filename = format!("{}.png", hash);
+        fs::write(source_images.join(&filename), &buffer).map_err(|e| e.to_string())?;
+        expected.insert(filename, hash);
+    }
+
+    let labels = r#"{"selftest":["label"]}"#;
+    fs::write(source_path.join(LABELS_FILE), labels).map_err(|e| e.to_string())?;
+
+    // 备份
+    let zip_path = scratch.join("selftest_backup.zip");
+    let started = std::time::Instant::now();
+    write_backup_archive(
+        &zip_path,
+        &data,
+        &source_path,
+        &source_images,
+        &[LABELS_FILE],
+        None,
+        BackupCompression::default(),
+        &rayon::ThreadPoolBuilder::new()
+            .build()
+            .map_err(|e| e.to_string())?,
+        &|_, _, _| Ok(()),
+    )?;
+    let backup_ms = started.elapsed().as_millis() as u64;
+    let archive_bytes = fs::metadata(&zip_path).map(|m| m.len()).unwrap_or(0);
+
+    // 恢复
+    let started = std::time::Instant::now();
+    let restored_data = extract_backup_archive(
+        &BackupArchive::plain(&zip_path),
+        &restored_path,
+        &restored_images,
+        &|_, _, _| Ok(()),
+    )?;
+    let restore_ms = started.elapsed().as_millis() as u64;
+
+    // 校验
+    let started = std::time::Instant::now();
+    let mut discrepancies = Vec::new();
+    if restored_data != data {
+        discrepancies.push("data.json content differs after restore".to_string());
+    }
+
+    for (filename, hash) in &expected {
+        match fs::read(restored_images.join(filename)) {
+            Ok(bytes) => {
+                let actual = hash_bytes(&bytes);
+                if &actual != hash {
+                    discrepancies.push(format!("{}: hash mismatch (got {})", filename, actual));
+                }
+            }
+            Err(e) => discrepancies.push(format!("{}: missing after restore ({})", filename, e)),
+        }
+    }
+
+    if let Ok(entries) = fs::read_dir(&restored_images) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !expected.contains_key(&name) {
+                discrepancies.push(format!("{}: unexpected file after restore", name));
+            }
+        }
+    }
+
+    match fs::read_to_string(restored_path.join(LABELS_FILE)) {
+        Ok(content) if content == labels => {}
+        Ok(_) => discrepancies.push(format!("{}: content differs after restore", LABELS_FILE)),
+        Err(e) => discrepancies.push(format!("{}: missing after restore ({})", LABELS_FILE, e)),
+    }
+    let verify_ms = started.elapsed().as_millis() as u64;
+
+    Ok(BackupSelfTestResult {
+        passed: discrepancies.is_empty(),
+        discrepancies,
+        image_count: expected.len(),
+        archive_bytes,
+        backup_ms,
+        restore_ms,
+        verify_ms,
+    })
+}
+
+// 备份自检：使用临时目录验证备份/恢复流程，结束后清理
+#[tauri::command]
+async fn self_test_backup() -> Result<BackupSelfTestResult, String> {
+    let scratch = std::env::temp_dir().join(format!("litepad_selftest_{}", Uuid::new_v4()));
+    let result = run_backup_self_test(&scratch);
+    let _ = fs::remove_dir_all(&scratch);
+    result
+}
+
+// Delete backup
+#[tauri::command]
+async fn delete_backup(app: AppHandle, filename: String) -> Result<(), String> {
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    let settings: BackupSettings = store
+        .get("backupSettings")
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+
+    let backup_dir = resolve_backup_directory(settings.backup_directory)
+        .ok_or("Backup directory not configured")?;
+    let file_path = backup_file_path(&app, &filename)?;
+
+    fs::remove_file(file_path).map_err(|e| e.to_string())?;
+    remove_backup_labels(std::path::Path::new(&backup_dir), &[filename])
+}
+
+// Set or clear (empty label) the user note shown for a backup
+#[tauri::command]
+async fn set_backup_label(
+    app: AppHandle,
+    filename: String,
+    label: Option<String>,
+) -> Result<(), String> {
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    let settings: BackupSettings = store
+        .get("backupSettings")
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+
+    let backup_dir = resolve_backup_directory(settings.backup_directory)
+        .ok_or("Backup directory not configured")?;
+    let backup_dir = std::path::Path::new(&backup_dir);
+    if !backup_file_path(&app, &filename)?.is_file() {
+        return Err(format!("Backup not found: {}", filename));
+    }
+
+    let mut labels = load_backup_labels(backup_dir);
+    match label.as_deref().map(str::trim).filter(|l| !l.is_empty()) {
+        Some(label) => {
+            labels.insert(filename, label.to_string());
+        }
+        None => {
+            labels.remove(&filename);
+        }
+    }
+    save_backup_labels(backup_dir, &labels)
+}
+
+// Import a backup zip chosen in an open dialog into the backup directory; returns None when cancelled
+#[tauri::command]
+async fn import_backup(app: AppHandle) -> Result<Option<BackupInfo>, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let source = match app
+        .dialog()
+        .file()
+        .add_filter("ZIP", &["zip"])
+        .blocking_pick_file()
+    {
+        Some(file_path) => file_path.into_path().map_err(|e| e.to_string())?,
+        None => return Ok(None),
+    };
+
+    // 必须包含 data.json；增量备份依赖其 parent，无法单独导入
+    let file = fs::File::open(&source).map_err(|e| e.to_string())?;
+    let mut archive = ZipArchive::new(file).map_err(|_| "NOT_A_LITEPAD_BACKUP".to_string())?;
+    if archive.by_name("data.json").is_err() {
+        return Err("NOT_A_LITEPAD_BACKUP".to_string());
+    }
+    let manifest = read_backup_manifest(&mut archive)?;
+    if manifest.as_ref().is_some_and(|m| m.parent.is_some()) {
+        return Err("INCREMENTAL_BACKUP_NOT_SUPPORTED".to_string());
+    }
+    drop(archive);
+
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    let settings: BackupSettings = store
+        .get("backupSettings")
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    let backup_dir = resolve_backup_directory(settings.backup_directory)
+        .ok_or("Backup directory not configured")?;
+    let backup_path = PathBuf::from(&backup_dir);
+    fs::create_dir_all(&backup_path).map_err(|e| e.to_string())?;
+
+    // 文件名不符合 litepad_backup_... 或已存在时，按备份时间重新命名
+    let source_name = source
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let filename = if is_backup_filename(&source_name) && !backup_path.join(&source_name).exists() {
+        source_name
+    } else {
+        let mut created = manifest
+            .and_then(|m| chrono::DateTime::from_timestamp_millis(m.created_at))
+            .map(|t| t.with_timezone(&Local).naive_local())
+            .or_else(|| backup_timestamp(&source_name))
+            .unwrap_or_else(|| Local::now().naive_local());
+        loop {
+            let name = format!("litepad_backup_{}.zip", created.format("%Y%m%d_%H%M%S"));
+            if !backup_path.join(&name).exists() {
+                break name;
+            }
+            created += chrono::Duration::seconds(1);
+        }
+    };
+
+    let dest = backup_path.join(&filename);
+    if dest != source {
+        fs::copy(&source, &dest).map_err(|e| e.to_string())?;
+    }
+    let metadata = fs::metadata(&dest).map_err(|e| e.to_string())?;
+    Ok(Some(backup_info(filename, &metadata)))
+}
+
+// Copy a backup to a location chosen in a save dialog; returns None when cancelled
+#[tauri::command]
+async fn export_backup(app: AppHandle, filename: String) -> Result<Option<String>, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let source = backup_file_path(&app, &filename)?;
+    if !source.is_file() {
+        return Err(format!("Backup not found: {}", filename));
+    }
+
+    let extension = match filename.ends_with(ENCRYPTED_BACKUP_EXTENSION) {
+        true => "enc",
+        false => "zip",
+    };
+    let dest = match app
+        .dialog()
+        .file()
+        .set_file_name(&filename)
+        .add_filter("LitePad Backup", &[extension])
+        .blocking_save_file()
+    {
+        Some(file_path) => file_path.into_path().map_err(|e| e.to_string())?,
+        None => return Ok(None),
+    };
+    if dest == source {
+        return Ok(Some(dest.to_string_lossy().to_string()));
+    }
+
+    fs::copy(&source, &dest).map_err(|e| e.to_string())?;
+    Ok(Some(dest.to_string_lossy().to_string()))
+}
+
+// Export data.json and all images to a zip at dest_path (or a path chosen in a save dialog); returns None when cancelled
+#[tauri::command]
+async fn export_all(
+    app: AppHandle,
+    dest_path: Option<String>,
+    data: String,
+) -> Result<Option<String>, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    if is_ephemeral(&app) {
+        return Err("EPHEMERAL_MODE".to_string());
+    }
+
+    let dest = match dest_path {
+        Some(path) => PathBuf::from(path),
+        None => match app
+            .dialog()
+            .file()
+            .set_file_name(format!(
+                "litepad_export_{}.zip",
+                Local::now().format("%Y%m%d_%H%M%S")
+            ))
+            .add_filter("ZIP", &["zip"])
+            .blocking_save_file()
+        {
+            Some(file_path) => file_path.into_path().map_err(|e| e.to_string())?,
+            None => return Ok(None),
+        },
+    };
+
+    let (data_path, images_path) = {
+        let state = app.state::<Mutex<AppState>>();
+        let state = state.lock().unwrap();
+        (state.data_path.clone(), state.images_path.clone())
+    };
+
+    let on_progress = |current: u64, total: u64, file: &str| -> Result<(), String> {
+        let _ = app.emit(
+            "export-progress",
+            TransferProgress {
+                current,
+                total,
+                current_file: file.to_string(),
+            },
+        );
+        Ok(())
+    };
+
+    // 与备份使用同一 zip 格式，但不参与备份轮换；先写临时文件，完成后再改名
+    let part_path = dest.with_extension("zip.part");
+    let data = format_json_snapshot(&data, pretty_json_enabled(&app));
+    let result = write_backup_archive(
+        &part_path,
+        &data,
+        &data_path,
+        &images_path,
+        &[IMAGE_META_DIR, LABELS_FILE],
+        None,
+        BackupCompression::default(),
+        &hashing_pool(&app)?,
+        &on_progress,
+    )
+    .and_then(|_| fs::rename(&part_path, &dest).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        let _ = fs::remove_file(&part_path);
+        return Err(e);
+    }
+
+    Ok(Some(dest.to_string_lossy().to_string()))
+}
+
+// 导入结果：data.json 内容交由前端加载
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportAllResult {
+    pub data: String,
+    pub imported_images: u32,
+    // 内容与文件名 hash 不一致而跳过的图片
+    pub corrupt_images: Vec<String>,
+}
+
+// Import a full export into the current data directory; refuses when notes already exist unless forced
+#[tauri::command]
+async fn import_all(
+    app: AppHandle,
+    source_path: String,
+    force: Option<bool>,
+) -> Result<ImportAllResult, String> {
+    if is_ephemeral(&app) {
+        return Err("EPHEMERAL_MODE".to_string());
+    }
+    let (data_path, images_path) = {
+        let state = app.state::<Mutex<AppState>>();
+        let state = state.lock().unwrap();
+        (state.data_path.clone(), state.images_path.clone())
+    };
+
+    // 已有笔记时默认拒绝，避免覆盖正在使用的数据
+    let has_notes = fs::read_to_string(data_path.join("data.json"))
+        .ok()
+        .and_then(|content| notes_from_data_json(&content).ok())
+        .is_some_and(|notes| !notes.is_empty());
+    if has_notes && !force.unwrap_or(false) {
+        return Err("DATA_NOT_EMPTY".to_string());
+    }
+
+    let file = fs::File::open(&source_path).map_err(|e| e.to_string())?;
+    let mut archive = ZipArchive::new(file).map_err(|e| e.to_string())?;
+    let data = read_backup_data_json(&mut archive)?;
+    notes_from_data_json(&data)?;
+
+    let total = archive
+        .file_names()
+        .filter(|name| name.starts_with("images/") && !name.ends_with('/'))
+        .count() as u64;
+    let mut current = 0;
+    let mut imported_images = 0;
+    let mut corrupt_images = Vec::new();
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(|e| e.to_string())?;
+        let name = file.name().to_string();
+        if name.ends_with('/') {
+            continue;
+        }
+
+        if let Some(relative) = name.strip_prefix("images/") {
+            current += 1;
+            let _ = app.emit(
+                "import-progress",
+                TransferProgress {
+                    current,
+                    total,
+                    current_file: relative.to_string(),
+                },
+            );
+            let Some(dest_path) = restored_image_path(&images_path, relative) else {
+                continue;
+            };
+            let mut content = Vec::new();
+            file.read_to_end(&mut content).map_err(|e| e.to_string())?;
+
+            let filename = relative.rsplit('/').next().unwrap_or(relative);
+            if let Some(expected) = hash_from_image_filename(filename) {
+                if hash_bytes(&content) != expected {
+                    corrupt_images.push(filename.to_string());
+                    continue;
+                }
+            }
+            if let Some(parent) = dest_path.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            write_atomic(&dest_path, &content)?;
+            imported_images += 1;
+        } else if is_sidecar_entry(&name) {
+            if let Some(relative) = file.enclosed_name() {
+                let dest_path = data_path.join(relative);
+                if let Some(parent) = dest_path.parent() {
+                    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                write_atomic_from(&dest_path, &mut file)?;
+            }
+        }
+    }
+
+    Ok(ImportAllResult {
+        data,
+        imported_images,
+        corrupt_images,
+    })
+}
+
+// Open the backup directory in the system file manager, optionally selecting a backup
+#[tauri::command]
+async fn open_backup_directory(app: AppHandle, filename: Option<String>) -> Result<(), String> {
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    let settings: BackupSettings = store
+        .get("backupSettings")
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+
+    let backup_dir = resolve_backup_directory(settings.backup_directory)
+        .ok_or("Backup directory not configured")?;
+    let backup_path = std::path::Path::new(&backup_dir);
+
+    if !backup_path.exists() {
+        fs::create_dir_all(backup_path).map_err(|e| e.to_string())?;
+    }
+
+    // 在文件管理器中选中指定备份
+    if let Some(name) = &filename {
+        validate_backup_filename(name)?;
+    }
+    if let Some(file_path) = filename
+        .map(|name| backup_path.join(name))
+        .filter(|path| path.is_file())
+    {
+        return reveal_in_file_manager(&app, &file_path);
+    }
+
+    #[allow(deprecated)]
+    app.shell()
+        .open(backup_path.to_string_lossy().to_string(), None)
+        .map_err(|e| e.to_string())
+}
+
+// Get default backup directory
+#[tauri::command]
+fn get_default_backup_dir() -> Option<String> {
+    get_default_backup_directory()
+}
+
+// Validate backup path
+#[tauri::command]
+fn validate_backup_path(path: String) -> PathValidationResult {
+    let path = std::path::Path::new(&path);
+
+    // Check if path exists
+    let exists = path.exists();
+
+    // Check if writable
+    let is_writable = if exists {
+        // Try to create a test file
+        let test_file = path.join(".litepad_write_test");
+        match fs::File::create(&test_file) {
+            Ok(_) => {
+                let _ = fs::remove_file(&test_file);
+                true
+            }
+            Err(_) => false,
+        }
+    } else {
+        // Path doesn't exist, check if parent directory exists and is writable
+        if let Some(parent) = path.parent() {
+            if parent.exists() {
+                let test_file = parent.join(".litepad_write_test");
+                match fs::File::create(&test_file) {
+                    Ok(_) => {
+                        let _ = fs::remove_file(&test_file);
+                        true
+                    }
+                    Err(_) => false,
+                }
+            } else {
+                false
+            }
+        } else {
+            false
+        }
+    };
+
+    let (is_valid, error_code) = match (exists, is_writable) {
+        (true, true) => (true, None),
+        (true, false) => (false, Some("NO_WRITE_PERMISSION".to_string())),
+        (false, true) => (true, None), // Can be created
+        (false, false) => (false, Some("PATH_NOT_ACCESSIBLE".to_string())),
+    };
+
+    PathValidationResult {
+        is_valid,
+        exists,
+        is_writable,
+        error_code,
+    }
+}
+
+// Check for updates; a cached result younger than max_age_secs is returned unless force is set
+#[tauri::command]
+async fn check_for_updates(
+    app: AppHandle,
+    include_prereleases: Option<bool>,
+    max_age_secs: Option<u64>,
+    force: Option<bool>,
+) -> Result<UpdateInfo, String> {
+    let include_prereleases = include_prereleases.unwrap_or(false);
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+
+    if let (Some(max_age_secs), false) = (max_age_secs, force.unwrap_or(false)) {
+        let cached = store
+            .get("updateCache")
+            .and_then(|v| serde_json::from_value::<UpdateCache>(v).ok())
+            .filter(|cache| {
+                let age_ms = Local::now().timestamp_millis() - cache.info.checked_at;
+                cache.include_prereleases == include_prereleases
+                    && cache.info.current_version == env!("CARGO_PKG_VERSION")
+                    && (0..=max_age_secs as i64 * 1000).contains(&age_ms)
+            });
+        if let Some(cache) = cached {
+            return Ok(UpdateInfo {
+                from_cache: true,
+                ..cache.info
+            });
+        }
+    }
+
+    let info = fetch_update_info(include_prereleases).await?;
+    let cache = UpdateCache {
+        info,
+        include_prereleases,
+    };
+    store.set(
+        "updateCache",
+        serde_json::to_value(&cache).map_err(|e| e.to_string())?,
+    );
+    persist_store(&app, &store)?;
+
+    Ok(cache.info)
+}
+
+// 请求 GitHub Releases 并生成更新信息
+async fn fetch_update_info(include_prereleases: bool) -> Result<UpdateInfo, String> {
+    let current_version = env!("CARGO_PKG_VERSION");
+
+    // GitHub API URL (使用官方 REST API v3)；包含预发布版本时从 releases 列表中挑选最新版本
+    let url = if include_prereleases {
+        "https://api.github.com/repos/L0dyv/LitePad/releases?per_page=20"
+    } else {
+        "https://api.github.com/repos/L0dyv/LitePad/releases/latest"
+    };
+
+    // 创建 HTTP 客户端
+    let client = reqwest::Client::builder()
+        .user_agent("LitePad-Update-Checker")
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+    // 发送请求
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("Network error: {}", e))?;
+
+    // 检查响应状态
+    if !response.status().is_success() {
+        return Err(format!("GitHub API error: {}", response.status()));
+    }
+
+    // 解析 JSON
+    let release: GitHubRelease = if include_prereleases {
+        let releases: Vec<GitHubRelease> = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse response: {}", e))?;
+        releases
+            .into_iter()
+            .filter(|r| !r.draft)
+            .filter_map(|r| parse_version(&r.tag_name).map(|v| (v, r)))
+            .max_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(_, r)| r)
+            .ok_or("No releases found")?
+    } else {
+        response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse response: {}", e))?
+    };
+
+    // 比较版本
+    let has_update = compare_versions(current_version, &release.tag_name);
+
+    // 匹配当前平台的安装包
+    let asset = select_platform_asset(&release.assets).map(UpdateAsset::from);
+    let assets = release.assets.iter().map(UpdateAsset::from).collect();
+
+    Ok(UpdateInfo {
+        has_update,
+        current_version: current_version.to_string(),
+        latest_version: Some(release.tag_name),
+        release_url: Some(release.html_url),
+        release_notes: release.body,
+        published_at: release.published_at,
+        asset,
+        assets,
+        checked_at: Local::now().timestamp_millis(),
+        from_cache: false,
+    })
+}
+
+// 更新安装包下载缓存目录（未完成的下载以 .part 结尾）
+fn update_cache_dir() -> PathBuf {
+    std::env::temp_dir().join("LitePad").join("updates")
+}
+
+// 下载更新与清理缓存互斥：下载中的 .part 不能被删除，同一时间也只允许一个下载
+static UPDATE_CACHE_BUSY: AtomicBool = AtomicBool::new(false);
+
+struct UpdateCacheGuard;
+
+impl UpdateCacheGuard {
+    fn acquire() -> Result<Self, String> {
+        UPDATE_CACHE_BUSY
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .map_err(|_| "UPDATE_CACHE_BUSY".to_string())?;
+        Ok(UpdateCacheGuard)
+    }
+}
+
+impl Drop for UpdateCacheGuard {
+    fn drop(&mut self) {
+        UPDATE_CACHE_BUSY.store(false, Ordering::Release);
+    }
+}
+
+// 从安装包文件名中提取版本号，如 LitePad_3.1.0_x64-setup.exe -> 3.1.0
+fn version_from_filename(name: &str) -> Option<String> {
+    name.split(['_', '-', ' '])
+        .map(|part| part.trim_start_matches('v'))
+        .find(|part| {
+            let numbers: Vec<&str> = part.split('.').take(3).collect();
+            numbers.len() == 3
+                && numbers
+                    .iter()
+                    .all(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+        })
+        .map(|part| part.split('.').take(3).collect::<Vec<_>>().join("."))
+}
+
+// 更新缓存条目
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCacheEntry {
+    pub filename: String,
+    pub version: Option<String>,
+    pub size: u64,
+    pub complete: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCacheStats {
+    pub directory: String,
+    pub total_bytes: u64,
+    pub entries: Vec<UpdateCacheEntry>,
+}
+
+fn collect_update_cache_stats() -> UpdateCacheStats {
+    let dir = update_cache_dir();
+    let mut entries: Vec<UpdateCacheEntry> = fs::read_dir(&dir)
+        .map(|read_dir| {
+            read_dir
+                .filter_map(|e| e.ok())
+                .filter_map(|e| {
+                    let metadata = e.metadata().ok()?;
+                    if !metadata.is_file() {
+                        return None;
+                    }
+                    let filename = e.file_name().to_string_lossy().to_string();
+                    Some(UpdateCacheEntry {
+                        version: version_from_filename(&filename),
+                        complete: !filename.ends_with(".part"),
+                        size: metadata.len(),
+                        filename,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    entries.sort_by(|a, b| a.filename.cmp(&b.filename));
+
+    UpdateCacheStats {
+        directory: dir.to_string_lossy().to_string(),
+        total_bytes: entries.iter().map(|e| e.size).sum(),
+        entries,
+    }
+}
+
+// List cached update downloads
+#[tauri::command]
+fn get_update_cache_stats() -> UpdateCacheStats {
+    collect_update_cache_stats()
+}
+
+// 安装包下载进度（update-download-progress 事件）
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateDownloadProgress {
+    pub filename: String,
+    pub downloaded: u64,
+    pub total: Option<u64>,
+}
+
+// 从下载地址中取出安装包文件名（URL 最后一段）
+fn update_asset_filename(asset_url: &str) -> Result<String, String> {
+    let path = asset_url
+        .split(|c| c == '?' || c == '#')
+        .next()
+        .unwrap_or_default();
+    let name = path.rsplit('/').next().unwrap_or_default();
+    if name.is_empty() || name == "." || name == ".." || name.contains('\\') {
+        return Err(format!("Invalid asset URL: {}", asset_url));
+    }
+    Ok(name.to_string())
+}
+
+// 在 release notes 中查找安装包的 SHA-256：优先匹配包含文件名的行，否则仅当全文只有一个哈希时使用
+fn expected_sha256(release_notes: &str, filename: &str) -> Option<String> {
+    let hashes_in = |line: &str| -> Vec<String> {
+        line.split(|c: char| !c.is_ascii_hexdigit())
+            .filter(|token| token.len() == 64)
+            .map(|token| token.to_ascii_lowercase())
+            .collect()
+    };
+
+    if let Some(hash) = release_notes
+        .lines()
+        .filter(|line| line.contains(filename))
+        .find_map(|line| hashes_in(line).into_iter().next())
+    {
+        return Some(hash);
+    }
+
+    let mut all: Vec<String> = hashes_in(release_notes);
+    all.dedup();
+    if all.len() == 1 {
+        all.pop()
+    } else {
+        None
+    }
+}
+
+// Download an update installer into dest_dir (default: the update cache) and return its path; the installer is not run
+#[tauri::command]
+async fn download_update(
+    app: AppHandle,
+    asset_url: String,
+    dest_dir: Option<String>,
+) -> Result<String, String> {
+    if !asset_url.starts_with("https://") {
+        return Err(format!("Invalid asset URL: {}", asset_url));
+    }
+    let filename = update_asset_filename(&asset_url)?;
+    let _busy = UpdateCacheGuard::acquire()?;
+    let dest_dir = dest_dir
+        .filter(|d| !d.trim().is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(update_cache_dir);
+    fs::create_dir_all(&dest_dir).map_err(|e| e.to_string())?;
+
+    // 从上一次更新检查的缓存中读取 release notes
+    let release_notes = app
+        .store("config.json")
+        .ok()
+        .and_then(|store| store.get("updateCache"))
+        .and_then(|v| serde_json::from_value::<UpdateCache>(v).ok())
+        .and_then(|cache| cache.info.release_notes);
+    let expected = release_notes
+        .as_deref()
+        .and_then(|notes| expected_sha256(notes, &filename));
+
+    // 与更新检查相同的 user-agent；安装包较大，只限制连接超时
+    let client = reqwest::Client::builder()
+        .user_agent("LitePad-Update-Checker")
+        .connect_timeout(std::time::Duration::from_secs(10))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+    let mut response = client
+        .get(&asset_url)
+        .send()
+        .await
+        .map_err(|e| format!("Network error: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Download error: {}", response.status()));
+    }
+    let total = response.content_length();
+
+    let final_path = dest_dir.join(&filename);
+    let part_path = dest_dir.join(format!("{}.part", filename));
+    let mut file = fs::File::create(&part_path).map_err(|e| e.to_string())?;
+    let mut hasher = Sha256::new();
+    let mut downloaded: u64 = 0;
+    let mut last_emitted: u64 = 0;
+
+    let result: Result<(), String> = async {
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?
+        {
+            file.write_all(&chunk).map_err(|e| e.to_string())?;
+            hasher.update(&chunk);
+            downloaded += chunk.len() as u64;
+
+            // 每 256KB 通知一次进度
+            if downloaded - last_emitted >= 256 * 1024 {
+                last_emitted = downloaded;
+                let _ = app.emit(
+                    "update-download-progress",
+                    UpdateDownloadProgress {
+                        filename: filename.clone(),
+                        downloaded,
+                        total,
+                    },
+                );
+            }
+        }
+        file.flush().map_err(|e| e.to_string())?;
+
+        if let Some(total) = total {
+            if downloaded != total {
+                return Err(format!(
+                    "Incomplete download: {} of {} bytes",
+                    downloaded, total
+                ));
+            }
+        }
+        if let Some(expected) = &expected {
+            if hex::encode(hasher.finalize()) != *expected {
+                return Err("CHECKSUM_MISMATCH".to_string());
+            }
+        }
+        Ok(())
+    }
+    .await;
+    drop(file);
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&part_path);
+        return Err(e);
+    }
+
+    let _ = app.emit(
+        "update-download-progress",
+        UpdateDownloadProgress {
+            filename: filename.clone(),
+            downloaded,
+            total,
+        },
+    );
+
+    if final_path.exists() {
+        fs::remove_file(&final_path).map_err(|e| e.to_string())?;
+    }
+    fs::rename(&part_path, &final_path).map_err(|e| e.to_string())?;
+
+    Ok(final_path.to_string_lossy().to_string())
+}
+
+const INSTALLER_EXTENSIONS: &[&str] = &[
+    ".msi",
+    ".exe",
+    ".dmg",
+    ".pkg",
+    ".app.tar.gz",
+    ".appimage",
+    ".deb",
+    ".rpm",
+];
+
+fn is_installer_filename(name: &str) -> bool {
+    let name = name.to_lowercase();
+    INSTALLER_EXTENSIONS.iter().any(|ext| name.ends_with(ext))
+}
+
+// 已下载到更新缓存目录的安装包
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StagedUpdate {
+    pub path: String,
+    pub filename: String,
+    pub version: String,
+    pub size: u64,
+    // release notes 中有 SHA-256 时才校验，否则为 None
+    pub checksum_verified: Option<bool>,
+    pub valid: bool,
+}
+
+// Find an installer for the latest known version already downloaded to the update cache
+#[tauri::command]
+async fn get_staged_update(app: AppHandle) -> Result<Option<StagedUpdate>, String> {
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    let Some(info) = store
+        .get("updateCache")
+        .and_then(|v| serde_json::from_value::<UpdateCache>(v).ok())
+        .map(|cache| cache.info)
+        .filter(|info| info.has_update)
+    else {
+        return Ok(None);
+    };
+    let Some(latest) = info.latest_version.as_deref() else {
+        return Ok(None);
+    };
+    let latest = latest.trim_start_matches('v');
+
+    // 优先使用当前平台的安装包，否则按文件名中的版本号查找
+    let dir = update_cache_dir();
+    let asset = info
+        .asset
+        .as_ref()
+        .filter(|asset| dir.join(&asset.name).is_file());
+    let filename = match asset {
+        Some(asset) => asset.name.clone(),
+        None => {
+            let found = fs::read_dir(&dir)
+                .map(|read_dir| {
+                    read_dir
+                        .filter_map(|e| e.ok())
+                        .map(|e| e.file_name().to_string_lossy().to_string())
+                        .filter(|name| is_installer_filename(name))
+                        .find(|name| version_from_filename(name).as_deref() == Some(latest))
+                })
+                .unwrap_or_default();
+            match found {
+                Some(name) => name,
+                None => return Ok(None),
+            }
+        }
+    };
+
+    let path = dir.join(&filename);
+    let size = fs::metadata(&path).map_err(|e| e.to_string())?.len();
+    let checksum_verified = match info
+        .release_notes
+        .as_deref()
+        .and_then(|notes| expected_sha256(notes, &filename))
+    {
+        Some(expected) => {
+            let path = path.clone();
+            let actual = tauri::async_runtime::spawn_blocking(move || hash_file(&path))
+                .await
+                .map_err(|e| e.to_string())??;
+            Some(actual == expected)
+        }
+        None => None,
+    };
+    let size_matches = asset
+        .map(|asset| asset.size == 0 || asset.size == size)
+        .unwrap_or(true);
+
+    Ok(Some(StagedUpdate {
+        path: path.to_string_lossy().to_string(),
+        version: latest.to_string(),
+        size,
+        valid: size_matches && checksum_verified != Some(false),
+        checksum_verified,
+        filename,
+    }))
+}
+
+// Run a downloaded installer from the update cache, optionally quitting the app afterwards
+#[tauri::command]
+fn launch_installer(app: AppHandle, path: String, quit: Option<bool>) -> Result<(), String> {
+    // 只允许运行更新缓存目录中的安装包
+    let dir = update_cache_dir()
+        .canonicalize()
+        .map_err(|e| e.to_string())?;
+    let path = PathBuf::from(&path)
+        .canonicalize()
+        .map_err(|e| e.to_string())?;
+    let filename = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if path.parent() != Some(dir.as_path()) || !path.is_file() || !is_installer_filename(&filename)
+    {
+        return Err(format!("Not a staged installer: {}", path.display()));
+    }
+
+    let lower = filename.to_lowercase();
+    if cfg!(windows) && lower.ends_with(".msi") {
+        // msiexec 会自行请求管理员权限
+        std::process::Command::new("msiexec")
+            .arg("/i")
+            .arg(&path)
+            .spawn()
+            .map_err(|e| e.to_string())?;
+    } else if lower.ends_with(".appimage") {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+                .map_err(|e| e.to_string())?;
+        }
+        std::process::Command::new(&path)
+            .spawn()
+            .map_err(|e| e.to_string())?;
+    } else {
+        // Windows 上通过 ShellExecute 打开，安装包清单中的权限要求会触发 UAC
+        #[allow(deprecated)]
+        app.shell()
+            .open(path.to_string_lossy().to_string(), None)
+            .map_err(|e| e.to_string())?;
+    }
+
+    if quit.unwrap_or(false) {
+        request_quit(&app);
+    }
+    Ok(())
+}
+
+// Delete all cached update downloads, returns bytes freed; fails while a download is running
+#[tauri::command]
+fn clear_update_cache() -> Result<u64, String> {
+    let _busy = UpdateCacheGuard::acquire()?;
+    let dir = update_cache_dir();
+    if !dir.exists() {
+        return Ok(0);
+    }
+
+    let mut freed = 0;
+    for entry in fs::read_dir(&dir)
+        .map_err(|e| e.to_string())?
+        .filter_map(|e| e.ok())
+    {
+        let path = entry.path();
+        if path.is_file() {
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            fs::remove_file(&path).map_err(|e| e.to_string())?;
+            freed += size;
+        }
+    }
+
+    Ok(freed)
+}
+
+// 旧版 Electron 配置迁移结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacyMigrationResult {
+    pub found: bool,
+    pub source_path: Option<String>,
+    pub backup_path: Option<String>,
+    pub migrated_keys: Vec<String>,
+}
+
+// electron-store 默认写在 userData/config.json（Windows: %APPDATA%, macOS: Application Support, Linux: ~/.config）
+fn legacy_config_candidates() -> Vec<PathBuf> {
+    let config_dir = match dirs::config_dir() {
+        Some(dir) => dir,
+        None => return vec![],
+    };
+    ["LitePad", "litepad", "FlashPad", "flashpad"]
+        .iter()
+        .map(|name| config_dir.join(name).join("config.json"))
+        .collect()
+}
+
+// 旧配置中的字段可能嵌套在对象里（settings.alwaysOnTop），也可能是平铺的（alwaysOnTop）
+fn legacy_lookup<'a>(
+    legacy: &'a serde_json::Value,
+    section: &str,
+    key: &str,
+) -> Option<&'a serde_json::Value> {
+    legacy
+        .get(section)
+        .and_then(|s| s.get(key))
+        .or_else(|| legacy.get(key))
+}
+
+// Detect and migrate legacy electron-store config into the Tauri store
+#[tauri::command]
+async fn migrate_legacy_config(app: AppHandle) -> Result<LegacyMigrationResult, String> {
+    if is_ephemeral(&app) {
+        return Err("EPHEMERAL_MODE".to_string());
+    }
+
+    let legacy_path = match legacy_config_candidates().into_iter().find(|p| p.is_file()) {
+        Some(path) => path,
+        None => {
+            return Ok(LegacyMigrationResult {
+                found: false,
+                source_path: None,
+                backup_path: None,
+                migrated_keys: vec![],
+            })
+        }
+    };
+
+    let content = fs::read_to_string(&legacy_path).map_err(|e| e.to_string())?;
+    let legacy: serde_json::Value =
+        serde_json::from_str(&content).map_err(|e| format!("Invalid legacy config: {}", e))?;
+
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    let mut migrated_keys = Vec::new();
+
+    // settings
+    let mut settings: Settings = store
+        .get("settings")
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    if let Some(v) = legacy_lookup(&legacy, "settings", "autoLaunch").and_then(|v| v.as_bool()) {
+        settings.auto_launch = v;
+        migrated_keys.push("settings.autoLaunch".to_string());
+    }
+    if let Some(v) = legacy_lookup(&legacy, "settings", "alwaysOnTop").and_then(|v| v.as_bool()) {
+        settings.always_on_top = v;
+        migrated_keys.push("settings.alwaysOnTop".to_string());
+    }
+
+    // backupSettings
+    let mut backup_settings: BackupSettings = store
+        .get("backupSettings")
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    if let Some(v) =
+        legacy_lookup(&legacy, "backupSettings", "backupDirectory").and_then(|v| v.as_str())
+    {
+        backup_settings.backup_directory = Some(v.to_string());
+        migrated_keys.push("backupSettings.backupDirectory".to_string());
+    }
+    if let Some(v) = legacy_lookup(&legacy, "backupSettings", "maxBackups").and_then(|v| v.as_u64())
+    {
+        backup_settings.max_backups = v as u32;
+        migrated_keys.push("backupSettings.maxBackups".to_string());
+    }
+    if let Some(v) =
+        legacy_lookup(&legacy, "backupSettings", "autoBackupEnabled").and_then(|v| v.as_bool())
+    {
+        backup_settings.auto_backup_enabled = v;
+        migrated_keys.push("backupSettings.autoBackupEnabled".to_string());
+    }
+    if let Some(v) =
+        legacy_lookup(&legacy, "backupSettings", "autoBackupInterval").and_then(|v| v.as_u64())
+    {
+        backup_settings.auto_backup_interval = v as u32;
+        migrated_keys.push("backupSettings.autoBackupInterval".to_string());
+    }
+
+    // windowBounds（electron-store 中与当前结构一致）
+    if let Some(bounds) = legacy
+        .get("windowBounds")
+        .and_then(|v| serde_json::from_value::<WindowBounds>(v.clone()).ok())
+    {
+        store.set("windowBounds", serde_json::to_value(&bounds).unwrap());
+        migrated_keys.push("windowBounds".to_string());
+    }
+
+    store.set("settings", serde_json::to_value(&settings).unwrap());
+    store.set(
+        "backupSettings",
+        serde_json::to_value(&backup_settings).unwrap(),
+    );
+    persist_store(&app, &store)?;
+
+    // 保留旧文件作为备份，而不是删除
+    let backup_path = legacy_path.with_extension("json.migrated.bak");
+    fs::rename(&legacy_path, &backup_path).map_err(|e| e.to_string())?;
+
+    Ok(LegacyMigrationResult {
+        found: true,
+        source_path: Some(legacy_path.to_string_lossy().to_string()),
+        backup_path: Some(backup_path.to_string_lossy().to_string()),
+        migrated_keys,
+    })
+}
+
+// 诊断信息（用于排查问题）
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsReport {
+    pub version: String,
+    pub os: String,
+    pub arch: String,
+    pub ephemeral: bool,
+    pub data_path: String,
+    pub data_dir_writable: bool,
+    pub images_path: String,
+    pub image_count: u64,
+    pub image_bytes: u64,
+    pub backup_directory: Option<String>,
+    pub backup_directory_exists: bool,
+    pub backup_directory_writable: bool,
+    pub update_cache_bytes: u64,
+}
+
+fn collect_diagnostics(
+    app: &AppHandle,
+    data_path: &std::path::Path,
+    images_path: &std::path::Path,
+    ephemeral: bool,
+) -> DiagnosticsReport {
+    let (image_count, image_bytes) = WalkDir::new(images_path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && e.file_name() != SHARDED_LAYOUT_MARKER)
+        .fold((0u64, 0u64), |(count, bytes), e| {
+            let size = e.metadata().map(|m| m.len()).unwrap_or(0);
+            (count + 1, bytes + size)
+        });
+
+    let backup_settings: BackupSettings = app
+        .store("config.json")
+        .ok()
+        .and_then(|store| store.get("backupSettings"))
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    let backup_directory = resolve_backup_directory(backup_settings.backup_directory);
+    // 可写性检测会创建测试文件，临时模式下跳过
+    let (backup_directory_exists, backup_directory_writable) = match &backup_directory {
+        Some(_) if ephemeral => (false, false),
+        Some(dir) => {
+            let validation = validate_backup_path(dir.clone());
+            (validation.exists, validation.is_writable)
+        }
+        None => (false, false),
+    };
+
+    DiagnosticsReport {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        ephemeral,
+        data_path: data_path.to_string_lossy().to_string(),
+        data_dir_writable: !ephemeral
+            && validate_backup_path(data_path.to_string_lossy().to_string()).is_writable,
+        images_path: images_path.to_string_lossy().to_string(),
+        image_count,
+        image_bytes,
+        backup_directory,
+        backup_directory_exists,
+        backup_directory_writable,
+        update_cache_bytes: collect_update_cache_stats().total_bytes,
+    }
+}
+
+// 生效配置项及其来源（default / env / cli / store）
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveConfigEntry {
+    pub key: String,
+    pub value: serde_json::Value,
+    pub source: String,
+}
+
+fn config_entry(key: &str, value: serde_json::Value, source: &str) -> EffectiveConfigEntry {
+    EffectiveConfigEntry {
+        key: key.to_string(),
+        value,
+        source: source.to_string(),
+    }
+}
+
+// store 中显式设置的值优先，否则为默认值
+fn store_or_default_entry(
+    stored: &Option<serde_json::Value>,
+    key: &str,
+    field: &str,
+    default: serde_json::Value,
+) -> EffectiveConfigEntry {
+    match stored.as_ref().and_then(|v| v.get(field)) {
+        Some(value) if !value.is_null() => config_entry(key, value.clone(), "store"),
+        _ => config_entry(key, default, "default"),
+    }
+}
+
+fn collect_effective_config(app: &AppHandle) -> Vec<EffectiveConfigEntry> {
+    let (data_path, ephemeral) = {
+        let state = app.state::<Mutex<AppState>>();
+        let state = state.lock().unwrap();
+        (state.data_path.clone(), state.ephemeral)
+    };
+    let store = app.store("config.json").ok();
+    let settings = store.as_ref().and_then(|s| s.get("settings"));
+    let backup_settings = store.as_ref().and_then(|s| s.get("backupSettings"));
+    let default_settings = Settings::default();
+
+    let mut entries = vec![
+        config_entry(
+            "dataDir",
+            serde_json::json!(data_path.to_string_lossy()),
+            "default",
+        ),
+        config_entry(
+            "ephemeral",
+            serde_json::json!(ephemeral),
+            if ephemeral { "cli" } else { "default" },
+        ),
+        store_or_default_entry(
+            &settings,
+            "offlineMode",
+            "offlineMode",
+            serde_json::json!(default_settings.offline_mode),
+        ),
+        store_or_default_entry(
+            &settings,
+            "alwaysOnTop",
+            "alwaysOnTop",
+            serde_json::json!(default_settings.always_on_top),
+        ),
+        store_or_default_entry(
+            &settings,
+            "saveIntervalMs",
+            "saveIntervalMs",
+            serde_json::json!(default_settings.save_interval_ms),
+        ),
+        store_or_default_entry(
+            &backup_settings,
+            "backupDirectory",
+            "backupDirectory",
+            serde_json::json!(get_default_backup_directory()),
+        ),
+    ];
+
+    // reqwest 会读取系统代理环境变量
+    let proxy = [
+        "HTTPS_PROXY",
+        "https_proxy",
+        "HTTP_PROXY",
+        "http_proxy",
+        "ALL_PROXY",
+    ]
+    .iter()
+    .find_map(|name| std::env::var(name).ok().map(|v| (*name, v)));
+    entries.push(match proxy {
+        Some((name, value)) => {
+            config_entry("proxy", serde_json::json!(value), &format!("env:{}", name))
+        }
+        None => config_entry("proxy", serde_json::Value::Null, "default"),
+    });
+
+    let locale = ["LC_ALL", "LANG"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok().map(|v| (*name, v)));
+    entries.push(match locale {
+        Some((name, value)) => {
+            config_entry("locale", serde_json::json!(value), &format!("env:{}", name))
+        }
+        None => config_entry("locale", serde_json::Value::Null, "default"),
+    });
+
+    entries
+}
+
+// Get the resolved value and source of each setting that influences behavior
+#[tauri::command]
+fn get_effective_config(app: AppHandle) -> Vec<EffectiveConfigEntry> {
+    collect_effective_config(&app)
+}
+
+// Whether this session runs in ephemeral (in-memory only) mode
+#[tauri::command]
+fn is_ephemeral_mode(state: State<'_, Mutex<AppState>>) -> bool {
+    state.lock().unwrap().ephemeral
+}
+
+// Run diagnostics
+#[tauri::command]
+async fn run_diagnostics(
+    state: State<'_, Mutex<AppState>>,
+    app: AppHandle,
+) -> Result<DiagnosticsReport, String> {
+    let (data_path, images_path, ephemeral) = {
+        let state = state.lock().unwrap();
+        (
+            state.data_path.clone(),
+            state.images_path.clone(),
+            state.ephemeral,
+        )
+    };
+    Ok(collect_diagnostics(
+        &app,
+        &data_path,
+        &images_path,
+        ephemeral,
+    ))
+}
+
+// 支持包脱敏规则：
+// 1. 键名包含 password / passphrase / token / secret 的字段直接移除
+// 2. scrub_paths 为 true 时，看起来像文件路径的字符串值替换为 "<redacted-path>"
+// 笔记内容 (data.json) 和图片永远不会被打包
+const REDACTED_KEY_MARKERS: [&str; 4] = ["password", "passphrase", "token", "secret"];
+
+fn looks_like_path(s: &str) -> bool {
+    s.starts_with('/')
+        || s.starts_with('~')
+        || s.starts_with("\\\\")
+        || (s.len() >= 3
+            && s.as_bytes()[1] == b':'
+            && (s.as_bytes()[2] == b'\\' || s.as_bytes()[2] == b'/'))
+}
+
+fn redact_value(value: &mut serde_json::Value, scrub_paths: bool) {
+    match value {
+        serde_json::Value::Object(map) => {
+            map.retain(|key, _| {
+                let key = key.to_lowercase();
+                !REDACTED_KEY_MARKERS.iter().any(|m| key.contains(m))
+            });
+            for v in map.values_mut() {
+                redact_value(v, scrub_paths);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for v in items.iter_mut() {
+                redact_value(v, scrub_paths);
+            }
+        }
+        serde_json::Value::String(s) => {
+            if scrub_paths && looks_like_path(s) {
+                *s = "<redacted-path>".to_string();
+            }
+        }
+        _ => {}
+    }
+}
+
+// 最近的日志文件（data/logs/*.log），按修改时间倒序
+fn recent_log_files(data_path: &std::path::Path, limit: usize) -> Vec<PathBuf> {
+    let mut logs: Vec<(std::time::SystemTime, PathBuf)> = fs::read_dir(data_path.join("logs"))
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.is_file() && p.extension().and_then(|e| e.to_str()) == Some("log"))
+                .map(|p| {
+                    let modified = fs::metadata(&p)
+                        .and_then(|m| m.modified())
+                        .unwrap_or(std::time::UNIX_EPOCH);
+                    (modified, p)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    logs.sort_by(|a, b| b.0.cmp(&a.0));
+    logs.into_iter().take(limit).map(|(_, p)| p).collect()
+}
+
+// Create a support bundle (diagnostics + logs + redacted config) at a user-chosen path
+#[tauri::command]
+async fn create_support_bundle(
+    state: State<'_, Mutex<AppState>>,
+    app: AppHandle,
+    scrub_paths: bool,
+) -> Result<Option<String>, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let default_name = format!(
+        "litepad_support_{}.zip",
+        Local::now().format("%Y%m%d_%H%M%S")
+    );
+    let dest = match app
+        .dialog()
+        .file()
+        .set_file_name(&default_name)
+        .add_filter("ZIP", &["zip"])
+        .blocking_save_file()
+    {
+        Some(file_path) => file_path.into_path().map_err(|e| e.to_string())?,
+        None => return Ok(None),
+    };
+
+    let (data_path, images_path, ephemeral) = {
+        let state = state.lock().unwrap();
+        (
+            state.data_path.clone(),
+            state.images_path.clone(),
+            state.ephemeral,
+        )
+    };
+
+    let mut diagnostics = serde_json::to_value(collect_diagnostics(
+        &app,
+        &data_path,
+        &images_path,
+        ephemeral,
+    ))
+    .map_err(|e| e.to_string())?;
+    redact_value(&mut diagnostics, scrub_paths);
+
+    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    let mut config = serde_json::Value::Object(store.entries().into_iter().collect());
+    redact_value(&mut config, scrub_paths);
+
+    let mut effective_config =
+        serde_json::to_value(collect_effective_config(&app)).map_err(|e| e.to_string())?;
+    redact_value(&mut effective_config, scrub_paths);
+
+    let file = fs::File::create(&dest).map_err(|e| e.to_string())?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    zip.start_file("version.txt", options)
+        .map_err(|e| e.to_string())?;
+    zip.write_all(env!("CARGO_PKG_VERSION").as_bytes())
+        .map_err(|e| e.to_string())?;
+
+    zip.start_file("diagnostics.json", options)
+        .map_err(|e| e.to_string())?;
+    zip.write_all(
+        serde_json::to_string_pretty(&diagnostics)
+            .map_err(|e| e.to_string())?
+            .as_bytes(),
+    )
+    .map_err(|e| e.to_string())?;
+
+    zip.start_file("config.json", options)
+        .map_err(|e| e.to_string())?;
+    zip.write_all(
+        serde_json::to_string_pretty(&config)
+            .map_err(|e| e.to_string())?
+            .as_bytes(),
+    )
+    .map_err(|e| e.to_string())?;
+
+    zip.start_file("effective_config.json", options)
+        .map_err(|e| e.to_string())?;
+    zip.write_all(
+        serde_json::to_string_pretty(&effective_config)
+            .map_err(|e| e.to_string())?
+            .as_bytes(),
+    )
+    .map_err(|e| e.to_string())?;
+
+    for log_path in recent_log_files(&data_path, 5) {
+        if let Some(name) = log_path.file_name() {
+            zip.start_file(format!("logs/{}", name.to_string_lossy()), options)
+                .map_err(|e| e.to_string())?;
+            let mut log_file = fs::File::open(&log_path).map_err(|e| e.to_string())?;
+            std::io::copy(&mut log_file, &mut zip).map_err(|e| e.to_string())?;
+        }
+    }
+
+    zip.finish().map_err(|e| e.to_string())?;
+
+    Ok(Some(dest.to_string_lossy().to_string()))
+}
+
+// 显示器矩形区域
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// 显示器信息
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorInfo {
+    pub name: Option<String>,
+    pub bounds: MonitorRect,
+    pub work_area: MonitorRect,
+    pub scale_factor: f64,
+}
+
+impl From<&tauri::Monitor> for MonitorInfo {
+    fn from(monitor: &tauri::Monitor) -> Self {
+        let work_area = monitor.work_area();
+        Self {
+            name: monitor.name().cloned(),
+            bounds: MonitorRect {
+                x: monitor.position().x,
+                y: monitor.position().y,
+                width: monitor.size().width,
+                height: monitor.size().height,
+            },
+            work_area: MonitorRect {
+                x: work_area.position.x,
+                y: work_area.position.y,
+                width: work_area.size.width,
+                height: work_area.size.height,
+            },
+            scale_factor: monitor.scale_factor(),
+        }
+    }
+}
+
+// 当前显示器查询结果；off_screen 为 true 表示窗口不在任何显示器上
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurrentMonitorResult {
+    pub off_screen: bool,
+    pub monitor: Option<MonitorInfo>,
+}
+
+// Get the monitor the window is on (largest overlap wins when spanning several)
+#[tauri::command]
+fn get_current_monitor(app: AppHandle) -> Result<CurrentMonitorResult, String> {
+    let window = app
+        .get_webview_window("main")
+        .ok_or("Main window not found")?;
+    let pos = window.outer_position().map_err(|e| e.to_string())?;
+    let size = window.outer_size().map_err(|e| e.to_string())?;
+    let monitors = window.available_monitors().map_err(|e| e.to_string())?;
+
+    let best = monitors
+        .iter()
+        .map(|m| {
+            (
+                monitor_overlap_area(pos.x, pos.y, size.width, size.height, m),
+                m,
+            )
+        })
+        .filter(|(area, _)| *area > 0)
+        .max_by_key(|(area, _)| *area)
+        .map(|(_, m)| MonitorInfo::from(m));
+
+    Ok(CurrentMonitorResult {
+        off_screen: best.is_none(),
+        monitor: best,
+    })
+}
+
+// 去掉 HTML 标签与常见 Markdown 标记，得到用于统计/搜索的纯文本
+fn note_plain_text(content: &str) -> String {
+    let mut text = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            // 块级标签换行，避免相邻段落粘连；行内标签直接去掉
+            '<' if chars
+                .peek()
+                .is_some_and(|n| n.is_ascii_alphabetic() || *n == '/' || *n == '!') =>
+            {
+                let mut tag = String::new();
+                for n in chars.by_ref() {
+                    if n == '>' {
+                        break;
+                    }
+                    tag.push(n);
+                }
+                let name = tag
+                    .trim_start_matches('/')
+                    .split(|c: char| c.is_whitespace() || c == '/')
+                    .next()
+                    .unwrap_or_default()
+                    .to_ascii_lowercase();
+                if matches!(
+                    name.as_str(),
+                    "p" | "div"
+                        | "br"
+                        | "li"
+                        | "ul"
+                        | "ol"
+                        | "tr"
+                        | "td"
+                        | "th"
+                        | "pre"
+                        | "blockquote"
+                        | "h1"
+                        | "h2"
+                        | "h3"
+                        | "h4"
+                        | "h5"
+                        | "h6"
+                        | "hr"
+                ) {
+                    text.push('\n');
+                }
+            }
+            '&' => {
+                let mut entity = String::new();
+                while let Some(&n) = chars.peek() {
+                    if n == ';' || entity.len() > 8 || !(n.is_ascii_alphanumeric() || n == '#') {
+                        break;
+                    }
+                    entity.push(n);
+                    chars.next();
+                }
+                let decoded = match entity.as_str() {
+                    "amp" => Some('&'),
+                    "lt" => Some('<'),
+                    "gt" => Some('>'),
+                    "quot" => Some('"'),
+                    "apos" | "#39" => Some('\''),
+                    "nbsp" => Some(' '),
+                    _ => None,
+                };
+                match decoded {
+                    Some(d) if chars.peek() == Some(&';') => {
+                        chars.next();
+                        text.push(d);
+                    }
+                    _ => {
+                        text.push('&');
+                        text.push_str(&entity);
+                    }
+                }
+            }
+            _ => text.push(c),
+        }
+    }
+
+    // Markdown：图片/链接保留文字部分，去掉行首标记与强调符号
+    let mut plain = String::with_capacity(text.len());
+    for line in text.lines() {
+        let line = line.trim_start();
+        let line = line.trim_start_matches(['#', '>']).trim_start();
+        let line = ["- [ ] ", "- [x] ", "- ", "* ", "+ "]
+            .iter()
+            .find_map(|marker| line.strip_prefix(marker))
+            .unwrap_or(line);
+
+        let mut rest = line;
+        while let Some(start) = rest.find('[') {
+            let (before, after) = rest.split_at(start);
+            plain.push_str(before.strip_suffix('!').unwrap_or(before));
+            match after
+                .find("](")
+                .and_then(|mid| after[mid..].find(')').map(|end| (mid, mid + end)))
+            {
+                Some((mid, end)) => {
+                    plain.push_str(&after[1..mid]);
+                    rest = &after[end + 1..];
+                }
+                None => {
+                    plain.push('[');
+                    rest = &after[1..];
+                }
+            }
+        }
+        plain.push_str(rest);
+        plain.push('\n');
+    }
+    plain.retain(|c| !matches!(c, '*' | '`' | '~'));
+    plain
+}
+
+// 中日韩字符逐字计为一个词
+fn is_cjk_char(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF)
+}
+
+fn count_words(text: &str) -> u64 {
+    let mut words = 0;
+    let mut in_word = false;
+    for c in text.chars() {
+        if is_cjk_char(c) {
+            words += 1;
+            in_word = false;
+        } else if c.is_alphanumeric() || (in_word && (c == '\'' || c == '-' || c == '_')) {
+            if !in_word {
+                words += 1;
+                in_word = true;
+            }
+        } else {
+            in_word = false;
+        }
+    }
+    words
+}
+
+// 单条笔记的统计
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteTextStats {
+    pub words: u64,
+    // 不含空白的字符数
+    pub characters: u64,
+    pub characters_with_spaces: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextStats {
+    pub total_words: u64,
+    pub total_characters: u64,
+    pub total_characters_with_spaces: u64,
+    // key: note ID
+    pub notes: BTreeMap<String, NoteTextStats>,
+}
+
+fn note_text_stats(content: &str) -> NoteTextStats {
+    let text = note_plain_text(content);
+    let text = text.trim();
+    NoteTextStats {
+        words: count_words(text),
+        characters: text.chars().filter(|c| !c.is_whitespace()).count() as u64,
+        characters_with_spaces: text.chars().filter(|c| *c != '\n').count() as u64,
+    }
+}
+
+// Word and character counts for every note (HTML and Markdown formatting stripped)
+#[tauri::command]
+async fn compute_text_stats(data: String) -> Result<TextStats, String> {
+    let notes = notes_from_data_json(&data)?;
+    let mut stats = TextStats {
+        total_words: 0,
+        total_characters: 0,
+        total_characters_with_spaces: 0,
+        notes: BTreeMap::new(),
+    };
+
+    for note in &notes {
+        let Some(id) = note_id_of(note) else {
+            continue;
+        };
+        let note_stats = note_text_stats(
+            note.get("content")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default(),
+        );
+        stats.total_words += note_stats.words;
+        stats.total_characters += note_stats.characters;
+        stats.total_characters_with_spaces += note_stats.characters_with_spaces;
+        stats.notes.insert(id, note_stats);
+    }
+
+    Ok(stats)
+}
+
+// 内容相同的一组笔记
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateNoteGroup {
+    pub hash: String,
+    pub ids: Vec<String>,
+}
+
+// 宽松比较：去掉格式，忽略大小写，空白折叠为单个空格
+fn normalize_note_for_dedup(content: &str) -> String {
+    note_plain_text(content)
+        .split_whitespace()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Group notes whose content is identical (or identical after normalization); read-only
+#[tauri::command]
+async fn find_duplicate_notes(
+    data: String,
+    normalized: Option<bool>,
+) -> Result<Vec<DuplicateNoteGroup>, String> {
+    let normalized = normalized.unwrap_or(true);
+    let notes = notes_from_data_json(&data)?;
+    let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for note in &notes {
+        let Some(id) = note_id_of(note) else {
+            continue;
+        };
+        let content = note
+            .get("content")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        let key = if normalized {
+            normalize_note_for_dedup(content)
+        } else {
+            content.to_string()
+        };
+        // 空笔记不算重复
+        if key.trim().is_empty() {
+            continue;
+        }
+        groups
+            .entry(hash_bytes(key.as_bytes()))
+            .or_default()
+            .push(id);
+    }
+
+    Ok(groups
+        .into_iter()
+        .filter(|(_, ids)| ids.len() > 1)
+        .map(|(hash, ids)| DuplicateNoteGroup { hash, ids })
+        .collect())
+}
+
+// 搜索选项
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchOptions {
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    // 模糊匹配：按词比较，允许少量拼写差异
+    pub fuzzy: bool,
+}
+
+// 匹配位置（纯文本中的 UTF-16 偏移，与前端字符串下标一致）
+#[derive(Debug, Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchMatch {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteSearchResult {
+    pub id: String,
+    pub title_match: bool,
+    pub match_count: usize,
+    pub matches: Vec<SearchMatch>,
+    pub snippet: String,
+    // snippet 在纯文本中的起始位置（UTF-16）
+    pub snippet_start: usize,
+}
+
+// 单条笔记最多返回的匹配位置数
+const MAX_MATCHES_PER_NOTE: usize = 1000;
+const SNIPPET_BEFORE_CHARS: usize = 40;
+const SNIPPET_AFTER_CHARS: usize = 80;
+
+// 逐字符转换大小写，保证与原文字符一一对应
+fn normalize_for_search(text: &str, case_sensitive: bool) -> String {
+    if case_sensitive {
+        text.to_string()
+    } else {
+        text.chars()
+            .map(|c| c.to_lowercase().next().unwrap_or(c))
+            .collect()
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// 查找 needle 在 haystack 中的所有位置，返回 (起始字节, 结束字节)
+fn find_exact_matches(haystack: &str, needle: &str, whole_word: bool) -> Vec<(usize, usize)> {
+    haystack
+        .match_indices(needle)
+        .map(|(start, m)| (start, start + m.len()))
+        .filter(|&(start, end)| {
+            !whole_word
+                || (!haystack[..start]
+                    .chars()
+                    .next_back()
+                    .is_some_and(is_word_char)
+                    && !haystack[end..].chars().next().is_some_and(is_word_char))
+        })
+        .take(MAX_MATCHES_PER_NOTE)
+        .collect()
+}
+
+// 限定距离的编辑距离（相邻字符交换计为一次），超过 max 时提前返回 None
+fn bounded_edit_distance(a: &[char], b: &[char], max: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    let mut before_previous = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for i in 0..a.len() {
+        current[0] = i + 1;
+        let mut row_min = current[0];
+        for j in 0..b.len() {
+            let cost = usize::from(a[i] != b[j]);
+            current[j + 1] = (previous[j] + cost)
+                .min(previous[j + 1] + 1)
+                .min(current[j] + 1);
+            if i > 0 && j > 0 && a[i] == b[j - 1] && a[i - 1] == b[j] {
+                current[j + 1] = current[j + 1].min(before_previous[j - 1] + 1);
+            }
+            row_min = row_min.min(current[j + 1]);
+        }
+        if row_min > max {
+            return None;
+        }
+        std::mem::swap(&mut before_previous, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+    (previous[b.len()] <= max).then_some(previous[b.len()])
+}
+
+// 模糊匹配：每个查询词都需在文本中找到相近的词（长度 4 以上允许 1 处差异，8 以上允许 2 处）
+fn find_fuzzy_matches(haystack: &str, query: &str) -> Vec<(usize, usize)> {
+    let tokens: Vec<Vec<char>> = query
+        .split(|c: char| !is_word_char(c))
+        .filter(|t| !t.is_empty())
+        .map(|t| t.chars().collect())
+        .collect();
+    if tokens.is_empty() {
+        return Vec::new();
+    }
+
+    let mut matches = Vec::new();
+    let mut matched_tokens = vec![false; tokens.len()];
+    let mut word: Vec<char> = Vec::new();
+    let mut word_start = 0;
+    for (i, c) in haystack
+        .char_indices()
+        .chain(std::iter::once((haystack.len(), ' ')))
+    {
+        if is_word_char(c) {
+            if word.is_empty() {
+                word_start = i;
+            }
+            word.push(c);
+            continue;
+        }
+        if word.is_empty() {
+            continue;
+        }
+        for (token, matched) in tokens.iter().zip(matched_tokens.iter_mut()) {
+            let max = match token.len() {
+                0..=3 => 0,
+                4..=7 => 1,
+                _ => 2,
+            };
+            if bounded_edit_distance(&word, token, max).is_some() {
+                *matched = true;
+                if matches.len() < MAX_MATCHES_PER_NOTE {
+                    matches.push((word_start, i));
+                }
+                break;
+            }
+        }
+        word.clear();
+    }
+
+    if matched_tokens.iter().all(|m| *m) {
+        matches
+    } else {
+        Vec::new()
+    }
+}
+
+// 将 normalized 中的字节偏移换算为原文的 UTF-16 偏移（两者字符一一对应）
+fn to_utf16_offsets(original: &str, normalized: &str, byte_offsets: &[usize]) -> Vec<usize> {
+    let mut result = Vec::with_capacity(byte_offsets.len());
+    let mut pending = byte_offsets.iter().peekable();
+    let mut utf16 = 0;
+    for ((byte, _), c) in normalized
+        .char_indices()
+        .chain(std::iter::once((normalized.len(), ' ')))
+        .zip(original.chars().map(Some).chain(std::iter::once(None)))
+    {
+        while pending.peek().is_some_and(|&&offset| offset <= byte) {
+            pending.next();
+            result.push(utf16);
+        }
+        if let Some(c) = c {
+            utf16 += c.len_utf16();
+        }
+    }
+    result
+}
+
+fn search_snippet(text: &str, first_match_utf16: usize) -> (String, usize) {
+    // 先找到匹配所在的字符下标
+    let mut utf16 = 0;
+    let mut match_char = 0;
+    for (i, c) in text.chars().enumerate() {
+        if utf16 >= first_match_utf16 {
+            match_char = i;
+            break;
+        }
+        utf16 += c.len_utf16();
+        match_char = i + 1;
+    }
+    let start_char = match_char.saturating_sub(SNIPPET_BEFORE_CHARS);
+    let snippet_start: usize = text.chars().take(start_char).map(char::len_utf16).sum();
+    let snippet: String = text
+        .chars()
+        .skip(start_char)
+        .take(match_char - start_char + SNIPPET_AFTER_CHARS)
+        .map(|c| if c == '\n' { ' ' } else { c })
+        .collect();
+    (snippet, snippet_start)
+}
+
+fn search_in_text(text: &str, query: &str, options: &SearchOptions) -> Vec<SearchMatch> {
+    let normalized = normalize_for_search(text, options.case_sensitive);
+    let byte_matches = if options.fuzzy {
+        find_fuzzy_matches(&normalized, query)
+    } else {
+        find_exact_matches(&normalized, query, options.whole_word)
+    };
+    let offsets: Vec<usize> = byte_matches.iter().flat_map(|&(s, e)| [s, e]).collect();
+    let mut sorted = offsets.clone();
+    sorted.sort_unstable();
+    let utf16 = to_utf16_offsets(text, &normalized, &sorted);
+    let lookup = |byte: usize| utf16[sorted.partition_point(|&o| o < byte)];
+    byte_matches
+        .iter()
+        .map(|&(s, e)| SearchMatch {
+            start: lookup(s),
+            end: lookup(e),
+        })
+        .collect()
+}
+
+// Full-text search across notes; returns match positions in each note's plain text
+#[tauri::command]
+async fn search_notes(
+    data: String,
+    query: String,
+    options: Option<SearchOptions>,
+) -> Result<Vec<NoteSearchResult>, String> {
+    let options = options.unwrap_or_default();
+    let query = normalize_for_search(query.trim(), options.case_sensitive);
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let notes = notes_from_data_json(&data)?;
+    let mut results = Vec::new();
+    for note in &notes {
+        let Some(id) = note_id_of(note) else {
+            continue;
+        };
+        let title = note
+            .get("title")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        let text = note_plain_text(
+            note.get("content")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default(),
+        );
+
+        let title_match = !search_in_text(title, &query, &options).is_empty();
+        let matches = search_in_text(&text, &query, &options);
+        if matches.is_empty() && !title_match {
+            continue;
+        }
+
+        let (snippet, snippet_start) = match matches.first() {
+            Some(first) => search_snippet(&text, first.start),
+            None => search_snippet(&text, 0),
+        };
+        results.push(NoteSearchResult {
+            id,
+            title_match,
+            match_count: matches.len(),
+            matches,
+            snippet,
+            snippet_start,
+        });
+    }
+
+    // 标题命中优先，其次按匹配次数
+    results.sort_by(|a, b| {
+        b.title_match
+            .cmp(&a.title_match)
+            .then_with(|| b.match_count.cmp(&a.match_count))
+    });
+    Ok(results)
+}
+
+// 备份内容搜索：解析后的笔记按 (文件名, 修改时间) 缓存，备份未变化时不再解压
+struct BackupSearchNote {
+    id: String,
+    title: String,
+    text: String,
+}
+
+struct BackupSearchCache(
+    Mutex<HashMap<String, (std::time::SystemTime, std::sync::Arc<Vec<BackupSearchNote>>)>>,
+);
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSearchResult {
+    pub filename: String,
+    pub matching_note_ids: Vec<String>,
+    pub snippets: Vec<String>,
+}
+
+fn load_backup_search_notes(path: &std::path::Path) -> Result<Vec<BackupSearchNote>, String> {
+    let file = fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut archive = ZipArchive::new(file).map_err(|e| e.to_string())?;
+    let notes = notes_from_data_json(&read_backup_data_json(&mut archive)?)?;
+    Ok(notes
+        .iter()
+        .filter_map(|note| {
+            let id = note_id_of(note)?;
+            let field = |key: &str| {
+                note.get(key)
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            Some(BackupSearchNote {
+                id,
+                title: field("title"),
+                text: note_plain_text(&field("content")),
+            })
+        })
+        .collect())
+}
+
+// Search note text inside every (unencrypted) backup without restoring them
+#[tauri::command]
+async fn search_in_backups(
+    app: AppHandle,
+    cache: State<'_, BackupSearchCache>,
+    query: String,
+) -> Result<Vec<BackupSearchResult>, String> {
+    let options = SearchOptions::default();
+    let query = normalize_for_search(query.trim(), options.case_sensitive);
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let backups = get_backup_list(app.clone(), None).await?;
+    let mut results = Vec::new();
+    let mut seen = HashSet::new();
+    for backup in backups.iter().filter(|b| !b.encrypted) {
+        let path = backup_file_path(&app, &backup.filename)?;
+        let Ok(modified) = fs::metadata(&path).and_then(|m| m.modified()) else {
+            continue;
+        };
+        seen.insert(backup.filename.clone());
+
+        let cached = cache
+            .0
+            .lock()
+            .unwrap()
+            .get(&backup.filename)
+            .filter(|(mtime, _)| *mtime == modified)
+            .map(|(_, notes)| notes.clone());
+        let notes = match cached {
+            Some(notes) => notes,
+            None => {
+                // 损坏的备份跳过，不影响其他备份的搜索
+                let Ok(notes) = load_backup_search_notes(&path) else {
+                    continue;
+                };
+                let notes = std::sync::Arc::new(notes);
+                cache
+                    .0
+                    .lock()
+                    .unwrap()
+                    .insert(backup.filename.clone(), (modified, notes.clone()));
+                notes
+            }
+        };
+
+        let mut result = BackupSearchResult {
+            filename: backup.filename.clone(),
+            matching_note_ids: Vec::new(),
+            snippets: Vec::new(),
+        };
+        for note in notes.iter() {
+            let title_match = !search_in_text(&note.title, &query, &options).is_empty();
+            let first = search_in_text(&note.text, &query, &options)
+                .first()
+                .map(|m| m.start);
+            if first.is_none() && !title_match {
+                continue;
+            }
+            result.matching_note_ids.push(note.id.clone());
+            result
+                .snippets
+                .push(search_snippet(&note.text, first.unwrap_or(0)).0);
+        }
+        if !result.matching_note_ids.is_empty() {
+            results.push(result);
+        }
+    }
+
+    // 已删除的备份不再占用缓存
+    cache
+        .0
+        .lock()
+        .unwrap()
+        .retain(|filename, _| seen.contains(filename));
+    Ok(results)
+}
+
+// 持久化搜索索引（data_path/search_index.json）。文件中只保存每条笔记的词频，
+// 倒排表在加载时重建；版本不一致时丢弃，由前端调用 index_build 重新建立
+const SEARCH_INDEX_FILE: &str = "search_index.json";
+const SEARCH_INDEX_VERSION: u32 = 1;
+const SEARCH_INDEX_SAVE_DELAY_MS: u64 = 2000;
+
+static SEARCH_INDEX_SAVE_SCHEDULED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct IndexedNote {
+    // 内容 hash，未变化的笔记在 index_build 时跳过
+    hash: String,
+    length: u32,
+    terms: BTreeMap<String, u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct SearchIndex {
+    version: u32,
+    notes: BTreeMap<String, IndexedNote>,
+    // term -> (note_id -> 词频)
+    #[serde(skip)]
+    postings: BTreeMap<String, HashMap<String, u32>>,
+}
+
+struct SearchIndexState(Mutex<SearchIndex>);
 
-        if name.starts_with("images/") && !name.ends_with('/') {
-            if let Some(relative) = name.strip_prefix("images/") {
-                let dest_path = images_path.join(relative);
+impl SearchIndex {
+    fn new() -> Self {
+        Self {
+            version: SEARCH_INDEX_VERSION,
+            ..Default::default()
+        }
+    }
 
-                if let Some(parent) = dest_path.parent() {
-                    let _ = fs::create_dir_all(parent);
-                }
+    fn load(data_path: &std::path::Path) -> Self {
+        let mut index = fs::read_to_string(data_path.join(SEARCH_INDEX_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str::<SearchIndex>(&content).ok())
+            .filter(|index| index.version == SEARCH_INDEX_VERSION)
+            .unwrap_or_else(SearchIndex::new);
+        for (id, note) in &index.notes {
+            for (term, count) in &note.terms {
+                index
+                    .postings
+                    .entry(term.clone())
+                    .or_default()
+                    .insert(id.clone(), *count);
+            }
+        }
+        index
+    }
 
-                let mut dest_file = fs::File::create(&dest_path).map_err(|e| e.to_string())?;
-                std::io::copy(&mut file, &mut dest_file).map_err(|e| e.to_string())?;
+    fn remove(&mut self, id: &str) -> bool {
+        let Some(note) = self.notes.remove(id) else {
+            return false;
+        };
+        for term in note.terms.keys() {
+            if let Some(posting) = self.postings.get_mut(term) {
+                posting.remove(id);
+                if posting.is_empty() {
+                    self.postings.remove(term);
+                }
             }
         }
+        true
     }
 
-    Ok(data_json)
-}
+    // 返回是否有变化
+    fn update(&mut self, id: &str, text: &str) -> bool {
+        let hash = hash_bytes(text.as_bytes());
+        if self.notes.get(id).is_some_and(|note| note.hash == hash) {
+            return false;
+        }
+        self.remove(id);
 
-// Delete backup
-#[tauri::command]
-async fn delete_backup(app: AppHandle, filename: String) -> Result<(), String> {
-    let store = app.store("config.json").map_err(|e| e.to_string())?;
-    let settings: BackupSettings = store
-        .get("backupSettings")
-        .and_then(|v| serde_json::from_value(v).ok())
-        .unwrap_or_default();
+        let mut terms: BTreeMap<String, u32> = BTreeMap::new();
+        let mut length = 0;
+        for term in index_terms(&note_plain_text(text)) {
+            *terms.entry(term).or_default() += 1;
+            length += 1;
+        }
+        for (term, count) in &terms {
+            self.postings
+                .entry(term.clone())
+                .or_default()
+                .insert(id.to_string(), *count);
+        }
+        self.notes.insert(
+            id.to_string(),
+            IndexedNote {
+                hash,
+                length,
+                terms,
+            },
+        );
+        true
+    }
 
-    let backup_dir = resolve_backup_directory(settings.backup_directory)
-        .ok_or("Backup directory not configured")?;
-    let file_path = std::path::Path::new(&backup_dir).join(&filename);
+    // BM25 排序；最后一个查询词按前缀匹配，便于边输入边搜索
+    fn query(&self, query: &str, limit: usize) -> Vec<IndexQueryResult> {
+        let terms = index_terms(query);
+        if terms.is_empty() || self.notes.is_empty() {
+            return Vec::new();
+        }
+        let note_count = self.notes.len() as f64;
+        let average_length = self.notes.values().map(|n| n.length as f64).sum::<f64>() / note_count;
+        let (k1, b) = (1.2, 0.75);
+
+        let mut scores: HashMap<&str, (f64, usize)> = HashMap::new();
+        for (i, term) in terms.iter().enumerate() {
+            let matching: Vec<(&String, &HashMap<String, u32>)> = if i == terms.len() - 1 {
+                self.postings
+                    .range(term.clone()..)
+                    .take_while(|(t, _)| t.starts_with(term.as_str()))
+                    .collect()
+            } else {
+                self.postings.get_key_value(term).into_iter().collect()
+            };
+
+            let mut matched_notes: HashSet<&str> = HashSet::new();
+            for (_, posting) in matching {
+                let idf = ((note_count - posting.len() as f64 + 0.5)
+                    / (posting.len() as f64 + 0.5)
+                    + 1.0)
+                    .ln();
+                for (id, count) in posting {
+                    let length = self.notes.get(id).map(|n| n.length).unwrap_or(0) as f64;
+                    let tf = *count as f64;
+                    let score = idf * tf * (k1 + 1.0)
+                        / (tf + k1 * (1.0 - b + b * length / average_length.max(1.0)));
+                    let entry = scores.entry(id.as_str()).or_default();
+                    entry.0 += score;
+                    if matched_notes.insert(id.as_str()) {
+                        entry.1 += 1;
+                    }
+                }
+            }
+        }
 
-    fs::remove_file(file_path).map_err(|e| e.to_string())?;
-    Ok(())
+        let mut results: Vec<IndexQueryResult> = scores
+            .into_iter()
+            .map(|(id, (score, matched_terms))| IndexQueryResult {
+                id: id.to_string(),
+                score,
+                matched_terms,
+            })
+            .collect();
+        // 命中查询词多的优先，其次按分数
+        results.sort_by(|a, b| {
+            b.matched_terms
+                .cmp(&a.matched_terms)
+                .then_with(|| b.score.total_cmp(&a.score))
+        });
+        results.truncate(limit);
+        results
+    }
 }
 
-// Get default backup directory
-#[tauri::command]
-fn get_default_backup_dir() -> Option<String> {
-    get_default_backup_directory()
+// 索引词：小写的字母数字词，中日韩文字逐字成词
+fn index_terms(text: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    let mut word = String::new();
+    for c in text.chars().chain(std::iter::once(' ')) {
+        if is_cjk_char(c) {
+            if !word.is_empty() {
+                terms.push(std::mem::take(&mut word));
+            }
+            terms.push(c.to_string());
+        } else if is_word_char(c) {
+            word.extend(c.to_lowercase());
+        } else if !word.is_empty() {
+            terms.push(std::mem::take(&mut word));
+        }
+    }
+    terms
 }
 
-// Validate backup path
-#[tauri::command]
-fn validate_backup_path(path: String) -> PathValidationResult {
-    let path = std::path::Path::new(&path);
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexQueryResult {
+    pub id: String,
+    pub score: f64,
+    pub matched_terms: usize,
+}
 
-    // Check if path exists
-    let exists = path.exists();
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexBuildResult {
+    pub indexed: usize,
+    pub updated: usize,
+    pub removed: usize,
+}
 
-    // Check if writable
-    let is_writable = if exists {
-        // Try to create a test file
-        let test_file = path.join(".litepad_write_test");
-        match fs::File::create(&test_file) {
-            Ok(_) => {
-                let _ = fs::remove_file(&test_file);
-                true
-            }
-            Err(_) => false,
+// 索引变化后延迟写盘，合并连续的更新
+fn schedule_search_index_save(app: &AppHandle) {
+    if SEARCH_INDEX_SAVE_SCHEDULED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let app_handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_millis(SEARCH_INDEX_SAVE_DELAY_MS)).await;
+        SEARCH_INDEX_SAVE_SCHEDULED.store(false, Ordering::SeqCst);
+
+        let (data_path, ephemeral) = {
+            let state = app_handle.state::<Mutex<AppState>>();
+            let state = state.lock().unwrap();
+            (state.data_path.clone(), state.ephemeral)
+        };
+        if ephemeral {
+            return;
         }
-    } else {
-        // Path doesn't exist, check if parent directory exists and is writable
-        if let Some(parent) = path.parent() {
-            if parent.exists() {
-                let test_file = parent.join(".litepad_write_test");
-                match fs::File::create(&test_file) {
-                    Ok(_) => {
-                        let _ = fs::remove_file(&test_file);
-                        true
-                    }
-                    Err(_) => false,
+        let content = {
+            let index = app_handle.state::<SearchIndexState>();
+            let index = index.0.lock().unwrap();
+            serde_json::to_vec(&*index)
+        };
+        match content {
+            Ok(content) => {
+                if let Err(e) = write_atomic(&data_path.join(SEARCH_INDEX_FILE), &content) {
+                    eprintln!("Warning: Failed to save search index: {}", e);
                 }
-            } else {
-                false
             }
-        } else {
-            false
+            Err(e) => eprintln!("Warning: Failed to serialize search index: {}", e),
         }
-    };
+    });
+}
 
-    let (is_valid, error_code) = match (exists, is_writable) {
-        (true, true) => (true, None),
-        (true, false) => (false, Some("NO_WRITE_PERMISSION".to_string())),
-        (false, true) => (true, None), // Can be created
-        (false, false) => (false, Some("PATH_NOT_ACCESSIBLE".to_string())),
-    };
+fn note_index_text(note: &serde_json::Value) -> String {
+    let title = note
+        .get("title")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    let content = note
+        .get("content")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    format!("{}\n{}", title, content)
+}
 
-    PathValidationResult {
-        is_valid,
-        exists,
-        is_writable,
-        error_code,
+// Sync the search index with a full notes snapshot; unchanged notes are skipped
+#[tauri::command]
+async fn index_build(
+    app: AppHandle,
+    index: State<'_, SearchIndexState>,
+    data: String,
+    force: Option<bool>,
+) -> Result<IndexBuildResult, String> {
+    let notes = notes_from_data_json(&data)?;
+    let mut index = index.0.lock().unwrap();
+    if force.unwrap_or(false) {
+        *index = SearchIndex::new();
+    }
+
+    let mut updated = 0;
+    let mut seen = HashSet::new();
+    for note in &notes {
+        let Some(id) = note_id_of(note) else {
+            continue;
+        };
+        if index.update(&id, &note_index_text(note)) {
+            updated += 1;
+        }
+        seen.insert(id);
+    }
+    let stale: Vec<String> = index
+        .notes
+        .keys()
+        .filter(|id| !seen.contains(*id))
+        .cloned()
+        .collect();
+    for id in &stale {
+        index.remove(id);
+    }
+
+    if updated > 0 || !stale.is_empty() {
+        schedule_search_index_save(&app);
     }
+    Ok(IndexBuildResult {
+        indexed: index.notes.len(),
+        updated,
+        removed: stale.len(),
+    })
 }
 
-// Check for updates
+// Add or replace a single note in the search index
 #[tauri::command]
-async fn check_for_updates() -> Result<UpdateInfo, String> {
-    let current_version = env!("CARGO_PKG_VERSION");
+async fn index_update(
+    app: AppHandle,
+    index: State<'_, SearchIndexState>,
+    note_id: String,
+    text: String,
+) -> Result<(), String> {
+    if index.0.lock().unwrap().update(&note_id, &text) {
+        schedule_search_index_save(&app);
+    }
+    Ok(())
+}
 
-    // GitHub API URL (使用官方 REST API v3)
-    let url = "https://api.github.com/repos/L0dyv/LitePad/releases/latest";
+// Remove a note from the search index
+#[tauri::command]
+async fn index_remove(
+    app: AppHandle,
+    index: State<'_, SearchIndexState>,
+    note_id: String,
+) -> Result<(), String> {
+    if index.0.lock().unwrap().remove(&note_id) {
+        schedule_search_index_save(&app);
+    }
+    Ok(())
+}
 
-    // 创建 HTTP 客户端
-    let client = reqwest::blocking::Client::builder()
-        .user_agent("LitePad-Update-Checker")
-        .timeout(std::time::Duration::from_secs(10))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+// Query the search index; returns note IDs ranked by relevance
+#[tauri::command]
+async fn index_query(
+    index: State<'_, SearchIndexState>,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<IndexQueryResult>, String> {
+    Ok(index.0.lock().unwrap().query(&query, limit.unwrap_or(50)))
+}
 
-    // 发送请求
-    let response = client
-        .get(url)
-        .send()
-        .map_err(|e| format!("Network error: {}", e))?;
+// litepad:// 协议处理：/images/{hash}{ext} 与 /thumbs/{name}
+fn litepad_protocol_response(app: &AppHandle, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let uri = request.uri();
+    let path = uri.path();
+    let range = request
+        .headers()
+        .get("Range")
+        .and_then(|value| value.to_str().ok());
+
+    // 解析路径：/images/{hash}{ext}
+    if path.starts_with("/images/") && is_safe_image_filename(&path[8..]) {
+        let filename = &path[8..]; // 去掉 "/images/" 前缀
+
+        // images 目录以 AppState 为准；临时模式下优先从内存中读取
+        let (images_path, memory_image) = {
+            let state = app.state::<Mutex<AppState>>();
+            let state = state.lock().unwrap();
+            (
+                state.images_path.clone(),
+                state.memory_images.get(filename).cloned(),
+            )
+        };
+        if let Some(content) = memory_image {
+            let ext = filename.rsplit('.').next().unwrap_or("png");
+            let mime_type = image_content_type(ext, &content);
+            return image_response(content, mime_type, range, false);
+        }
 
-    // 检查响应状态
-    if !response.status().is_success() {
-        return Err(format!("GitHub API error: {}", response.status()));
+        let file_path = image_file_path(&images_path, filename);
+
+        // 规范化后必须仍位于 images 目录内
+        let inside_images = match (file_path.canonicalize(), images_path.canonicalize()) {
+            (Ok(file), Ok(root)) => file.starts_with(root),
+            _ => false,
+        };
+
+        let packs = app.state::<PackIndexCache>();
+        if inside_images || packs.load(&images_path).entries.contains_key(filename) {
+            match read_stored_image(&images_path, &packs, filename) {
+                Some(content) => {
+                    // 根据扩展名设置 MIME 类型
+                    let ext = file_path
+                        .extension()
+                        .and_then(|e| e.to_str())
+                        .unwrap_or("png");
+                    let mime_type = image_content_type(ext, &content);
+
+                    return image_response(content, mime_type, range, true);
+                }
+                None => {
+                    return Response::builder()
+                        .status(500)
+                        .body(Vec::new())
+                        .expect("Failed to build error response");
+                }
+            }
+        }
     }
 
-    // 解析 JSON
-    let release: GitHubRelease = response
-        .json()
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    // 缩略图：/thumbs/{hash}_{max_dim}{ext}
+    if let Some(filename) = path
+        .strip_prefix("/thumbs/")
+        .filter(|f| is_safe_image_filename(f))
+    {
+        let thumbs_path = {
+            let state = app.state::<Mutex<AppState>>();
+            let state = state.lock().unwrap();
+            state.data_path.join(THUMBS_DIR)
+        };
+        let file_path = thumbs_path.join(filename);
+
+        // 同样要求规范化后仍位于 thumbs 目录内
+        let inside_thumbs = match (file_path.canonicalize(), thumbs_path.canonicalize()) {
+            (Ok(file), Ok(root)) => file.starts_with(root),
+            _ => false,
+        };
+        if inside_thumbs {
+            if let Ok(content) = fs::read(&file_path) {
+                let ext = filename.rsplit('.').next().unwrap_or("png");
+                let mime_type = image_content_type(ext, &content);
+                return image_response(content, mime_type, range, true);
+            }
+        }
+    }
 
-    // 比较版本
-    let has_update = compare_versions(current_version, &release.tag_name);
+    // 404 Not Found
+    Response::builder()
+        .status(404)
+        .body(Vec::new())
+        .expect("Failed to build 404 response")
+}
 
-    Ok(UpdateInfo {
-        has_update,
-        current_version: current_version.to_string(),
-        latest_version: Some(release.tag_name),
-        release_url: Some(release.html_url),
-        release_notes: release.body,
-        published_at: Some(release.published_at),
-    })
+// 统一补充 Content-Length；HEAD 请求只返回头部
+fn finalize_protocol_response(response: Response<Vec<u8>>, head: bool) -> Response<Vec<u8>> {
+    let (mut parts, body) = response.into_parts();
+    parts
+        .headers
+        .insert(CONTENT_LENGTH, HeaderValue::from(body.len()));
+    let body = if head { Vec::new() } else { body };
+    Response::from_parts(parts, body)
 }
 
 fn main() {
-    // Setup portable data path
-    let data_path = get_portable_data_path();
+    // Setup data path: portable when the executable directory is writable, otherwise the user data dir
+    let data_path = resolve_data_path();
     let images_path = data_path.join("images");
+    let args: Vec<String> = std::env::args().collect();
+    let ephemeral = args.iter().any(|arg| arg == "--ephemeral");
+    let view_path = std::env::current_dir()
+        .ok()
+        .and_then(|cwd| view_path_from_args(&args, &cwd));
 
     // Ensure directories exist
-    fs::create_dir_all(&data_path).expect("Failed to create data directory");
-    fs::create_dir_all(&images_path).expect("Failed to create images directory");
+    if !ephemeral {
+        fs::create_dir_all(&data_path).expect("Failed to create data directory");
+        fs::create_dir_all(&images_path).expect("Failed to create images directory");
+    }
+
+    // 搜索索引在启动时从磁盘加载，版本不一致时为空
+    let search_index = if ephemeral {
+        SearchIndex::new()
+    } else {
+        SearchIndex::load(&data_path)
+    };
 
     let app_state = AppState {
         data_path: data_path.clone(),
         images_path,
+        ephemeral,
+        memory_images: HashMap::new(),
     };
 
     tauri::Builder::default()
@@ -933,72 +9433,31 @@ fn main() {
             MacosLauncher::LaunchAgent,
             Some(vec!["--hidden"]),
         ))
-        .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
+        .plugin(tauri_plugin_single_instance::init(|app, args, cwd| {
             // When second instance is launched, show and focus existing window
             if let Some(window) = app.get_webview_window("main") {
                 let _ = window.show();
                 let _ = window.set_focus();
             }
+            // litepad --view backup.zip：交给已运行的实例打开
+            if let Some(path) = view_path_from_args(&args, std::path::Path::new(&cwd)) {
+                enter_view_mode(app, &path);
+            }
         }))
         .plugin(tauri_plugin_global_shortcut::Builder::new().build())
         .plugin(tauri_plugin_dialog::init())
         // 注册 litepad:// 协议处理器
-        .register_uri_scheme_protocol("litepad", move |_ctx, request| {
-            let uri = request.uri();
-            let path = uri.path();
-
-            // 解析路径：/images/{hash}{ext}
-            if path.starts_with("/images/") {
-                let filename = &path[8..]; // 去掉 "/images/" 前缀
-
-                // 从可执行文件路径获取 images 目录
-                let exe_path = std::env::current_exe().expect("Failed to get executable path");
-                let exe_dir = exe_path.parent().expect("Failed to get executable directory");
-                let images_path = exe_dir.join("data").join("images");
-                let file_path = images_path.join(filename);
-
-                if file_path.exists() {
-                    match std::fs::read(&file_path) {
-                        Ok(content) => {
-                            // 根据扩展名设置 MIME 类型
-                            let ext = file_path
-                                .extension()
-                                .and_then(|e| e.to_str())
-                                .unwrap_or("png");
-                            let mime_type = match ext {
-                                "png" => "image/png",
-                                "jpg" | "jpeg" => "image/jpeg",
-                                "gif" => "image/gif",
-                                "webp" => "image/webp",
-                                "svg" => "image/svg+xml",
-                                "bmp" => "image/bmp",
-                                _ => "application/octet-stream",
-                            };
-
-                            return Response::builder()
-                                .status(200)
-                                .header("Content-Type", mime_type)
-                                .header("Cache-Control", "max-age=31536000, immutable")
-                                .body(content)
-                                .expect("Failed to build response");
-                        }
-                        Err(_) => {
-                            return Response::builder()
-                                .status(500)
-                                .body(Vec::new())
-                                .expect("Failed to build error response");
-                        }
-                    }
-                }
-            }
-
-            // 404 Not Found
-            Response::builder()
-                .status(404)
-                .body(Vec::new())
-                .expect("Failed to build 404 response")
+        .register_uri_scheme_protocol("litepad", move |ctx, request| {
+            let head = request.method() == Method::HEAD;
+            finalize_protocol_response(litepad_protocol_response(ctx.app_handle(), &request), head)
         })
         .manage(Mutex::new(app_state))
+        .manage(FontCache(Mutex::new(None)))
+        .manage(SearchIndexState(Mutex::new(search_index)))
+        .manage(ViewModeState(Mutex::new(None)))
+        .manage(BackupSearchCache(Mutex::new(HashMap::new())))
+        .manage(ImageWatcherState(Mutex::new(None)))
+        .manage(PackIndexCache(Mutex::new(None)))
         .invoke_handler(tauri::generate_handler![
             get_version,
             get_settings,
@@ -1025,38 +9484,155 @@ fn main() {
             get_default_backup_dir,
             validate_backup_path,
             check_for_updates,
+            migrate_legacy_config,
+            run_diagnostics,
+            create_support_bundle,
+            get_current_monitor,
+            set_always_on_top_temporary,
+            get_clipboard_kind,
+            pack_small_images,
+            unpack_images,
+            is_ephemeral_mode,
+            reconcile_image_references,
+            save_data,
+            flush_pending_save,
+            set_save_interval,
+            download_and_store_image,
+            download_and_store_images,
+            get_effective_config,
+            perform_auto_backup,
+            get_last_backup_status,
+            get_update_cache_stats,
+            clear_update_cache,
+            self_test_backup,
+            set_toggle_shortcut,
+            peek_backup,
+            restore_note_from_backup,
+            verify_backup,
+            perform_incremental_backup,
+            export_markdown,
+            import_markdown,
+            get_image_storage_stats,
+            compute_image_hash,
+            cancel_backup,
+            open_backup_directory,
+            download_update,
+            get_system_fonts_detailed,
+            refresh_system_fonts,
+            has_font,
+            find_closest_font,
+            set_close_to_tray,
+            reset_window_position,
+            set_always_on_top_shortcut,
+            set_window_opacity,
+            write_recovery_snapshot,
+            read_recovery_snapshot,
+            clear_recovery_snapshot,
+            verify_image_store,
+            migrate_data_directory,
+            get_data_directory,
+            get_shortcut_registration_failures,
+            set_tray_tooltip,
+            set_tray_badge,
+            export_backup,
+            import_backup,
+            compute_text_stats,
+            search_notes,
+            index_build,
+            index_update,
+            index_remove,
+            index_query,
+            trash_image,
+            list_trashed_images,
+            restore_trashed_image,
+            empty_trash,
+            recompress_image,
+            get_thumbnail,
+            get_image_info,
+            save_images_batch,
+            set_image_limits,
+            get_image_path_by_url,
+            has_image_by_url,
+            read_image_by_url,
+            save_downloaded_image_by_url,
+            set_backup_label,
+            preview_restore,
+            restore_backup_merge,
+            is_valid_backup,
+            find_duplicate_notes,
+            migrate_to_sharded_layout,
+            get_disk_space,
+            set_hash_threads,
+            open_image_externally,
+            export_all,
+            import_all,
+            list_stored_images,
+            migrate_old_images_batch,
+            confirm_quit_ready,
+            reveal_image_in_folder,
+            get_view_mode,
+            exit_view_mode,
+            get_window_preset,
+            apply_window_preset,
+            search_in_backups,
+            get_staged_update,
+            launch_installer,
+            set_image_watcher,
+            set_pretty_json,
+            get_storage_report,
         ])
-        .setup(|app| {
+        .setup(move |app| {
             // Get window and configure
             let window = app.get_webview_window("main").unwrap();
 
+            // 临时模式：配置只保留在内存中，关闭自动保存
+            if ephemeral {
+                tauri_plugin_store::StoreBuilder::new(app.handle(), "config.json")
+                    .disable_auto_save()
+                    .build()?;
+                let _ = app.emit("ephemeral-mode", true);
+            } else {
+                // 上次运行时为外部打开而写出的图片（单实例检查之后才清理）
+                let _ = fs::remove_dir_all(external_image_dir());
+            }
+
+            // --view：只读打开备份，前端启动后通过 get_view_mode 获取
+            if let Some(path) = &view_path {
+                enter_view_mode(app.handle(), path);
+            }
+
             // Load saved window bounds
             if let Ok(store) = app.store("config.json") {
                 if let Some(bounds_value) = store.get("windowBounds") {
                     if let Ok(bounds) = serde_json::from_value::<WindowBounds>(bounds_value) {
-                        let mut width = bounds.width.max(MIN_WINDOW_WIDTH);
-                        let mut height = bounds.height.max(MIN_WINDOW_HEIGHT);
+                        // 上次使用便签预设时允许更小的窗口
+                        let (min_width, min_height) = if current_window_preset(&store) == "sticky" {
+                            let _ = window.set_min_size(Some(tauri::LogicalSize::new(
+                                STICKY_NOTE_SIZE,
+                                STICKY_NOTE_SIZE,
+                            )));
+                            (STICKY_NOTE_SIZE, STICKY_NOTE_SIZE)
+                        } else {
+                            (MIN_WINDOW_WIDTH, MIN_WINDOW_HEIGHT)
+                        };
+                        let mut width = bounds.width.max(min_width);
+                        let mut height = bounds.height.max(min_height);
                         let mut pos = bounds.x.zip(bounds.y);
 
                         if let Ok(Some(monitor)) = window.primary_monitor() {
-                            let monitor_pos = monitor.position();
-                            let monitor_size = monitor.size();
-                            width = width.min(monitor_size.width);
-                            height = height.min(monitor_size.height);
-
-                            if let Some((x, y)) = pos {
-                                let max_x = monitor_pos.x + monitor_size.width as i32 - width as i32;
-                                let max_y = monitor_pos.y + monitor_size.height as i32 - height as i32;
-                                let clamped_x = x.clamp(monitor_pos.x, max_x);
-                                let clamped_y = y.clamp(monitor_pos.y, max_y);
-                                pos = Some((clamped_x, clamped_y));
-                            }
+                            (pos, width, height) =
+                                clamp_rect_to_monitor(pos, width, height, &monitor);
                         }
 
                         let _ = window.set_size(PhysicalSize::new(width, height));
                         if let Some((x, y)) = pos {
                             let _ = window.set_position(PhysicalPosition::new(x, y));
                         }
+                        if bounds.fullscreen {
+                            let _ = window.set_fullscreen(true);
+                        } else if bounds.maximized {
+                            let _ = window.maximize();
+                        }
                     }
                 }
 
@@ -1066,27 +9642,66 @@ fn main() {
                         if settings.always_on_top {
                             let _ = window.set_always_on_top(true);
                         }
+                        if settings.window_opacity < MAX_WINDOW_OPACITY {
+                            let _ = apply_window_opacity(&window, settings.window_opacity);
+                        }
                     }
                 }
             }
 
+            let startup_settings = app
+                .store("config.json")
+                .ok()
+                .and_then(|store| store.get("settings"))
+                .and_then(|v| serde_json::from_value::<Settings>(v).ok())
+                .unwrap_or_default();
+            let shortcut_setting = startup_settings.shortcut.clone();
+            // 无效的快捷键设置回退到默认值，托盘菜单和提示显示实际生效的快捷键
+            let (shortcut, shortcut_label) = match parse_shortcut(&shortcut_setting) {
+                Ok(shortcut) => (shortcut, shortcut_setting.clone()),
+                Err(e) => {
+                    eprintln!(
+                        "Warning: {}. Falling back to {}.",
+                        e, DEFAULT_TOGGLE_SHORTCUT
+                    );
+                    (
+                        Shortcut::new(Some(Modifiers::ALT), Code::KeyX),
+                        DEFAULT_TOGGLE_SHORTCUT.to_string(),
+                    )
+                }
+            };
+            *TRAY_SHORTCUT_LABEL.lock().unwrap() = shortcut_label.clone();
+
             // Setup tray
-            let show_hide = MenuItem::with_id(app, "show_hide", "Show/Hide (Alt+X)", true, None::<&str>)?;
+            let show_hide = MenuItem::with_id(
+                app,
+                "show_hide",
+                format!("Show/Hide ({})", shortcut_label),
+                true,
+                None::<&str>,
+            )?;
+            app.manage(show_hide.clone());
+            let always_on_top = CheckMenuItem::with_id(
+                app,
+                "always_on_top",
+                "Always on Top",
+                true,
+                startup_settings.always_on_top,
+                None::<&str>,
+            )?;
+            app.manage(always_on_top.clone());
             let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
-            let menu = Menu::with_items(app, &[&show_hide, &quit])?;
+            let menu = Menu::with_items(app, &[&show_hide, &always_on_top, &quit])?;
 
-            let _tray = TrayIconBuilder::new()
+            let _tray = TrayIconBuilder::with_id(TRAY_ID)
                 .icon(app.default_window_icon().unwrap().clone())
-                .tooltip("LitePad - Alt+X to toggle")
+                .tooltip(default_tray_tooltip())
                 .menu(&menu)
-                .on_menu_event(|app, event| {
-                    match event.id.as_ref() {
-                        "show_hide" => toggle_window(app),
-                        "quit" => {
-                            app.exit(0);
-                        }
-                        _ => {}
-                    }
+                .on_menu_event(|app, event| match event.id.as_ref() {
+                    "show_hide" => toggle_window(app),
+                    "always_on_top" => toggle_always_on_top(app),
+                    "quit" => request_quit(app),
+                    _ => {}
                 })
                 .on_tray_icon_event(|tray, event| {
                     if let TrayIconEvent::Click {
@@ -1100,27 +9715,30 @@ fn main() {
                 })
                 .build(app)?;
 
-            // Register global shortcut Alt+X
-            let shortcut = Shortcut::new(Some(Modifiers::ALT), Code::KeyX);
-            let app_handle = app.handle().clone();
-            let register_result = app.global_shortcut().on_shortcut(shortcut, move |_app, _shortcut, event| {
-                match event.state {
-                    ShortcutState::Pressed => {
-                        // 防止按住触发重复 Pressed；并避免 Pressed/Released 双触发导致“闪一下”
-                        let was_held = SHORTCUT_HELD.swap(true, Ordering::Relaxed);
-                        if was_held {
-                            return;
+            // Start backend-driven auto backup
+            spawn_auto_backup_scheduler(app.handle().clone());
+
+            if startup_settings.watch_images {
+                if let Err(e) = start_image_watcher(app.handle()) {
+                    eprintln!("Warning: Failed to watch images directory: {}", e);
+                }
+            }
+
+            // Register toggle shortcut (default Alt+X)
+            if let Err(e) = register_toggle_shortcut(app.handle(), shortcut) {
+                report_shortcut_failure(app.handle(), "toggle", &shortcut_label, e);
+            }
+
+            // Register always-on-top shortcut (optional)
+            if let Some(value) = startup_settings.always_on_top_shortcut.as_deref() {
+                match parse_shortcut(value) {
+                    Ok(shortcut) => {
+                        if let Err(e) = register_always_on_top_shortcut(app.handle(), shortcut) {
+                            report_shortcut_failure(app.handle(), "alwaysOnTop", value, e);
                         }
-                        toggle_window(&app_handle);
-                    }
-                    ShortcutState::Released => {
-                        SHORTCUT_HELD.store(false, Ordering::Relaxed);
                     }
+                    Err(e) => eprintln!("Warning: {}", e),
                 }
-            });
-
-            if let Err(e) = register_result {
-                eprintln!("Warning: Failed to register Alt+X shortcut: {}. Another application may be using it.", e);
             }
 
             // Save window bounds on resize/move
@@ -1130,22 +9748,61 @@ fn main() {
                 match event {
                     WindowEvent::Resized(_) | WindowEvent::Moved(_) => {
                         if let Ok(store) = app_handle.store("config.json") {
-                            if let (Ok(size), Ok(pos)) = (window_clone.outer_size(), window_clone.outer_position()) {
-                                let bounds = WindowBounds {
+                            let maximized = window_clone.is_maximized().unwrap_or(false);
+                            let fullscreen = window_clone.is_fullscreen().unwrap_or(false);
+                            let bounds = if maximized || fullscreen {
+                                // 最大化/全屏时只更新标记，保留还原尺寸
+                                let previous: WindowBounds = store
+                                    .get("windowBounds")
+                                    .and_then(|v| serde_json::from_value(v).ok())
+                                    .unwrap_or_default();
+                                Some(WindowBounds {
+                                    maximized,
+                                    fullscreen,
+                                    ..previous
+                                })
+                            } else if let (Ok(size), Ok(pos)) =
+                                (window_clone.outer_size(), window_clone.outer_position())
+                            {
+                                Some(WindowBounds {
                                     x: Some(pos.x),
                                     y: Some(pos.y),
                                     width: size.width,
                                     height: size.height,
-                                };
+                                    maximized: false,
+                                    fullscreen: false,
+                                })
+                            } else {
+                                None
+                            };
+                            if let Some(bounds) = bounds {
                                 store.set("windowBounds", serde_json::to_value(&bounds).unwrap());
-                                let _ = store.save();
+                                schedule_window_bounds_save(&app_handle);
                             }
                         }
                     }
+                    // 显示器配置变化（拔掉外接显示器、缩放变化）后重新检查窗口是否可见
+                    WindowEvent::Focused(true) | WindowEvent::ScaleFactorChanged { .. } => {
+                        ensure_window_on_screen(&window_clone);
+                    }
                     WindowEvent::CloseRequested { api, .. } => {
-                        // Hide instead of close
+                        let close_to_tray = app_handle
+                            .store("config.json")
+                            .ok()
+                            .and_then(|store| store.get("settings"))
+                            .and_then(|v| serde_json::from_value::<Settings>(v).ok())
+                            .unwrap_or_default()
+                            .close_to_tray;
+
                         api.prevent_close();
-                        let _ = window_clone.hide();
+                        if close_to_tray {
+                            // Hide instead of close
+                            let _ = flush_pending_data(&app_handle);
+                            flush_window_bounds(&app_handle);
+                            let _ = window_clone.hide();
+                        } else {
+                            request_quit(&app_handle);
+                        }
                     }
                     _ => {}
                 }
@@ -1153,6 +9810,349 @@ fn main() {
 
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // 无论从哪里退出，都在进程结束前写入待保存的数据
+            if let RunEvent::Exit = event {
+                flush_before_exit(app);
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prerelease_is_lower_than_release() {
+        assert!(compare_versions("1.2.0-rc1", "1.2.0"));
+        assert!(!compare_versions("1.2.0", "1.2.0-rc1"));
+    }
+
+    #[test]
+    fn numeric_components_compare_numerically() {
+        assert!(compare_versions("1.9.0", "1.10.0"));
+        assert!(!compare_versions("1.10.0", "1.9.0"));
+    }
+
+    #[test]
+    fn build_metadata_is_ignored() {
+        assert!(!compare_versions("1.2.0", "v1.2.0+build3"));
+        assert!(!compare_versions("v1.2.0+build3", "1.2.0"));
+    }
+
+    #[test]
+    fn prerelease_identifiers_follow_precedence() {
+        assert!(compare_versions("1.2.0-beta.1", "1.2.0-beta.2"));
+        assert!(compare_versions("1.2.0-beta.2", "1.2.0-beta.11"));
+        assert!(compare_versions("1.2.0-alpha", "1.2.0-alpha.1"));
+        assert!(compare_versions("1.2.0-beta.11", "1.2.0-rc.1"));
+    }
+
+    fn legacy_fixture(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("litepad-legacy-{}", name));
+        let nested = dir.join("old images");
+        fs::create_dir_all(&nested).unwrap();
+        let file = nested.join("pic.png");
+        fs::write(&file, b"png").unwrap();
+        file
+    }
+
+    #[test]
+    fn legacy_paths_accept_both_separator_styles() {
+        let file = legacy_fixture("separators");
+        let raw = file.to_string_lossy().to_string();
+        let forward = raw.replace('\\', "/");
+        let backward = raw.replace('/', "\\");
+
+        assert_eq!(legacy_image_path(&forward), file);
+        assert_eq!(legacy_image_path(&backward), file);
+        assert_eq!(
+            check_old_images_exist(vec![forward, backward]),
+            vec![true, true]
+        );
+        let _ = fs::remove_dir_all(file.parent().unwrap().parent().unwrap());
+    }
+
+    #[test]
+    fn legacy_paths_collapse_doubled_separators() {
+        let file = legacy_fixture("doubled");
+        let raw = file.to_string_lossy().to_string();
+        let doubled_forward = raw.replace('\\', "/").replace('/', "//");
+        let doubled_backward = raw.replace('/', "\\").replace('\\', "\\\\");
+
+        assert_eq!(legacy_image_path(&doubled_forward), file);
+        assert_eq!(legacy_image_path(&doubled_backward), file);
+        let _ = fs::remove_dir_all(file.parent().unwrap().parent().unwrap());
+    }
+
+    #[test]
+    fn view_path_accepts_both_arg_forms() {
+        let cwd = std::env::temp_dir();
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            view_path_from_args(&args(&["litepad", "--view", "backup.zip"]), &cwd),
+            Some(cwd.join("backup.zip"))
+        );
+        assert_eq!(
+            view_path_from_args(&args(&["litepad", "--view=backup.zip"]), &cwd),
+            Some(cwd.join("backup.zip"))
+        );
+        assert_eq!(
+            view_path_from_args(&args(&["litepad", "--view"]), &cwd),
+            None
+        );
+        assert_eq!(
+            view_path_from_args(&args(&["litepad", "--hidden"]), &cwd),
+            None
+        );
+    }
+
+    #[test]
+    fn pretty_json_sorts_keys_and_keeps_minified_default() {
+        let raw = r#"{"tabs":[{"title":"a","id":"1"}],"activeTabId":"1"}"#;
+        assert_eq!(format_json_snapshot(raw, false), raw);
+        assert_eq!(
+            format_json_snapshot(raw, true),
+            "{\n  \"activeTabId\": \"1\",\n  \"tabs\": [\n    {\n      \"id\": \"1\",\n      \"title\": \"a\"\n    }\n  ]\n}"
+        );
+        assert_eq!(format_json_snapshot("not json", true), "not json");
+    }
+
+    #[test]
+    fn missing_legacy_paths_are_reported() {
+        let missing = std::env::temp_dir().join("litepad-legacy-missing/none.png");
+        let raw = missing.to_string_lossy().to_string();
+        assert_eq!(check_old_images_exist(vec![raw]), vec![false]);
+    }
+
+    #[test]
+    fn encrypted_backup_round_trips_across_chunks() {
+        // 跨越多个分块且末块不满，覆盖分块边界
+        let plain: Vec<u8> = (0..BACKUP_CHUNK_LEN * 2 + 123)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let mut encrypted = Vec::new();
+        encrypt_backup_stream(&mut plain.as_slice(), &mut encrypted, "secret").unwrap();
+        assert!(encrypted.starts_with(ENCRYPTED_BACKUP_MAGIC));
+
+        let decrypted = decrypt_backup_stream(&mut encrypted.as_slice(), "secret").unwrap();
+        assert_eq!(decrypted, plain);
+
+        let wrong = decrypt_backup_stream(&mut encrypted.as_slice(), "wrong");
+        assert_eq!(wrong, Err("INVALID_PASSWORD".to_string()));
+
+        // 截掉末块后认证失败，而不是返回不完整的数据
+        let truncated = &encrypted[..encrypted.len() - 200];
+        assert!(decrypt_backup_stream(&mut &truncated[..], "secret").is_err());
+    }
+
+    #[test]
+    fn encrypted_backup_handles_empty_and_exact_chunk_input() {
+        for len in [0, BACKUP_CHUNK_LEN] {
+            let plain = vec![7u8; len];
+            let mut encrypted = Vec::new();
+            encrypt_backup_stream(&mut plain.as_slice(), &mut encrypted, "secret").unwrap();
+            let decrypted = decrypt_backup_stream(&mut encrypted.as_slice(), "secret").unwrap();
+            assert_eq!(decrypted, plain);
+        }
+    }
+
+    fn search(text: &str, query: &str, options: &SearchOptions) -> Vec<(usize, usize)> {
+        let query = normalize_for_search(query, options.case_sensitive);
+        search_in_text(text, &query, options)
+            .iter()
+            .map(|m| (m.start, m.end))
+            .collect()
+    }
+
+    #[test]
+    fn search_offsets_are_utf16_for_cjk_and_astral_text() {
+        let options = SearchOptions::default();
+        // 😀 占两个 UTF-16 单元，中日韩字符各占一个
+        assert_eq!(search("😀中文abc", "abc", &options), vec![(4, 7)]);
+        assert_eq!(search("中文😀中文", "中文", &options), vec![(0, 2), (4, 6)]);
+        // 小写后字节长度变化（İ -> i）时仍按原文字符换算
+        assert_eq!(search("İstanbul", "stanbul", &options), vec![(1, 8)]);
+        assert_eq!(search("ÄBC äbc", "ÄBC", &options), vec![(0, 3), (4, 7)]);
+
+        let case_sensitive = SearchOptions {
+            case_sensitive: true,
+            ..Default::default()
+        };
+        assert_eq!(search("ÄBC äbc", "ÄBC", &case_sensitive), vec![(0, 3)]);
+    }
+
+    #[test]
+    fn whole_word_search_respects_word_boundaries() {
+        let options = SearchOptions {
+            whole_word: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            search("cat concat cat_s cat.", "cat", &options),
+            vec![(0, 3), (17, 20)]
+        );
+        assert!(search("猫cat", "cat", &options).is_empty());
+    }
+
+    #[test]
+    fn edit_distance_counts_transpositions_and_stops_at_bound() {
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+        assert_eq!(
+            bounded_edit_distance(&chars("note"), &chars("note"), 0),
+            Some(0)
+        );
+        assert_eq!(
+            bounded_edit_distance(&chars("ntoe"), &chars("note"), 1),
+            Some(1)
+        );
+        assert_eq!(
+            bounded_edit_distance(&chars("kitten"), &chars("sitting"), 2),
+            None
+        );
+        assert_eq!(
+            bounded_edit_distance(&chars("kitten"), &chars("sitting"), 3),
+            Some(3)
+        );
+        assert_eq!(bounded_edit_distance(&chars("a"), &chars("abcd"), 2), None);
+    }
+
+    #[test]
+    fn fuzzy_thresholds_scale_with_word_length() {
+        let options = SearchOptions {
+            fuzzy: true,
+            ..Default::default()
+        };
+        // 3 个字符以内必须完全一致
+        assert_eq!(search("cat cot", "cat", &options), vec![(0, 3)]);
+        // 4-7 个字符允许 1 处差异
+        assert_eq!(
+            search("nite ntoe nuts", "note", &options),
+            vec![(0, 4), (5, 9)]
+        );
+        // 8 个字符以上允许 2 处差异
+        assert_eq!(search("nitebouk", "notebook", &options), vec![(0, 8)]);
+        assert!(search("nitabouk", "notebook", &options).is_empty());
+        // 每个查询词都必须命中
+        assert!(search("note here", "note missing", &options).is_empty());
+        assert_eq!(search("😀 Notes", "note", &options), vec![(3, 8)]);
+    }
+
+    #[test]
+    fn snippet_starts_before_match_in_utf16_units() {
+        let text = format!("{}😀\nmatch", "x".repeat(50));
+        // 匹配位于第 52 个字符（UTF-16 偏移 53），往前取 40 个字符
+        let (snippet, start) = search_snippet(&text, 53);
+        assert_eq!(start, 12);
+        assert_eq!(snippet, format!("{}😀 match", "x".repeat(38)));
+        assert_eq!(search_snippet("short", 0), ("short".to_string(), 0));
+    }
+
+    #[test]
+    fn plain_text_strips_html_and_decodes_entities() {
+        assert_eq!(
+            note_plain_text("<p>Hello&nbsp;<b>world</b></p><p>a &amp; b</p>"),
+            "\nHello world\n\na & b\n"
+        );
+        // 不是标签的 < 和未知实体原样保留
+        assert_eq!(note_plain_text("a < b &foo; &lt"), "a < b &foo; &lt\n");
+    }
+
+    #[test]
+    fn plain_text_strips_markdown_markers() {
+        assert_eq!(
+            note_plain_text(
+                "# Title\n- [x] done **bold** `code`\n![alt](img.png) [link](http://x)"
+            ),
+            "Title\ndone bold code\nalt link\n"
+        );
+        assert_eq!(
+            note_plain_text("> quote [not a link"),
+            "quote [not a link\n"
+        );
+    }
+
+    #[test]
+    fn words_count_cjk_characters_individually() {
+        assert_eq!(count_words(""), 0);
+        assert_eq!(count_words("Hello, world! it's well-known"), 4);
+        assert_eq!(count_words("中文字"), 3);
+        assert_eq!(count_words("mixed中文text"), 4);
+        assert_eq!(count_words("-- _x 3.14"), 3);
+    }
+
+    #[test]
+    fn expected_sha256_prefers_the_line_naming_the_file() {
+        let (setup, msi) = ("a".repeat(64), "b".repeat(64));
+        let notes = format!(
+            "LitePad_3.1.0_x64-setup.exe: {}\nLitePad_3.1.0_x64.msi {}",
+            setup,
+            msi.to_uppercase()
+        );
+        assert_eq!(
+            expected_sha256(&notes, "LitePad_3.1.0_x64-setup.exe"),
+            Some(setup.clone())
+        );
+        assert_eq!(expected_sha256(&notes, "LitePad_3.1.0_x64.msi"), Some(msi));
+        // 没有对应行且哈希不唯一时不校验
+        assert_eq!(expected_sha256(&notes, "LitePad_3.1.0.dmg"), None);
+
+        let single = format!("SHA-256: {}", setup);
+        assert_eq!(expected_sha256(&single, "anything.exe"), Some(setup));
+        assert_eq!(expected_sha256(&"c".repeat(65), "anything.exe"), None);
+    }
+
+    #[test]
+    fn version_is_read_from_installer_filenames() {
+        assert_eq!(
+            version_from_filename("LitePad_3.1.0_x64-setup.exe").as_deref(),
+            Some("3.1.0")
+        );
+        assert_eq!(
+            version_from_filename("LitePad-v3.2.1-beta.dmg").as_deref(),
+            Some("3.2.1")
+        );
+        assert_eq!(
+            version_from_filename("LitePad_3.1.0.exe").as_deref(),
+            Some("3.1.0")
+        );
+        assert_eq!(
+            version_from_filename("LitePad_3.1.0.1_x64.msi").as_deref(),
+            Some("3.1.0")
+        );
+        assert_eq!(version_from_filename("LitePad_3.1_x64.msi"), None);
+        assert_eq!(version_from_filename("setup.exe"), None);
+    }
+
+    #[test]
+    fn search_index_ranks_by_matched_terms_and_prefixes_last_term() {
+        let mut index = SearchIndex::new();
+        assert!(index.update("a", "Rust search engine"));
+        assert!(index.update("b", "rust notes"));
+        assert!(index.update("c", "<p>中文笔记</p>"));
+        assert!(!index.update("a", "Rust search engine"));
+
+        let ids = |results: Vec<IndexQueryResult>| -> Vec<String> {
+            results.into_iter().map(|r| r.id).collect()
+        };
+        assert_eq!(ids(index.query("RUST search", 10)), vec!["a", "b"]);
+        assert_eq!(ids(index.query("rust search", 1)), vec!["a"]);
+        // 只有最后一个词按前缀匹配
+        assert_eq!(ids(index.query("sea", 10)), vec!["a"]);
+        assert!(index
+            .query("sea rust", 10)
+            .iter()
+            .all(|r| r.matched_terms == 1));
+        // 中日韩文字逐字成词
+        let cjk = index.query("笔记", 10);
+        assert_eq!(cjk.len(), 1);
+        assert_eq!((cjk[0].id.as_str(), cjk[0].matched_terms), ("c", 2));
+
+        assert!(index.remove("a"));
+        assert!(index.query("search", 10).is_empty());
+        assert!(index.query("   ", 10).is_empty());
+    }
 }
@@ -41,11 +41,12 @@ import {
   saveTabSortMode,
   initStorage,
   refreshCache,
+  normalizeTabs,
   reindexTabs,
 } from "./utils/storage";
 import { initSync, addSyncListener } from "./sync";
 import { migrateOldImageUrls, updateVersionRecord } from "./utils/migration";
-import { tauriAPI, BackupSettings } from "./lib/tauri-api";
+import { tauriAPI, type ViewModeData } from "./lib/tauri-api";
 import { collectBackupDataFromLocalStorage } from "./utils/backup";
 import { ConflictResolver, Conflict } from "./components/ConflictResolver";
 import "./styles/App.css";
@@ -122,12 +123,11 @@ function App() {
   const [syncConflictsServerTime, setSyncConflictsServerTime] = useState<
     number | null
   >(null);
-  const [backupSettings, setBackupSettings] = useState<BackupSettings | null>(
-    null,
-  );
   const saveTimeoutRef = useRef<NodeJS.Timeout | null>(null);
   const saveImmediatelyRef = useRef(false);
-  const autoBackupTimerRef = useRef<number | null>(null);
+  const latestDataRef = useRef<AppData | null>(null);
+  const [viewMode, setViewMode] = useState<ViewModeData | null>(null);
+  const viewModeRef = useRef(false);
   const autoBackupRunningRef = useRef(false);
   const lastPointerRef = useRef<{ x: number; y: number; t: number } | null>(
     null,
@@ -237,26 +237,14 @@ function App() {
 
   useEffect(() => {
     if (!tauriAPI) return;
-    tauriAPI.getBackupSettings().then(setBackupSettings).catch(console.error);
-  }, []);
-
-  useEffect(() => {
-    if (autoBackupTimerRef.current !== null) {
-      window.clearInterval(autoBackupTimerRef.current);
-      autoBackupTimerRef.current = null;
-    }
-
-    if (!tauriAPI || !backupSettings?.autoBackupEnabled) return;
-
-    const intervalMinutes = Math.max(1, Number(backupSettings.autoBackupInterval) || 0);
-    const intervalMs = intervalMinutes * 60 * 1000;
 
+    // 调度由后端负责：到期时后端发出 auto-backup-request，这里提供当前数据
     const runAutoBackup = async () => {
       if (!tauriAPI || autoBackupRunningRef.current) return;
       autoBackupRunningRef.current = true;
       try {
         const data = collectBackupDataFromLocalStorage();
-        await tauriAPI.performBackup(JSON.stringify(data));
+        await tauriAPI.performAutoBackup(JSON.stringify(data));
       } catch (error) {
         console.error("自动备份失败:", error);
       } finally {
@@ -264,19 +252,72 @@ function App() {
       }
     };
 
-    void runAutoBackup();
-
-    autoBackupTimerRef.current = window.setInterval(() => {
+    const unlisten = tauriAPI.onAutoBackupRequest(() => {
       void runAutoBackup();
-    }, intervalMs);
+    });
 
     return () => {
-      if (autoBackupTimerRef.current !== null) {
-        window.clearInterval(autoBackupTimerRef.current);
-        autoBackupTimerRef.current = null;
+      void unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // 只读查看模式（litepad --view backup.zip）：显示备份内容，不保存
+  useEffect(() => {
+    if (!tauriAPI) return;
+
+    const applyViewMode = (view: ViewModeData | null) => {
+      if (!view) return;
+      // 先写入尚未保存的真实数据，之后的修改都不再保存
+      if (saveTimeoutRef.current) {
+        clearTimeout(saveTimeoutRef.current);
+        saveTimeoutRef.current = null;
+        if (latestDataRef.current) {
+          saveData(latestDataRef.current);
+        }
       }
+      viewModeRef.current = true;
+      setViewMode(view);
+      const tabs = normalizeTabs(view.notes.length > 0 ? view.notes : [createTab()]);
+      setData({ tabs, activeTabId: tabs[0].id });
     };
-  }, [backupSettings?.autoBackupEnabled, backupSettings?.autoBackupInterval]);
+
+    void tauriAPI.getViewMode().then(applyViewMode).catch(console.error);
+    const unlistenView = tauriAPI.onViewMode(applyViewMode);
+    const unlistenError = tauriAPI.onViewModeError((error) => {
+      console.error("无法打开备份:", error);
+    });
+
+    return () => {
+      void unlistenView.then((fn) => fn());
+      void unlistenError.then((fn) => fn());
+    };
+  }, []);
+
+  const handleExitViewMode = async () => {
+    await tauriAPI?.exitViewMode();
+    viewModeRef.current = false;
+    setViewMode(null);
+    setData(await refreshCache());
+  };
+
+  // 退出前写入尚未保存的数据
+  useEffect(() => {
+    if (!tauriAPI) return;
+
+    const unlisten = tauriAPI.onAppWillQuit(async () => {
+      if (saveTimeoutRef.current) {
+        clearTimeout(saveTimeoutRef.current);
+        saveTimeoutRef.current = null;
+      }
+      if (latestDataRef.current) {
+        await saveData(latestDataRef.current);
+      }
+    });
+
+    return () => {
+      void unlisten.then((fn) => fn());
+    };
+  }, []);
 
   // 应用字体设置
   useEffect(() => {
@@ -313,6 +354,8 @@ function App() {
 
   // 数据变化时保存
   useEffect(() => {
+    if (viewModeRef.current) return;
+    latestDataRef.current = data;
     if (saveImmediatelyRef.current) {
       saveImmediatelyRef.current = false;
       if (saveTimeoutRef.current) {
@@ -989,6 +1032,14 @@ function App() {
           setShowSearch(true);
         }}
       />
+      {viewMode && (
+        <div className="view-mode-banner">
+          <span>{t("app.viewMode", { source: viewMode.source })}</span>
+          <button onClick={() => void handleExitViewMode()}>
+            {t("app.exitViewMode")}
+          </button>
+        </div>
+      )}
       <div className="app-body">
         <main className="app-main">
           {activeTab && (
@@ -1044,7 +1095,6 @@ function App() {
             return newSettings;
           });
         }}
-        onBackupSettingsChange={setBackupSettings}
         tabSortMode={tabSortMode}
         onTabSortModeChange={handleTabSortModeChange}
       />
@@ -47,6 +47,7 @@ const imageMimeToExt: Record<string, string> = {
   "image/x-icon": ".ico",
   "image/vnd.microsoft.icon": ".ico",
   "image/tiff": ".tif",
+  "image/avif": ".avif",
 };
 
 interface JumpTarget {
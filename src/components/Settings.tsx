@@ -3,7 +3,7 @@ import { useTranslation } from 'react-i18next'
 import { Github, Info } from 'lucide-react'
 import { loadShortcuts, saveShortcuts, ShortcutSettings, DEFAULT_SHORTCUTS, loadFont, saveFont, loadEditorFont, saveEditorFont, loadEditorFontSize, saveEditorFontSize, loadEditorTabIndentText, saveEditorTabIndentText, loadEditorLineHeight, saveEditorLineHeight, loadEditorCodeBlockHighlight, saveEditorCodeBlockHighlight, loadEditorQuickSymbolInput, saveEditorQuickSymbolInput, TabSortMode } from '../utils/storage'
 import { changeLanguage, getCurrentLanguage } from '../i18n/i18n'
-import { tauriAPI, BackupSettings, BackupInfo, PathValidationResult, UpdateInfo } from '../lib/tauri-api'
+import { tauriAPI, BackupSettings, BackupCompression, BackupInfo, PathValidationResult, UpdateInfo } from '../lib/tauri-api'
 import packageJson from '../../package.json'
 import { AuthModal } from './AuthModal'
 import { isLoggedIn, getUserInfo, logout as authLogout } from '../sync/auth'
@@ -120,6 +120,15 @@ export function Settings({ isOpen, onClose, onOpenHelp, onShortcutsChange, onFon
     const [customServerUrl, setCustomServerUrl] = useState('')
     const [syncLoading, setSyncLoading] = useState(false)
 
+    // 托盘菜单或快捷键切换置顶时同步勾选状态
+    useEffect(() => {
+        if (!tauriAPI) return
+        const unlisten = tauriAPI.onAlwaysOnTopChanged(setAlwaysOnTop)
+        return () => {
+            void unlisten.then((fn) => fn())
+        }
+    }, [])
+
     useEffect(() => {
         // 获取当前设置
         window.electronAPI?.getSettings().then((settings) => {
@@ -159,7 +168,7 @@ export function Settings({ isOpen, onClose, onOpenHelp, onShortcutsChange, onFon
         // 加载默认备份路径
         tauriAPI?.getDefaultBackupDir().then(setDefaultBackupDir)
         // 加载备份列表
-        tauriAPI?.getBackupList().then((list) => {
+        tauriAPI?.getBackupList(true).then((list) => {
             setBackupList(list)
         })
         // 加载同步配置
@@ -392,7 +401,7 @@ export function Settings({ isOpen, onClose, onOpenHelp, onShortcutsChange, onFon
             await tauriAPI?.performBackup(JSON.stringify(data))
             setBackupMessage(t('settings.backupSuccess'))
             // Refresh backup list
-            const list = await tauriAPI?.getBackupList()
+            const list = await tauriAPI?.getBackupList(true)
             if (list) setBackupList(list)
         } catch {
             setBackupMessage(t('settings.backupFailed'))
@@ -425,7 +434,7 @@ export function Settings({ isOpen, onClose, onOpenHelp, onShortcutsChange, onFon
         if (!confirm(t('settings.confirmDelete'))) return
         try {
             await tauriAPI?.deleteBackup(filename)
-            const list = await tauriAPI?.getBackupList()
+            const list = await tauriAPI?.getBackupList(true)
             if (list) setBackupList(list)
         } catch {
             // Ignore errors
@@ -938,6 +947,19 @@ export function Settings({ isOpen, onClose, onOpenHelp, onShortcutsChange, onFon
                                 ))}
                             </select>
                         </label>
+                        <label className="settings-item">
+                            <span>{t('settings.backupCompression')}</span>
+                            <select
+                                value={backupSettings.compression ?? 'balanced'}
+                                onChange={(e) => handleBackupSettingChange('compression', e.target.value as BackupCompression)}
+                                className="settings-select"
+                            >
+                                <option value="none">{t('settings.compressionNone')}</option>
+                                <option value="fast">{t('settings.compressionFast')}</option>
+                                <option value="balanced">{t('settings.compressionBalanced')}</option>
+                                <option value="best">{t('settings.compressionBest')}</option>
+                            </select>
+                        </label>
                         <label className="settings-item">
                             <span>{t('settings.autoBackup')}</span>
                             <input
@@ -982,6 +1004,9 @@ export function Settings({ isOpen, onClose, onOpenHelp, onShortcutsChange, onFon
                                             <div className="backup-info">
                                                 <span className="backup-time">{formatBackupTime(backup.createdAt)}</span>
                                                 <span className="backup-size">{formatBackupSize(backup.size)}</span>
+                                                {backup.readable === false && (
+                                                    <span className="backup-warning" title={t('settings.backupUnreadable')}>⚠</span>
+                                                )}
                                             </div>
                                             <div className="backup-actions">
                                                 <button
@@ -5,11 +5,22 @@
  */
 
 import { invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
 import { open } from '@tauri-apps/plugin-shell'
 
 interface AppSettings {
     autoLaunch: boolean
     alwaysOnTop: boolean
+    saveIntervalMs?: number
+    offlineMode?: boolean
+    shortcut?: string
+    closeToTray?: boolean
+    alwaysOnTopShortcut?: string | null
+    windowOpacity?: number
+    maxImageBytes?: number
+    hashThreads?: number
+    watchImages?: boolean
+    prettyJson?: boolean
 }
 
 // Backup settings interface
@@ -18,13 +29,46 @@ export interface BackupSettings {
     maxBackups: number
     autoBackupEnabled: boolean
     autoBackupInterval: number
+    includeImageMetadata?: boolean
+    includeLabels?: boolean
+    includeThumbnails?: boolean
+    autoBackupRetries?: number
+    encryptBackups?: boolean
+    maxAgeDays?: number | null
+    maxTotalBytes?: number | null
+    filenamePrefix?: string | null
+    hostnameInFilename?: boolean
+    autoBackupTime?: string | null
+    compression?: BackupCompression
 }
 
+export type BackupCompression = 'none' | 'fast' | 'balanced' | 'best'
+
 // Backup info interface
 export interface BackupInfo {
     filename: string
     createdAt: number
     size: number
+    encrypted: boolean
+    tag: string | null
+    label: string | null
+    readable: boolean | null
+}
+
+// Backup validity interface
+export interface BackupValidity {
+    readable: boolean
+    hasDataJson: boolean
+    imageCount: number
+    appVersion: string | null
+}
+
+// Restore preview interface
+export interface RestorePreview {
+    newImages: number
+    existingImages: number
+    conflictingImages: string[]
+    noteCount: number
 }
 
 // Path validation result interface
@@ -43,6 +87,17 @@ export interface UpdateInfo {
     releaseUrl: string | null
     releaseNotes: string | null
     publishedAt: string | null
+    asset: UpdateAsset | null
+    assets: UpdateAsset[]
+    checkedAt: number
+    fromCache: boolean
+}
+
+// Release asset interface
+export interface UpdateAsset {
+    name: string
+    downloadUrl: string
+    size: number
 }
 
 // Save image result interface
@@ -61,6 +116,381 @@ export interface MigrateImageResult {
     newUrl: string
 }
 
+// Per-file result of a batch migration (serialized Rust Result)
+export type MigrateImageBatchItem = { Ok: MigrateImageResult } | { Err: string }
+
+// Legacy config migration result interface
+export interface LegacyMigrationResult {
+    found: boolean
+    sourcePath: string | null
+    backupPath: string | null
+    migratedKeys: string[]
+}
+
+// Diagnostics report interface
+export interface DiagnosticsReport {
+    version: string
+    os: string
+    arch: string
+    ephemeral: boolean
+    dataPath: string
+    dataDirWritable: boolean
+    imagesPath: string
+    imageCount: number
+    imageBytes: number
+    backupDirectory: string | null
+    backupDirectoryExists: boolean
+    backupDirectoryWritable: boolean
+    updateCacheBytes: number
+}
+
+// Monitor interfaces
+export interface MonitorRect {
+    x: number
+    y: number
+    width: number
+    height: number
+}
+
+export interface MonitorInfo {
+    name: string | null
+    bounds: MonitorRect
+    workArea: MonitorRect
+    scaleFactor: number
+}
+
+export interface CurrentMonitorResult {
+    offScreen: boolean
+    monitor: MonitorInfo | null
+}
+
+// Clipboard kind interface
+export interface ClipboardKind {
+    hasImage: boolean
+    hasText: boolean
+    hasFiles: boolean
+    kinds: string[]
+}
+
+// Pack result interface
+export interface PackResult {
+    packedCount: number
+    packedBytes: number
+    packFile: string | null
+}
+
+// Image reference reconciliation interface
+export interface ImageReferenceFix {
+    oldUrl: string
+    newUrl: string | null
+    status: 'ok' | 'fixed' | 'missing'
+}
+
+// Remote image download result interface
+export interface RemoteImageResult {
+    sourceUrl: string
+    result: SaveImageResult | null
+    error: string | null
+}
+
+// Effective config entry interface
+export interface EffectiveConfigEntry {
+    key: string
+    value: unknown
+    source: string
+}
+
+// Last backup status interface
+export interface LastBackupStatus {
+    success: boolean
+    filename: string | null
+    error: string | null
+    timestamp: number
+}
+
+export interface BackupStatus {
+    lastBackupAt: number | null
+    lastResult: LastBackupStatus | null
+    running: boolean
+}
+
+// Update cache interfaces
+export interface UpdateCacheEntry {
+    filename: string
+    version: string | null
+    size: number
+    complete: boolean
+}
+
+export interface UpdateCacheStats {
+    directory: string
+    totalBytes: number
+    entries: UpdateCacheEntry[]
+}
+
+export interface BackupSelfTestResult {
+    passed: boolean
+    discrepancies: string[]
+    imageCount: number
+    archiveBytes: number
+    backupMs: number
+    restoreMs: number
+    verifyMs: number
+}
+
+export interface BackupNoteSummary {
+    id: string
+    title: string
+    createdAt: number | null
+    updatedAt: number | null
+    imageCount: number
+}
+
+export interface RestoredNote {
+    note: any
+    restoredImages: string[]
+    missingImages: string[]
+}
+
+export interface BackupVerifyResult {
+    valid: boolean
+    hasManifest: boolean
+    formatVersion: number | null
+    appVersion: string | null
+    checkedCount: number
+    mismatched: string[]
+    missing: string[]
+}
+
+export interface MarkdownExportResult {
+    exportedCount: number
+    writtenPaths: string[]
+    missingImages: string[]
+}
+
+export interface ImportedNote {
+    title: string
+    content: string
+    createdAt: number
+}
+
+export interface MarkdownImportResult {
+    path: string
+    note: ImportedNote | null
+    error: string | null
+}
+
+export interface ExtensionStats {
+    extension: string
+    count: number
+    bytes: number
+}
+
+export interface ImageStorageStats {
+    totalCount: number
+    totalBytes: number
+    byExtension: ExtensionStats[]
+    largest: { hash: string; filename: string; size: number } | null
+}
+
+// Payload of backup-progress / restore-progress events
+export interface TransferProgress {
+    current: number
+    total: number
+    currentFile: string
+}
+
+// Payload of backup-complete / restore-complete events
+export interface TransferComplete {
+    filename: string
+    totalBytes: number
+}
+
+export interface UpdateDownloadProgress {
+    filename: string
+    downloaded: number
+    total: number | null
+}
+
+export interface FontInfo {
+    name: string
+    isMonospace: boolean
+    isSerif: boolean
+}
+
+export interface ImageStoreVerifyResult {
+    checked: number
+    corrupt: string[]
+    quarantined: string[]
+}
+
+export interface DataDirectoryInfo {
+    dataPath: string
+    imagesPath: string
+    portable: boolean
+    ephemeral: boolean
+}
+
+export interface ShortcutRegistrationFailure {
+    action: 'toggle' | 'alwaysOnTop'
+    shortcut: string
+    error: string
+}
+
+export interface NoteTextStats {
+    words: number
+    characters: number
+    charactersWithSpaces: number
+}
+
+export interface TextStats {
+    totalWords: number
+    totalCharacters: number
+    totalCharactersWithSpaces: number
+    notes: Record<string, NoteTextStats>
+}
+
+export interface SearchOptions {
+    caseSensitive?: boolean
+    wholeWord?: boolean
+    fuzzy?: boolean
+}
+
+// start/end are UTF-16 offsets into the note's plain text
+export interface SearchMatch {
+    start: number
+    end: number
+}
+
+export interface NoteSearchResult {
+    id: string
+    titleMatch: boolean
+    matchCount: number
+    matches: SearchMatch[]
+    snippet: string
+    snippetStart: number
+}
+
+export interface IndexQueryResult {
+    id: string
+    score: number
+    matchedTerms: number
+}
+
+export interface IndexBuildResult {
+    indexed: number
+    updated: number
+    removed: number
+}
+
+export interface TrashedImage {
+    hash: string
+    ext: string
+    size: number
+    deletedAt: number
+}
+
+export interface RecompressResult {
+    hash: string
+    ext: string
+    url: string
+    savedBytes: number
+    skipped: boolean
+}
+
+export interface ImageInfo {
+    width: number
+    height: number
+    format: string
+    size: number
+}
+
+export interface DuplicateNoteGroup {
+    hash: string
+    ids: string[]
+}
+
+export interface ShardMigrationResult {
+    moved: number
+    failed: string[]
+}
+
+export interface DiskSpace {
+    total: number
+    available: number
+}
+
+export interface ImportAllResult {
+    data: string
+    importedImages: number
+    corruptImages: string[]
+}
+
+export interface StoredImage {
+    hash: string
+    ext: string
+    size: number
+}
+
+export interface StoredImageList {
+    images: StoredImage[]
+    unrecognized: string[]
+}
+
+// Read-only view mode (litepad --view backup.zip)
+export interface ViewModeData {
+    source: string
+    notes: any[]
+}
+
+// Window layout presets
+export type WindowPreset = 'sticky' | 'editor' | 'compact'
+
+// Backup content search result
+export interface BackupSearchResult {
+    filename: string
+    matchingNoteIds: string[]
+    snippets: string[]
+}
+
+// Installer already downloaded to the update cache
+export interface StagedUpdate {
+    path: string
+    filename: string
+    version: string
+    size: number
+    checksumVerified: boolean | null
+    valid: boolean
+}
+
+// Files added/removed in the images directory by external tools
+export interface ImagesChanged {
+    added: string[]
+    removed: string[]
+}
+
+// Storage health report
+export interface StorageIssue {
+    code: 'DATA_DIR_NOT_WRITABLE' | 'LOW_DISK_SPACE' | 'CORRUPT_IMAGE' | 'ORPHANED_FILE'
+    detail: string | null
+}
+
+export interface StorageReport {
+    dataPath: string
+    portable: boolean
+    ephemeral: boolean
+    imageCount: number
+    imageBytes: number
+    backupCount: number
+    backupBytes: number
+    newestBackupAt: number | null
+    configBytes: number | null
+    updateCacheBytes: number
+    disk: DiskSpace | null
+    sampledImages: number
+    issues: StorageIssue[]
+}
+
 // Type declaration for the API
 export interface TauriAPI {
     getVersion: () => Promise<string>
@@ -74,25 +504,138 @@ export interface TauriAPI {
     openExternalUrl: (url: string) => void
     // Image APIs (hash-based)
     saveImage: (buffer: ArrayBuffer, ext: string) => Promise<SaveImageResult>
+    saveImagesBatch: (items: { buffer: ArrayBuffer; ext: string }[]) => Promise<SaveImageResult[]>
     getImagePath: (hash: string, ext: string) => Promise<string>
     hasImage: (hash: string, ext: string) => Promise<boolean>
     saveDownloadedImage: (hash: string, ext: string, buffer: ArrayBuffer) => Promise<string>
     readImage: (hash: string, ext: string) => Promise<ArrayBuffer>
+    getImagePathByUrl: (url: string) => Promise<string>
+    hasImageByUrl: (url: string) => Promise<boolean>
+    saveDownloadedImageByUrl: (url: string, buffer: ArrayBuffer) => Promise<string>
+    readImageByUrl: (url: string) => Promise<ArrayBuffer>
     // Migration APIs
     migrateOldImage: (oldPath: string) => Promise<MigrateImageResult>
+    migrateOldImagesBatch: (oldPaths: string[]) => Promise<MigrateImageBatchItem[]>
     checkOldImagesExist: (paths: string[]) => Promise<boolean[]>
     // Backup APIs
     selectBackupDirectory: () => Promise<string | null>
     getBackupSettings: () => Promise<BackupSettings>
     setBackupSettings: (settings: BackupSettings) => Promise<void>
-    performBackup: (data: string) => Promise<string>
-    getBackupList: () => Promise<BackupInfo[]>
-    restoreBackup: (filename: string) => Promise<string>
+    performBackup: (data: string, passphrase?: string) => Promise<string>
+    getBackupList: (validate?: boolean) => Promise<BackupInfo[]>
+    isValidBackup: (filename: string, passphrase?: string) => Promise<BackupValidity>
+    restoreBackup: (filename: string, targetDir?: string, verify?: boolean, passphrase?: string) => Promise<string>
+    previewRestore: (filename: string, passphrase?: string) => Promise<RestorePreview>
+    restoreBackupMerge: (filename: string, passphrase?: string) => Promise<any[]>
     deleteBackup: (filename: string) => Promise<void>
+    setBackupLabel: (filename: string, label: string | null) => Promise<void>
     getDefaultBackupDir: () => Promise<string | null>
     validateBackupPath: (path: string) => Promise<PathValidationResult>
     // Update check API
-    checkForUpdates: () => Promise<UpdateInfo>
+    checkForUpdates: (includePrereleases?: boolean, maxAgeSecs?: number, force?: boolean) => Promise<UpdateInfo>
+    // Legacy config migration API
+    migrateLegacyConfig: () => Promise<LegacyMigrationResult>
+    // Diagnostics APIs
+    runDiagnostics: () => Promise<DiagnosticsReport>
+    createSupportBundle: (scrubPaths: boolean) => Promise<string | null>
+    // Window APIs
+    getCurrentMonitor: () => Promise<CurrentMonitorResult>
+    setAlwaysOnTopTemporary: (durationSecs: number) => Promise<void>
+    // Clipboard API
+    getClipboardKind: () => Promise<ClipboardKind>
+    // Image pack APIs
+    packSmallImages: (thresholdBytes?: number) => Promise<PackResult>
+    unpackImages: () => Promise<number>
+    isEphemeralMode: () => Promise<boolean>
+    reconcileImageReferences: (urls: string[]) => Promise<ImageReferenceFix[]>
+    // Data persistence APIs
+    saveData: (data: string) => Promise<void>
+    flushPendingSave: () => Promise<boolean>
+    setSaveInterval: (intervalMs: number) => Promise<void>
+    // Remote image APIs
+    downloadAndStoreImage: (url: string) => Promise<SaveImageResult>
+    downloadAndStoreImages: (urls: string[]) => Promise<RemoteImageResult[]>
+    getEffectiveConfig: () => Promise<EffectiveConfigEntry[]>
+    performAutoBackup: (data: string, passphrase?: string) => Promise<string>
+    getLastBackupStatus: () => Promise<BackupStatus>
+    getUpdateCacheStats: () => Promise<UpdateCacheStats>
+    clearUpdateCache: () => Promise<number>
+    selfTestBackup: () => Promise<BackupSelfTestResult>
+    setToggleShortcut: (shortcut: string) => Promise<void>
+    peekBackup: (filename: string, passphrase?: string) => Promise<BackupNoteSummary[]>
+    restoreNoteFromBackup: (filename: string, noteId: string, passphrase?: string) => Promise<RestoredNote>
+    verifyBackup: (filename: string, passphrase?: string) => Promise<BackupVerifyResult>
+    performIncrementalBackup: (data: string, passphrase?: string) => Promise<string>
+    exportMarkdown: (outputDir: string, data: string) => Promise<MarkdownExportResult>
+    importMarkdown: (paths: string[]) => Promise<MarkdownImportResult[]>
+    getImageStorageStats: () => Promise<ImageStorageStats>
+    computeImageHash: (buffer: ArrayBuffer, ext: string) => Promise<SaveImageResult>
+    cancelBackup: () => Promise<void>
+    onAutoBackupRequest: (callback: () => void) => Promise<() => void>
+    openBackupDirectory: (filename?: string) => Promise<void>
+    downloadUpdate: (assetUrl: string, destDir?: string) => Promise<string>
+    onUpdateDownloadProgress: (callback: (progress: UpdateDownloadProgress) => void) => Promise<() => void>
+    getSystemFontsDetailed: () => Promise<FontInfo[]>
+    refreshSystemFonts: () => Promise<void>
+    hasFont: (family: string) => Promise<boolean>
+    findClosestFont: (family: string) => Promise<string | null>
+    setCloseToTray: (enabled: boolean) => Promise<void>
+    resetWindowPosition: () => Promise<void>
+    setAlwaysOnTopShortcut: (shortcut: string | null) => Promise<void>
+    onAlwaysOnTopChanged: (callback: (enabled: boolean) => void) => Promise<() => void>
+    setWindowOpacity: (opacity: number) => Promise<number>
+    writeRecoverySnapshot: (data: string) => Promise<void>
+    readRecoverySnapshot: () => Promise<string | null>
+    clearRecoverySnapshot: () => Promise<void>
+    verifyImageStore: (quarantine?: boolean) => Promise<ImageStoreVerifyResult>
+    migrateDataDirectory: (newPath: string) => Promise<string>
+    getDataDirectory: () => Promise<DataDirectoryInfo>
+    getShortcutRegistrationFailures: () => Promise<ShortcutRegistrationFailure[]>
+    onShortcutRegistrationFailed: (callback: (failure: ShortcutRegistrationFailure) => void) => Promise<() => void>
+    setTrayTooltip: (text: string) => Promise<void>
+    setTrayBadge: (count: number | null) => Promise<void>
+    exportBackup: (filename: string) => Promise<string | null>
+    importBackup: () => Promise<BackupInfo | null>
+    computeTextStats: (data: string) => Promise<TextStats>
+    findDuplicateNotes: (data: string, normalized?: boolean) => Promise<DuplicateNoteGroup[]>
+    searchNotes: (data: string, query: string, options?: SearchOptions) => Promise<NoteSearchResult[]>
+    indexBuild: (data: string, force?: boolean) => Promise<IndexBuildResult>
+    indexUpdate: (noteId: string, text: string) => Promise<void>
+    indexRemove: (noteId: string) => Promise<void>
+    indexQuery: (query: string, limit?: number) => Promise<IndexQueryResult[]>
+    trashImage: (hash: string, ext: string) => Promise<void>
+    listTrashedImages: () => Promise<TrashedImage[]>
+    restoreTrashedImage: (hash: string, ext: string) => Promise<string>
+    emptyTrash: (olderThanDays?: number) => Promise<number>
+    recompressImage: (hash: string, ext: string, format: 'webp' | 'jpeg' | 'png', quality?: number, trashOriginal?: boolean) => Promise<RecompressResult>
+    getThumbnail: (hash: string, ext: string, maxDim: number) => Promise<string>
+    getImageInfo: (hash: string, ext: string) => Promise<ImageInfo>
+    setImageLimits: (maxImageBytes: number) => Promise<number>
+    migrateToShardedLayout: () => Promise<ShardMigrationResult>
+    getDiskSpace: (path: string) => Promise<DiskSpace>
+    setHashThreads: (threads: number) => Promise<void>
+    openImageExternally: (hash: string, ext: string) => Promise<void>
+    exportAll: (data: string, destPath?: string) => Promise<string | null>
+    onExportProgress: (callback: (progress: TransferProgress) => void) => Promise<() => void>
+    importAll: (sourcePath: string, force?: boolean) => Promise<ImportAllResult>
+    onImportProgress: (callback: (progress: TransferProgress) => void) => Promise<() => void>
+    listStoredImages: () => Promise<StoredImageList>
+    confirmQuitReady: () => Promise<void>
+    onAppWillQuit: (callback: () => void | Promise<void>) => Promise<() => void>
+    revealImageInFolder: (hash: string, ext: string) => Promise<void>
+    getViewMode: () => Promise<ViewModeData | null>
+    exitViewMode: () => Promise<void>
+    onViewMode: (callback: (view: ViewModeData) => void) => Promise<() => void>
+    onViewModeError: (callback: (error: string) => void) => Promise<() => void>
+    getWindowPreset: () => Promise<WindowPreset>
+    applyWindowPreset: (name: WindowPreset) => Promise<void>
+    searchInBackups: (query: string) => Promise<BackupSearchResult[]>
+    getStagedUpdate: () => Promise<StagedUpdate | null>
+    launchInstaller: (path: string, quit?: boolean) => Promise<void>
+    setImageWatcher: (enabled: boolean) => Promise<void>
+    onImagesChanged: (callback: (change: ImagesChanged) => void) => Promise<() => void>
+    setPrettyJson: (enabled: boolean) => Promise<void>
+    getStorageReport: () => Promise<StorageReport>
 }
 
 // Check if running in Tauri
@@ -136,6 +679,11 @@ export const tauriAPI: TauriAPI | undefined = isTauri ? {
         })
     },
 
+    saveImagesBatch: async (items: { buffer: ArrayBuffer; ext: string }[]) =>
+        invoke<SaveImageResult[]>('save_images_batch', {
+            items: items.map(({ buffer, ext }) => [Array.from(new Uint8Array(buffer)), ext])
+        }),
+
     getImagePath: (hash: string, ext: string) => invoke<string>('get_image_path', { hash, ext }),
 
     hasImage: (hash: string, ext: string) => invoke<boolean>('has_image', { hash, ext }),
@@ -154,7 +702,25 @@ export const tauriAPI: TauriAPI | undefined = isTauri ? {
         return new Uint8Array(result).buffer
     },
 
+    getImagePathByUrl: (url: string) => invoke<string>('get_image_path_by_url', { url }),
+
+    hasImageByUrl: (url: string) => invoke<boolean>('has_image_by_url', { url }),
+
+    saveDownloadedImageByUrl: async (url: string, buffer: ArrayBuffer) => {
+        const uint8Array = new Uint8Array(buffer)
+        return invoke<string>('save_downloaded_image_by_url', {
+            url,
+            buffer: Array.from(uint8Array)
+        })
+    },
+
+    readImageByUrl: async (url: string) => {
+        const result = await invoke<number[]>('read_image_by_url', { url })
+        return new Uint8Array(result).buffer
+    },
+
     migrateOldImage: (oldPath: string) => invoke<MigrateImageResult>('migrate_old_image', { oldPath }),
+    migrateOldImagesBatch: (oldPaths: string[]) => invoke<MigrateImageBatchItem[]>('migrate_old_images_batch', { oldPaths }),
 
     checkOldImagesExist: (paths: string[]) => invoke<boolean[]>('check_old_images_exist', { paths }),
 
@@ -165,19 +731,250 @@ export const tauriAPI: TauriAPI | undefined = isTauri ? {
 
     setBackupSettings: (settings: BackupSettings) => invoke('set_backup_settings', { settings }),
 
-    performBackup: (data: string) => invoke<string>('perform_backup', { data }),
+    performBackup: (data: string, passphrase?: string) => invoke<string>('perform_backup', { data, passphrase }),
+
+    getBackupList: (validate?: boolean) => invoke<BackupInfo[]>('get_backup_list', { validate }),
+
+    isValidBackup: (filename: string, passphrase?: string) =>
+        invoke<BackupValidity>('is_valid_backup', { filename, passphrase }),
 
-    getBackupList: () => invoke<BackupInfo[]>('get_backup_list'),
+    restoreBackup: (filename: string, targetDir?: string, verify?: boolean, passphrase?: string) =>
+        invoke<string>('restore_backup', { filename, targetDir, verify, passphrase }),
 
-    restoreBackup: (filename: string) => invoke<string>('restore_backup', { filename }),
+    previewRestore: (filename: string, passphrase?: string) =>
+        invoke<RestorePreview>('preview_restore', { filename, passphrase }),
+
+    restoreBackupMerge: (filename: string, passphrase?: string) =>
+        invoke<any[]>('restore_backup_merge', { filename, passphrase }),
 
     deleteBackup: (filename: string) => invoke('delete_backup', { filename }),
 
+    setBackupLabel: (filename: string, label: string | null) => invoke('set_backup_label', { filename, label }),
+
     getDefaultBackupDir: () => invoke<string | null>('get_default_backup_dir'),
 
     validateBackupPath: (path: string) => invoke<PathValidationResult>('validate_backup_path', { path }),
 
-    checkForUpdates: () => invoke<UpdateInfo>('check_for_updates')
+    checkForUpdates: (includePrereleases?: boolean, maxAgeSecs?: number, force?: boolean) =>
+        invoke<UpdateInfo>('check_for_updates', { includePrereleases, maxAgeSecs, force }),
+
+    migrateLegacyConfig: () => invoke<LegacyMigrationResult>('migrate_legacy_config'),
+
+    runDiagnostics: () => invoke<DiagnosticsReport>('run_diagnostics'),
+
+    createSupportBundle: (scrubPaths: boolean) => invoke<string | null>('create_support_bundle', { scrubPaths }),
+
+    getCurrentMonitor: () => invoke<CurrentMonitorResult>('get_current_monitor'),
+
+    setAlwaysOnTopTemporary: (durationSecs: number) => invoke('set_always_on_top_temporary', { durationSecs }),
+
+    getClipboardKind: () => invoke<ClipboardKind>('get_clipboard_kind'),
+
+    packSmallImages: (thresholdBytes?: number) => invoke<PackResult>('pack_small_images', { thresholdBytes }),
+
+    unpackImages: () => invoke<number>('unpack_images'),
+
+    isEphemeralMode: () => invoke<boolean>('is_ephemeral_mode'),
+
+    reconcileImageReferences: (urls: string[]) => invoke<ImageReferenceFix[]>('reconcile_image_references', { urls }),
+
+    saveData: (data: string) => invoke('save_data', { data }),
+
+    flushPendingSave: () => invoke<boolean>('flush_pending_save'),
+
+    setSaveInterval: (intervalMs: number) => invoke('set_save_interval', { intervalMs }),
+
+    downloadAndStoreImage: (url: string) => invoke<SaveImageResult>('download_and_store_image', { url }),
+
+    downloadAndStoreImages: (urls: string[]) => invoke<RemoteImageResult[]>('download_and_store_images', { urls }),
+
+    getEffectiveConfig: () => invoke<EffectiveConfigEntry[]>('get_effective_config'),
+
+    performAutoBackup: (data: string, passphrase?: string) => invoke<string>('perform_auto_backup', { data, passphrase }),
+
+    getLastBackupStatus: () => invoke<BackupStatus>('get_last_backup_status'),
+
+    getUpdateCacheStats: () => invoke<UpdateCacheStats>('get_update_cache_stats'),
+
+    clearUpdateCache: () => invoke<number>('clear_update_cache'),
+
+    selfTestBackup: () => invoke<BackupSelfTestResult>('self_test_backup'),
+
+    setToggleShortcut: (shortcut: string) => invoke('set_toggle_shortcut', { shortcut }),
+
+    peekBackup: (filename: string, passphrase?: string) => invoke<BackupNoteSummary[]>('peek_backup', { filename, passphrase }),
+
+    restoreNoteFromBackup: (filename: string, noteId: string, passphrase?: string) =>
+        invoke<RestoredNote>('restore_note_from_backup', { filename, noteId, passphrase }),
+
+    verifyBackup: (filename: string, passphrase?: string) => invoke<BackupVerifyResult>('verify_backup', { filename, passphrase }),
+
+    performIncrementalBackup: (data: string, passphrase?: string) =>
+        invoke<string>('perform_incremental_backup', { data, passphrase }),
+
+    exportMarkdown: (outputDir: string, data: string) =>
+        invoke<MarkdownExportResult>('export_markdown', { outputDir, data }),
+
+    importMarkdown: (paths: string[]) => invoke<MarkdownImportResult[]>('import_markdown', { paths }),
+
+    getImageStorageStats: () => invoke<ImageStorageStats>('get_image_storage_stats'),
+
+    computeImageHash: (buffer: ArrayBuffer, ext: string) =>
+        invoke<SaveImageResult>('compute_image_hash', {
+            buffer: Array.from(new Uint8Array(buffer)),
+            ext
+        }),
+
+    cancelBackup: () => invoke('cancel_backup'),
+
+    onAutoBackupRequest: (callback: () => void) => listen('auto-backup-request', () => callback()),
+
+    openBackupDirectory: (filename?: string) => invoke('open_backup_directory', { filename }),
+
+    downloadUpdate: (assetUrl: string, destDir?: string) => invoke<string>('download_update', { assetUrl, destDir }),
+
+    onUpdateDownloadProgress: (callback: (progress: UpdateDownloadProgress) => void) =>
+        listen<UpdateDownloadProgress>('update-download-progress', (event) => callback(event.payload)),
+
+    getSystemFontsDetailed: () => invoke<FontInfo[]>('get_system_fonts_detailed'),
+
+    refreshSystemFonts: () => invoke('refresh_system_fonts'),
+
+    hasFont: (family: string) => invoke<boolean>('has_font', { family }),
+
+    findClosestFont: (family: string) => invoke<string | null>('find_closest_font', { family }),
+
+    setCloseToTray: (enabled: boolean) => invoke('set_close_to_tray', { enabled }),
+
+    resetWindowPosition: () => invoke('reset_window_position'),
+
+    setAlwaysOnTopShortcut: (shortcut: string | null) => invoke('set_always_on_top_shortcut', { shortcut }),
+
+    onAlwaysOnTopChanged: (callback: (enabled: boolean) => void) =>
+        listen<boolean>('always-on-top-changed', (event) => callback(event.payload)),
+
+    setWindowOpacity: (opacity: number) => invoke<number>('set_window_opacity', { opacity }),
+
+    writeRecoverySnapshot: (data: string) => invoke('write_recovery_snapshot', { data }),
+
+    readRecoverySnapshot: () => invoke<string | null>('read_recovery_snapshot'),
+
+    clearRecoverySnapshot: () => invoke('clear_recovery_snapshot'),
+
+    verifyImageStore: (quarantine?: boolean) => invoke<ImageStoreVerifyResult>('verify_image_store', { quarantine }),
+
+    migrateDataDirectory: (newPath: string) => invoke<string>('migrate_data_directory', { newPath }),
+
+    getDataDirectory: () => invoke<DataDirectoryInfo>('get_data_directory'),
+
+    getShortcutRegistrationFailures: () => invoke<ShortcutRegistrationFailure[]>('get_shortcut_registration_failures'),
+
+    onShortcutRegistrationFailed: (callback: (failure: ShortcutRegistrationFailure) => void) =>
+        listen<ShortcutRegistrationFailure>('shortcut-registration-failed', (event) => callback(event.payload)),
+
+    setTrayTooltip: (text: string) => invoke('set_tray_tooltip', { text }),
+
+    setTrayBadge: (count: number | null) => invoke('set_tray_badge', { count }),
+
+    exportBackup: (filename: string) => invoke<string | null>('export_backup', { filename }),
+
+    importBackup: () => invoke<BackupInfo | null>('import_backup'),
+
+    computeTextStats: (data: string) => invoke<TextStats>('compute_text_stats', { data }),
+
+    findDuplicateNotes: (data: string, normalized?: boolean) =>
+        invoke<DuplicateNoteGroup[]>('find_duplicate_notes', { data, normalized }),
+
+    searchNotes: (data: string, query: string, options?: SearchOptions) =>
+        invoke<NoteSearchResult[]>('search_notes', { data, query, options }),
+
+    indexBuild: (data: string, force?: boolean) => invoke<IndexBuildResult>('index_build', { data, force }),
+
+    indexUpdate: (noteId: string, text: string) => invoke('index_update', { noteId, text }),
+
+    indexRemove: (noteId: string) => invoke('index_remove', { noteId }),
+
+    indexQuery: (query: string, limit?: number) => invoke<IndexQueryResult[]>('index_query', { query, limit }),
+
+    trashImage: (hash: string, ext: string) => invoke('trash_image', { hash, ext }),
+
+    listTrashedImages: () => invoke<TrashedImage[]>('list_trashed_images'),
+
+    restoreTrashedImage: (hash: string, ext: string) => invoke<string>('restore_trashed_image', { hash, ext }),
+
+    emptyTrash: (olderThanDays?: number) => invoke<number>('empty_trash', { olderThanDays }),
+
+    recompressImage: (hash: string, ext: string, format: 'webp' | 'jpeg' | 'png', quality?: number, trashOriginal?: boolean) =>
+        invoke<RecompressResult>('recompress_image', { hash, ext, format, quality, trashOriginal }),
+
+    getThumbnail: (hash: string, ext: string, maxDim: number) =>
+        invoke<string>('get_thumbnail', { hash, ext, maxDim }),
+
+    getImageInfo: (hash: string, ext: string) => invoke<ImageInfo>('get_image_info', { hash, ext }),
+
+    setImageLimits: (maxImageBytes: number) => invoke<number>('set_image_limits', { maxImageBytes }),
+
+    migrateToShardedLayout: () => invoke<ShardMigrationResult>('migrate_to_sharded_layout'),
+
+    getDiskSpace: (path: string) => invoke<DiskSpace>('get_disk_space', { path }),
+
+    setHashThreads: (threads: number) => invoke('set_hash_threads', { threads }),
+
+    openImageExternally: (hash: string, ext: string) => invoke('open_image_externally', { hash, ext }),
+
+    exportAll: (data: string, destPath?: string) => invoke<string | null>('export_all', { data, destPath }),
+
+    onExportProgress: (callback: (progress: TransferProgress) => void) =>
+        listen<TransferProgress>('export-progress', (event) => callback(event.payload)),
+
+    importAll: (sourcePath: string, force?: boolean) => invoke<ImportAllResult>('import_all', { sourcePath, force }),
+
+    onImportProgress: (callback: (progress: TransferProgress) => void) =>
+        listen<TransferProgress>('import-progress', (event) => callback(event.payload)),
+
+    listStoredImages: () => invoke<StoredImageList>('list_stored_images'),
+
+    confirmQuitReady: () => invoke('confirm_quit_ready'),
+
+    onAppWillQuit: (callback: () => void | Promise<void>) =>
+        listen('app-will-quit', async () => {
+            try {
+                await callback()
+            } finally {
+                await invoke('confirm_quit_ready')
+            }
+        }),
+
+    revealImageInFolder: (hash: string, ext: string) => invoke('reveal_image_in_folder', { hash, ext }),
+
+    getViewMode: () => invoke<ViewModeData | null>('get_view_mode'),
+
+    exitViewMode: () => invoke('exit_view_mode'),
+
+    onViewMode: (callback: (view: ViewModeData) => void) =>
+        listen<ViewModeData>('view-mode', (event) => callback(event.payload)),
+
+    onViewModeError: (callback: (error: string) => void) =>
+        listen<string>('view-mode-error', (event) => callback(event.payload)),
+
+    getWindowPreset: () => invoke<WindowPreset>('get_window_preset'),
+
+    applyWindowPreset: (name: WindowPreset) => invoke('apply_window_preset', { name }),
+
+    searchInBackups: (query: string) => invoke<BackupSearchResult[]>('search_in_backups', { query }),
+
+    getStagedUpdate: () => invoke<StagedUpdate | null>('get_staged_update'),
+
+    launchInstaller: (path: string, quit?: boolean) => invoke('launch_installer', { path, quit }),
+
+    setImageWatcher: (enabled: boolean) => invoke('set_image_watcher', { enabled }),
+
+    onImagesChanged: (callback: (change: ImagesChanged) => void) =>
+        listen<ImagesChanged>('images-changed', (event) => callback(event.payload)),
+
+    setPrettyJson: (enabled: boolean) => invoke('set_pretty_json', { enabled }),
+
+    getStorageReport: () => invoke<StorageReport>('get_storage_report'),
 } : undefined
 
 // For backwards compatibility, also set on window object
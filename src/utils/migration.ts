@@ -194,7 +194,11 @@ function getMimeType(ext: string): string {
         '.gif': 'image/gif',
         '.webp': 'image/webp',
         '.svg': 'image/svg+xml',
-        '.bmp': 'image/bmp'
+        '.bmp': 'image/bmp',
+        '.avif': 'image/avif',
+        '.ico': 'image/x-icon',
+        '.tif': 'image/tiff',
+        '.tiff': 'image/tiff'
     }
     return mimeTypes[ext.toLowerCase()] || 'image/png'
 }
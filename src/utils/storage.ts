@@ -240,7 +240,7 @@ export function loadData(): AppData {
 }
 
 // 保存数据（同步版本 - 更新缓存并异步写入）
-export function saveData(data: AppData): void {
+export function saveData(data: AppData): Promise<void> {
     cachedData = data
 
     // 同时写入 localStorage（兼容）和 IndexedDB
@@ -250,8 +250,8 @@ export function saveData(data: AppData): void {
         console.error('保存数据失败:', e)
     }
 
-    // 异步写入 IndexedDB
-    saveDataAsync(data).catch(e => console.error('IndexedDB 保存失败:', e))
+    // 异步写入 IndexedDB（需要等待写入完成时可 await 返回值）
+    return saveDataAsync(data).catch(e => console.error('IndexedDB 保存失败:', e))
 }
 
 // 刷新缓存（从 IndexedDB 加载最新数据）